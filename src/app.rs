@@ -0,0 +1,201 @@
+use raylib::prelude::*;
+use crate::framebuffer::Framebuffer;
+use crate::profiler::{FrameProfiler, Stage};
+
+/// Lógica por frame de una aplicación que dibuja sobre el framebuffer.
+/// `run` se encarga de la ventana, el z-buffer y la subida de la textura a GPU.
+pub trait RenderApp {
+    /// Lectura de teclado/ratón. Recibe el framebuffer del frame anterior (útil para capturas).
+    fn handle_input(&mut self, _window: &RaylibHandle, _fb: &mut Framebuffer) {}
+
+    /// Avanza la simulación `dt` segundos.
+    fn update(&mut self, dt: f32);
+
+    /// Rasteriza el frame; el framebuffer ya viene limpio.
+    fn draw(&mut self, fb: &mut Framebuffer);
+
+    /// Post-proceso sobre el color buffer ya rasterizado.
+    fn post_process(&mut self, _fb: &mut Framebuffer) {}
+
+    /// Texto/HUD dibujado por raylib encima de la imagen rasterizada.
+    fn overlay(&mut self, _d: &mut RaylibDrawHandle) {}
+
+    /// Profiler de la app, si tiene uno; `run` le agrega las etapas que mide él mismo.
+    fn profiler(&mut self) -> Option<&mut FrameProfiler> { None }
+}
+
+/// Copia el color buffer a un arreglo RGBA8 listo para `update_texture`.
+pub fn framebuffer_to_rgba(fb: &Framebuffer) -> Vec<u8> {
+    let pixels: Vec<Color> = fb.image_data();
+    let mut raw: Vec<u8> = Vec::with_capacity(pixels.len() * 4);
+    for c in pixels {
+        raw.extend_from_slice(&[c.r, c.g, c.b, c.a]);
+    }
+    raw
+}
+
+/// Ejecuta un frame completo de `app` sin ventana: update + clear + draw + post-proceso.
+/// Es el mismo orden que usa `run`, útil para renderizar offscreen.
+pub fn step<A: RenderApp>(app: &mut A, fb: &mut Framebuffer, dt: f32) {
+    app.update(dt);
+    fb.clear();
+    app.draw(fb);
+
+    let t0 = app.profiler().and_then(|p| p.start());
+    app.post_process(fb);
+    if let Some(p) = app.profiler() { p.stop(Stage::PostProcess, t0); }
+}
+
+/// Cómo se ajusta la imagen rasterizada (de tamaño fijo) cuando la ventana cambia de tamaño.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Scaling {
+    /// Llena toda la ventana aunque se deforme.
+    Stretch,
+    /// Conserva la proporción: región centrada lo más grande posible, barras negras alrededor.
+    Letterbox,
+    /// El framebuffer cambia de tamaño con la ventana (`Framebuffer::resize`): sin escalar.
+    Resize,
+}
+
+/// Rectángulo (en píxeles de ventana) donde cabe una imagen `target_w x target_h` centrada
+/// y sin deformar dentro de una ventana `window_w x window_h`.
+/// P. ej. 4:3 en una ventana 16:9 de 1920x1080 da 1440x1080 en x = 240.
+pub fn letterbox_rect(window_w: i32, window_h: i32, target_w: u32, target_h: u32) -> Rectangle {
+    let (ww, wh) = (window_w.max(1) as f32, window_h.max(1) as f32);
+    let scale = (ww / target_w.max(1) as f32).min(wh / target_h.max(1) as f32);
+    let (w, h) = (target_w as f32 * scale, target_h as f32 * scale);
+    Rectangle::new(((ww - w) / 2.0).floor(), ((wh - h) / 2.0).floor(), w, h)
+}
+
+/// Abre la ventana y corre el loop de render hasta que se cierre (con `Scaling::Letterbox`).
+pub fn run<A: RenderApp>(app: &mut A, title: &str, width: u32, height: u32, background: Color) {
+    run_with(app, title, width, height, background, Scaling::Letterbox);
+}
+
+/// Como `run`, eligiendo cómo se escala la imagen si se cambia el tamaño de la ventana.
+/// Salvo con `Scaling::Resize` el framebuffer conserva su tamaño original; sólo cambia
+/// dónde se dibuja la textura.
+pub fn run_with<A: RenderApp>(app: &mut A, title: &str, width: u32, height: u32, background: Color, scaling: Scaling) {
+    let (mut window, thread) = raylib::init()
+        .size(width as i32, height as i32)
+        .title(title)
+        .resizable()
+        .build();
+
+    let mut fb = Framebuffer::new(width, height, background);
+    window.set_target_fps(60);
+
+    while !window.window_should_close() {
+        // Minimizada la ventana mide 0: se espera a que vuelva
+        if scaling == Scaling::Resize && window.is_window_resized() {
+            let (w, h) = (window.get_screen_width(), window.get_screen_height());
+            if w > 0 && h > 0 {
+                fb.resize(w as u32, h as u32);
+            }
+        }
+
+        app.handle_input(&window, &mut fb);
+        let dt = window.get_frame_time();
+        step(app, &mut fb, dt);
+
+        // --- Render final ---
+        let t0 = app.profiler().and_then(|p| p.start());
+        let raw = framebuffer_to_rgba(&fb);
+
+        if fb.texture.is_none() {
+            fb.init_texture(&mut window, &thread);
+        }
+
+        let (out_w, out_h) = fb.display_size();
+        if let Some(tex) = &mut fb.texture {
+            tex.update_texture(&raw);
+            if let Some(p) = app.profiler() { p.stop(Stage::Upload, t0); }
+
+            let (win_w, win_h) = (window.get_screen_width(), window.get_screen_height());
+            let dest = match scaling {
+                Scaling::Stretch | Scaling::Resize => Rectangle::new(0.0, 0.0, win_w as f32, win_h as f32),
+                Scaling::Letterbox => letterbox_rect(win_w, win_h, out_w, out_h),
+            };
+            let source = Rectangle::new(0.0, 0.0, out_w as f32, out_h as f32);
+
+            let mut d = window.begin_drawing(&thread);
+            d.clear_background(Color::BLACK); // barras del letterbox
+            d.draw_texture_pro(tex, source, dest, Vector2::new(0.0, 0.0), 0.0, Color::WHITE);
+            app.overlay(&mut d);
+        }
+
+        if let Some(p) = app.profiler() { p.end_frame(); }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::triangle::{draw_filled_triangle, DebugView, RasterOptions, ShaderType};
+
+    /// App mínima que dibuja un triángulo y anota qué hooks se llamaron, en orden.
+    #[derive(Default)]
+    struct RecordingApp {
+        calls: Vec<&'static str>,
+        dt: f32,
+    }
+
+    impl RenderApp for RecordingApp {
+        fn update(&mut self, dt: f32) {
+            self.calls.push("update");
+            self.dt = dt;
+        }
+
+        fn draw(&mut self, fb: &mut Framebuffer) {
+            self.calls.push("draw");
+            // El framebuffer llega limpio: lo que haya quedado del frame anterior no se ve
+            assert!(fb.pixels().iter().all(|&c| c == Color::BLACK));
+            let options = RasterOptions { debug_view: Some(DebugView::Normals), ..RasterOptions::default() };
+            draw_filled_triangle(
+                fb,
+                Vector3::new(-1.0, -1.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(1.0, -1.0, 0.0),
+                &Camera::default(),
+                &ShaderType::Rocky,
+                0.0,
+                &options,
+            );
+        }
+
+        fn post_process(&mut self, _fb: &mut Framebuffer) {
+            self.calls.push("post_process");
+        }
+    }
+
+    #[test]
+    fn step_calls_hooks_in_order_and_draws() {
+        let mut app = RecordingApp::default();
+        let mut fb = Framebuffer::new(32, 32, Color::BLACK);
+        fb.set_pixel_with_color(0, 0, Color::RED);
+
+        step(&mut app, &mut fb, 0.25);
+
+        assert_eq!(app.calls, ["update", "draw", "post_process"]);
+        assert_eq!(app.dt, 0.25);
+        let center = fb.pixels()[(16 * 32 + 16) as usize];
+        assert_ne!(center, Color::BLACK);
+
+        step(&mut app, &mut fb, 0.25);
+        assert_eq!(app.calls.len(), 6);
+    }
+
+    #[test]
+    fn letterbox_keeps_target_aspect_centered() {
+        // 16:9 con un objetivo 4:3: barras a los lados
+        let rect = letterbox_rect(1920, 1080, 800, 600);
+        assert_eq!((rect.x, rect.y, rect.width, rect.height), (240.0, 0.0, 1440.0, 1080.0));
+        // Ventana más alta que el objetivo: barras arriba y abajo
+        let rect = letterbox_rect(800, 800, 800, 600);
+        assert_eq!((rect.x, rect.y, rect.width, rect.height), (0.0, 100.0, 800.0, 600.0));
+        // Mismo aspecto: llena la ventana
+        let rect = letterbox_rect(1600, 1200, 800, 600);
+        assert_eq!((rect.x, rect.y, rect.width, rect.height), (0.0, 0.0, 1600.0, 1200.0));
+    }
+}
@@ -0,0 +1,350 @@
+use raylib::prelude::*;
+use std::borrow::Cow;
+
+/// Ojo de `Camera::default()`, z = -3 mirando hacia +Z. El espacio de vista que espera el
+/// rasterizador es el de esa cámara: con ella `to_view` no cambia nada.
+pub const BASE_EYE: Vector3 = Vector3::new(0.0, 0.0, -3.0);
+
+/// Campo de visión por defecto (radianes, de borde a borde de la pantalla). 90° es la
+/// proyección original `1/(z+3)`: a distancia 1 del ojo, x/y en [-1, 1] llenan la pantalla.
+pub const DEFAULT_FOV: f32 = std::f32::consts::FRAC_PI_2;
+
+/// Distancias de recorte por defecto (desde el ojo, a lo largo de la vista).
+pub const DEFAULT_NEAR: f32 = 0.1;
+pub const DEFAULT_FAR: f32 = 100.0;
+
+/// Cámara en perspectiva: ojo en `position` mirando a `target`, con `up` como arriba
+/// aproximado (no puede ser paralelo a la vista). `Camera::default()` es la proyección
+/// original: ojo en `BASE_EYE` mirando al origen.
+#[derive(Copy, Clone, Debug)]
+pub struct Camera {
+    pub position: Vector3, // el ojo, en el mundo
+    pub target: Vector3,   // punto al que mira
+    pub up: Vector3,       // arriba aproximado; el real es perpendicular a la vista (`view_up`)
+    pub near: f32,  // lo más cerca que se dibuja (distancia al ojo)
+    pub far: f32,   // lo más lejos que se dibuja (distancia al ojo)
+    pub fov: f32,   // campo de visión (radianes), el mismo en X y en Y
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            position: BASE_EYE,
+            target: Vector3::new(0.0, 0.0, 0.0),
+            up: Vector3::new(0.0, 1.0, 0.0),
+            near: DEFAULT_NEAR,
+            far: DEFAULT_FAR,
+            fov: DEFAULT_FOV,
+        }
+    }
+}
+
+/// Dirección de la vista girada `yaw` alrededor de Y y `pitch` alrededor de X (positivo =
+/// hacia abajo), partiendo de +Z. Sirve para armar `target` desde ángulos.
+pub fn look_direction(yaw: f32, pitch: f32) -> Vector3 {
+    let (sp, cp) = pitch.sin_cos();
+    let (sy, cy) = yaw.sin_cos();
+    Vector3::new(cp * sy, -sp, cp * cy)
+}
+
+/// Proyección del espacio de vista (ojo en `BASE_EYE` mirando hacia +Z) a clip space, en
+/// columna mayor como las matrices de raylib: x/y se amplían `focal`, `w` es la distancia al
+/// ojo y z/w va de -1 en `near` a 1 en `far`. Dentro del frustum |x|, |y|, |z| <= w, y x/w,
+/// y/w son las NDC de la pantalla (con focal 1, la proyección original `x / (z + 3)`).
+pub fn perspective(near: f32, far: f32, focal: f32) -> Matrix {
+    // Profundidad d -> z/w = a + b / d, con -1 en near y 1 en far
+    let a = (far + near) / (far - near);
+    let b = -2.0 * far * near / (far - near);
+    Matrix {
+        m0: focal,
+        m5: focal,
+        m10: a,
+        m14: b - a * BASE_EYE.z,
+        m11: 1.0,
+        m15: -BASE_EYE.z,
+        ..Matrix::default()
+    }
+}
+
+/// Punto por una matriz de clip space (`perspective`, `Camera::view_projection`): x, y, z, w.
+pub fn to_clip(p: Vector3, m: &Matrix) -> Vector4 {
+    Vector4::new(
+        m.m0 * p.x + m.m4 * p.y + m.m8 * p.z + m.m12,
+        m.m1 * p.x + m.m5 * p.y + m.m9 * p.z + m.m13,
+        m.m2 * p.x + m.m6 * p.y + m.m10 * p.z + m.m14,
+        m.m3 * p.x + m.m7 * p.y + m.m11 * p.z + m.m15,
+    )
+}
+
+impl Camera {
+    /// `true` si es la cámara por defecto (no hace falta transformar vértices).
+    /// `near`/`far`/`fov` no cuentan: no cambian los vértices, sólo cómo se proyectan.
+    pub fn is_identity(&self) -> bool {
+        self.position == BASE_EYE
+            && self.forward() == Vector3::new(0.0, 0.0, 1.0)
+            && self.right() == Vector3::new(1.0, 0.0, 0.0)
+    }
+
+    /// `true` si un punto ya en espacio de vista queda entre `near` y `far`.
+    pub fn in_depth_range(&self, view: Vector3) -> bool {
+        let d = view.z - BASE_EYE.z;
+        d >= self.near && d <= self.far
+    }
+
+    /// Distancia focal de `fov`: cuánto se amplía x/y antes de dividir entre la distancia al
+    /// ojo (1 con `DEFAULT_FOV`).
+    pub fn focal(&self) -> f32 {
+        1.0 / (self.fov * 0.5).tan()
+    }
+
+    /// Posición en pantalla de un punto ya en espacio de vista: `projection` y la división
+    /// entre `w`, como en el rasterizador.
+    pub fn project(&self, view: Vector3, width: u32, height: u32) -> Vector2 {
+        crate::triangle::clip_to_screen(to_clip(view, &self.projection()), width as f32, height as f32)
+    }
+
+    /// Dirección (normalizada) del rayo que pasa por el centro del píxel `(x, y)`.
+    /// Inverso de `project`: a un plano a distancia `focal`, x/y en NDC coinciden con la pantalla.
+    pub fn ray_dir(&self, x: i32, y: i32, width: u32, height: u32) -> Vector3 {
+        let nx = (x as f32 + 0.5 - width as f32 / 2.0) / (width as f32 / 2.0);
+        let ny = -(y as f32 + 0.5 - height as f32 / 2.0) / (height as f32 / 2.0);
+        (self.right() * nx + self.view_up() * ny + self.forward() * self.focal()).normalized()
+    }
+
+    /// Dirección (mundo) hacia la que mira la cámara: del ojo a `target`.
+    pub fn forward(&self) -> Vector3 {
+        let d = self.target - self.position;
+        if d.length() < 1e-6 { Vector3::new(0.0, 0.0, 1.0) } else { d.normalized() }
+    }
+
+    /// Dirección (mundo) hacia la derecha de la pantalla: `up x forward`.
+    pub fn right(&self) -> Vector3 {
+        let r = self.up.cross(self.forward());
+        if r.length() < 1e-6 { Vector3::new(1.0, 0.0, 0.0) } else { r.normalized() }
+    }
+
+    /// Dirección (mundo) hacia arriba de la pantalla: `forward x right`, perpendicular a la
+    /// vista y del lado de `up`.
+    pub fn view_up(&self) -> Vector3 {
+        self.forward().cross(self.right())
+    }
+
+    /// Matriz de vista (columna mayor): lleva un punto del mundo al espacio de vista del
+    /// rasterizador, igual que `to_view`.
+    pub fn view_matrix(&self) -> Matrix {
+        let eye = self.position;
+        let (r, u, f) = (self.right(), self.view_up(), self.forward());
+        Matrix {
+            m0: r.x,
+            m4: r.y,
+            m8: r.z,
+            m12: -r.dot(eye) + BASE_EYE.x,
+            m1: u.x,
+            m5: u.y,
+            m9: u.z,
+            m13: -u.dot(eye) + BASE_EYE.y,
+            m2: f.x,
+            m6: f.y,
+            m10: f.z,
+            m14: -f.dot(eye) + BASE_EYE.z,
+            m15: 1.0,
+            ..Matrix::default()
+        }
+    }
+
+    /// Proyección de la cámara (`perspective` con su `near`/`far`/`fov`), para puntos ya en
+    /// espacio de vista.
+    pub fn projection(&self) -> Matrix {
+        perspective(self.near, self.far, self.focal())
+    }
+
+    /// Matriz vista-proyección: lleva un punto del mundo a clip space (`view_matrix` y luego
+    /// `projection`). Con la cámara por defecto es la proyección original `x / (z + 3)`.
+    pub fn view_projection(&self) -> Matrix {
+        // En raylib `a * b` aplica primero `a`
+        self.view_matrix() * self.projection()
+    }
+
+    /// Distancia del ojo al centro de una esfera de `radius` para que su silueta ocupe
+    /// `fill` (0..1) del alto y del ancho de la pantalla.
+    pub fn framing_distance(&self, radius: f32, fill: f32) -> f32 {
+        // Semiángulo de la silueta: tan(a) * focal = fill, y sin(a) = radius / distancia
+        let half_angle = (fill.clamp(0.01, 1.0) / self.focal()).atan();
+        radius.max(1e-6) / half_angle.sin()
+    }
+
+    /// Mueve el ojo (sin cambiar la orientación) para que la esfera `center`/`radius` quede
+    /// centrada y ocupe `fill` de la pantalla (ver `framing_distance`).
+    pub fn frame_sphere(&mut self, center: Vector3, radius: f32, fill: f32) {
+        self.position = center - self.forward() * self.framing_distance(radius, fill);
+        self.target = center;
+    }
+
+    /// Posición del ojo en el mundo.
+    pub fn eye(&self) -> Vector3 {
+        self.position
+    }
+
+    /// Lleva un punto del mundo al espacio que espera el rasterizador (ojo en `BASE_EYE`).
+    /// Es la inversa de la orientación usada en `ray_dir`, alrededor del ojo.
+    pub fn to_view(&self, p: Vector3) -> Vector3 {
+        self.relative_to_view(p - self.eye())
+    }
+
+    /// Como `to_view` para un punto ya relativo al ojo (`p - eye()`): sólo rota. Con
+    /// coordenadas grandes conviene restar el ojo antes (ver `relative_to_eye`) y transformar
+    /// con números chicos.
+    pub fn relative_to_view(&self, rel: Vector3) -> Vector3 {
+        self.to_view_dir(rel) + BASE_EYE
+    }
+
+    /// `p * scale - eye()` calculado en f64: con `p` lejos del origen la resta en f32
+    /// perdería los decimales que después se notan como temblor de los vértices.
+    pub fn relative_to_eye(&self, p: Vector3, scale: f32) -> Vector3 {
+        let eye = self.eye();
+        let rel = |p: f32, e: f32| (p as f64 * scale as f64 - e as f64) as f32;
+        Vector3::new(rel(p.x, eye.x), rel(p.y, eye.y), rel(p.z, eye.z))
+    }
+
+    /// Rota una dirección (normal) al espacio de vista: sólo la parte de orientación de `to_view`.
+    pub fn to_view_dir(&self, d: Vector3) -> Vector3 {
+        Vector3::new(d.dot(self.right()), d.dot(self.view_up()), d.dot(self.forward()))
+    }
+
+    /// `to_view` para un arreglo de vértices; con la cámara por defecto no copia nada.
+    pub fn view_vertices<'a>(&self, vertices: &'a [Vector3]) -> Cow<'a, [Vector3]> {
+        if self.is_identity() {
+            Cow::Borrowed(vertices)
+        } else {
+            Cow::Owned(vertices.iter().map(|&v| self.to_view(v)).collect())
+        }
+    }
+}
+
+/// Elevación máxima (en valor absoluto) de `OrbitCamera`: un poco antes del polo, donde
+/// yaw deja de tener sentido y la vista se daría vuelta.
+pub const MAX_ELEVATION: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+/// Límites de la distancia de `OrbitCamera` al objetivo.
+pub const MIN_ORBIT_DISTANCE: f32 = 0.2;
+pub const MAX_ORBIT_DISTANCE: f32 = 50.0;
+
+/// Cámara que gira alrededor de un punto: acimut (alrededor de Y), elevación (positiva =
+/// desde arriba) y distancia. `OrbitCamera::default()` da la cámara fija (ojo en `BASE_EYE`
+/// mirando al origen).
+#[derive(Copy, Clone, Debug)]
+pub struct OrbitCamera {
+    pub target: Vector3,
+    pub azimuth: f32,
+    pub elevation: f32,
+    pub distance: f32,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        Self {
+            target: Vector3::new(0.0, 0.0, 0.0),
+            azimuth: 0.0,
+            elevation: 0.0,
+            distance: -BASE_EYE.z,
+        }
+    }
+}
+
+impl OrbitCamera {
+    /// Gira alrededor del objetivo; la elevación queda dentro de ±`MAX_ELEVATION`.
+    pub fn rotate(&mut self, d_azimuth: f32, d_elevation: f32) {
+        self.azimuth += d_azimuth;
+        self.elevation = (self.elevation + d_elevation).clamp(-MAX_ELEVATION, MAX_ELEVATION);
+    }
+
+    /// Multiplica la distancia por `factor` (< 1 acerca), dentro de los límites.
+    pub fn dolly(&mut self, factor: f32) {
+        self.distance = (self.distance * factor).clamp(MIN_ORBIT_DISTANCE, MAX_ORBIT_DISTANCE);
+    }
+
+    /// Orienta y ubica `camera` (conserva `near`/`far`/`fov`).
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.position = self.target - look_direction(self.azimuth, self.elevation) * self.distance;
+        camera.target = self.target;
+        camera.up = Vector3::new(0.0, 1.0, 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn camera_relative_keeps_far_vertices_precise() {
+        // Ojo y cuerpo a 1e5 del origen, donde el paso de un f32 es ~0.008
+        let camera = Camera {
+            position: Vector3::new(100_000.0, 0.0, 99_997.0),
+            target: Vector3::new(100_001.0, 0.0, 100_000.0),
+            ..Camera::default()
+        };
+        let center = Vector3::new(100_001.0, 0.0, 100_004.0);
+        let eye = camera.eye();
+        // Diferencia exacta centro - ojo (números chicos, representables sin pérdida)
+        let exact_rel = Vector3::new(center.x - eye.x, center.y - eye.y, center.z - eye.z);
+        assert_eq!(exact_rel, Vector3::new(1.0, 0.0, 7.0));
+
+        let (mut worst_relative, mut worst_naive) = (0.0f32, 0.0f32);
+        for i in 0..50 {
+            // Vértices del modelo (ya escalados) separados por menos de un paso de f32 a 1e5
+            let t = i as f32 * 0.0013;
+            let v = Vector3::new(0.123 + t, 0.456 - t, 0.789 + t);
+            let expected = camera.relative_to_view(v + exact_rel);
+            let relative = camera.relative_to_view(v + camera.relative_to_eye(center, 1.0));
+            let naive = camera.to_view(v + center);
+            worst_relative = worst_relative.max((relative - expected).length());
+            worst_naive = worst_naive.max((naive - expected).length());
+        }
+        assert!(worst_relative < 1e-5, "relativo al ojo: error {worst_relative}");
+        // El camino ingenuo redondea al paso de f32 de las coordenadas grandes: el temblor
+        assert!(worst_naive > 1e-3, "ingenuo: error {worst_naive}");
+    }
+
+    #[test]
+    fn view_projection_matches_project_and_the_depth_range() {
+        // Cámara por defecto: w = z + 3 y x/w, y/w son la proyección original 1/(z+3)
+        let c = to_clip(Vector3::new(0.5, -0.5, 2.0), &Camera::default().view_projection());
+        assert!((c.x - 0.5).abs() < 1e-5 && (c.y + 0.5).abs() < 1e-5 && (c.w - 5.0).abs() < 1e-5, "{c:?}");
+
+        let eye = BASE_EYE + Vector3::new(0.4, -0.3, 0.5);
+        let moved = Camera { position: eye, target: eye + look_direction(0.6, -0.25), near: 0.5, far: 20.0, fov: 1.2, ..Camera::default() };
+        for camera in [Camera::default(), moved] {
+            let m = camera.view_projection();
+            for p in [Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.3, -0.2, 1.5), Vector3::new(-1.0, 0.7, -0.5)] {
+                let (view, c) = (camera.to_view(p), to_clip(p, &m));
+                assert!((c.w - (view.z - BASE_EYE.z)).abs() < 1e-4, "w {} != {}", c.w, view.z - BASE_EYE.z);
+                let from_matrix = Vector2::new(100.0 + c.x / c.w * 100.0, 50.0 - c.y / c.w * 50.0);
+                let projected = camera.project(view, 200, 100);
+                assert!((from_matrix - projected).length() < 1e-3, "{from_matrix:?} != {projected:?}");
+            }
+            let depth_at = |d: f32| {
+                let c = to_clip(camera.eye() + camera.forward() * d, &m);
+                c.z / c.w
+            };
+            assert!((depth_at(camera.near) + 1.0).abs() < 1e-4, "{}", depth_at(camera.near));
+            assert!((depth_at(camera.far) - 1.0).abs() < 1e-4, "{}", depth_at(camera.far));
+        }
+    }
+
+    #[test]
+    fn target_is_centered_and_up_is_perpendicular() {
+        let target = Vector3::new(-0.5, 0.2, 1.5);
+        let camera = Camera { position: Vector3::new(1.0, 2.0, -4.0), target, ..Camera::default() };
+        let view = camera.to_view(target);
+        assert!(view.x.abs() < 1e-4 && view.y.abs() < 1e-4, "{view:?}");
+        assert!(view.z > BASE_EYE.z);
+        // Arriba real: perpendicular a la vista y del lado de `up`
+        let up = camera.view_up();
+        assert!(up.dot(camera.forward()).abs() < 1e-5 && up.dot(camera.right()).abs() < 1e-5 && up.y > 0.0);
+        // La matriz de vista hace lo mismo que `to_view`
+        for p in [target, Vector3::zero(), Vector3::new(3.0, -1.0, 0.5)] {
+            let c = to_clip(p, &camera.view_matrix());
+            assert!((Vector3::new(c.x, c.y, c.z) - camera.to_view(p)).length() < 1e-4 && c.w == 1.0);
+        }
+    }
+}
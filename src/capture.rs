@@ -0,0 +1,188 @@
+//! Metadatos de una captura: todo lo necesario para volver a renderizar el mismo frame
+//! (p. ej. sin ventana y a más calidad). Se guardan como JSON junto al PNG, con el mismo
+//! nombre: `capture.png` -> `capture.json`.
+//!
+//! El JSON es plano (una clave por línea) y lo escribe/lee este módulo, sin dependencias.
+use raylib::prelude::*;
+use std::path::Path;
+
+use crate::camera::{look_direction, Camera, BASE_EYE, DEFAULT_FOV};
+use crate::framebuffer::ColorSpace;
+
+/// Estado de la demo al momento de una captura.
+#[derive(Clone, Debug)]
+pub struct CaptureMetadata {
+    pub frame: u32,
+    pub planet: String,
+    pub planet_index: usize,
+    pub seed: u32, // `sampling::seed_from_name(planet)`
+    pub noise_seed: u32, // `shader::noise_seed` (0 = el ruido original)
+    pub time: f32, // tiempo de los shaders
+    pub angle: f32, // rotación Y del planeta
+    pub zoom: f32,
+    pub mode: String, // `RenderMode::name`
+    pub smooth: bool,
+    pub camera: Camera,
+    pub light_direction: Vector3,
+    pub color_space: ColorSpace, // del PNG guardado
+}
+
+impl CaptureMetadata {
+    pub fn to_json(&self) -> String {
+        let vec3 = |v: Vector3| format!("[{}, {}, {}]", v.x, v.y, v.z);
+        let fields = [
+            ("frame", self.frame.to_string()),
+            ("planet", format!("{:?}", self.planet)),
+            ("planet_index", self.planet_index.to_string()),
+            ("seed", self.seed.to_string()),
+            ("noise_seed", self.noise_seed.to_string()),
+            ("time", self.time.to_string()),
+            ("angle", self.angle.to_string()),
+            ("zoom", self.zoom.to_string()),
+            ("mode", format!("{:?}", self.mode)),
+            ("smooth", self.smooth.to_string()),
+            ("camera_position", vec3(self.camera.position)),
+            ("camera_target", vec3(self.camera.target)),
+            ("camera_up", vec3(self.camera.up)),
+            ("camera_near", self.camera.near.to_string()),
+            ("camera_far", self.camera.far.to_string()),
+            ("camera_fov", self.camera.fov.to_string()),
+            ("light_direction", vec3(self.light_direction)),
+            ("color_space", format!("{:?}", self.color_space.name())),
+        ];
+        let body: Vec<String> = fields.iter().map(|(k, v)| format!("  \"{}\": {}", k, v)).collect();
+        format!("{{\n{}\n}}\n", body.join(",\n"))
+    }
+
+    /// Lee lo que escribe `to_json`. `None` si falta una clave o algún valor no se entiende.
+    pub fn from_json(text: &str) -> Option<Self> {
+        let mut values = std::collections::HashMap::new();
+        for line in text.lines() {
+            let Some((key, value)) = line.trim().trim_end_matches(',').split_once(':') else { continue };
+            values.insert(key.trim().trim_matches('"'), value.trim());
+        }
+        let get = |k: &str| values.get(k).copied();
+        let num = |k: &str| get(k)?.parse::<f32>().ok();
+        let string = |k: &str| Some(get(k)?.trim_matches('"').to_string());
+        let vec3 = |k: &str| {
+            let parts: Vec<f32> = get(k)?
+                .trim_matches(|c| c == '[' || c == ']')
+                .split(',')
+                .map(|p| p.trim().parse().ok())
+                .collect::<Option<_>>()?;
+            match parts[..] {
+                [x, y, z] => Some(Vector3::new(x, y, z)),
+                _ => None,
+            }
+        };
+
+        // Capturas anteriores a `target`: el ojo relativo a `BASE_EYE` y yaw/pitch
+        let (position, target, up) = match vec3("camera_target") {
+            Some(target) => (vec3("camera_position")?, target, vec3("camera_up")?),
+            None => {
+                let position = BASE_EYE + vec3("camera_position")?;
+                let target = position + look_direction(num("camera_yaw")?, num("camera_pitch")?);
+                (position, target, Vector3::new(0.0, 1.0, 0.0))
+            }
+        };
+
+        Some(Self {
+            frame: get("frame")?.parse().ok()?,
+            planet: string("planet")?,
+            planet_index: get("planet_index")?.parse().ok()?,
+            seed: get("seed")?.parse().ok()?,
+            // Capturas anteriores a la semilla del ruido: la original
+            noise_seed: get("noise_seed").map_or(Some(0), |v| v.parse().ok())?,
+            time: num("time")?,
+            angle: num("angle")?,
+            zoom: num("zoom")?,
+            mode: string("mode")?,
+            smooth: get("smooth")?.parse().ok()?,
+            camera: Camera {
+                position,
+                target,
+                up,
+                near: num("camera_near")?,
+                far: num("camera_far")?,
+                // Capturas anteriores al fov configurable: la proyección original
+                fov: num("camera_fov").unwrap_or(DEFAULT_FOV),
+            },
+            light_direction: vec3("light_direction")?,
+            // Capturas anteriores a poder elegirlo: siempre sRGB
+            color_space: match string("color_space") {
+                Some(name) => ColorSpace::from_name(&name)?,
+                None => ColorSpace::Srgb,
+            },
+        })
+    }
+
+    /// Escribe el JSON junto a `image_path` (misma ruta con extensión `.json`).
+    pub fn save_beside(&self, image_path: &str) -> std::io::Result<()> {
+        std::fs::write(Path::new(image_path).with_extension("json"), self.to_json())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> CaptureMetadata {
+        CaptureMetadata {
+            frame: 42,
+            planet: "Kepler-22b".to_string(),
+            planet_index: 3,
+            seed: 0xDEAD_BEEF,
+            noise_seed: 7,
+            time: 12.345_678,
+            angle: -0.1,
+            zoom: 1.5,
+            mode: "Wire+Shaded".to_string(),
+            smooth: true,
+            camera: Camera {
+                position: Vector3::new(0.25, -1.0, 3.5),
+                target: Vector3::new(1.0, -0.5, 4.0),
+                up: Vector3::new(0.1, 1.0, 0.0),
+                near: 0.05,
+                far: 250.0,
+                fov: 1.1,
+            },
+            light_direction: Vector3::new(0.6, 0.8, 0.0),
+            color_space: ColorSpace::Linear,
+        }
+    }
+
+    #[test]
+    fn metadata_round_trips_through_json() {
+        let meta = sample();
+        let back = CaptureMetadata::from_json(&meta.to_json()).expect("JSON propio legible");
+        assert_eq!((back.frame, back.planet.as_str(), back.planet_index), (42, "Kepler-22b", 3));
+        assert_eq!((back.seed, back.noise_seed), (meta.seed, meta.noise_seed));
+        assert_eq!((back.time, back.angle, back.zoom), (meta.time, meta.angle, meta.zoom));
+        assert_eq!((back.mode.as_str(), back.smooth), ("Wire+Shaded", true));
+        let (a, b) = (back.camera, meta.camera);
+        assert_eq!((a.position, a.target, a.up), (b.position, b.target, b.up));
+        assert_eq!((a.near, a.far, a.fov), (b.near, b.far, b.fov));
+        assert_eq!(back.light_direction, meta.light_direction);
+        assert_eq!(back.color_space, ColorSpace::Linear);
+    }
+
+    #[test]
+    fn older_captures_use_defaults_and_missing_keys_fail() {
+        let json = sample().to_json();
+        let without = |text: &str, key: &str| {
+            text.lines().filter(|l| !l.contains(&format!("\"{key}\""))).collect::<Vec<_>>().join("\n")
+        };
+        let old = without(&without(&without(&json, "noise_seed"), "camera_fov"), "color_space");
+        let back = CaptureMetadata::from_json(&old).expect("captura vieja");
+        assert_eq!((back.noise_seed, back.camera.fov, back.color_space), (0, DEFAULT_FOV, ColorSpace::Srgb));
+        assert!(CaptureMetadata::from_json(&without(&json, "seed")).is_none());
+
+        // Cámara de antes de `target`: el ojo relativo a `BASE_EYE` y yaw/pitch
+        let no_target = without(&without(&json, "camera_target"), "camera_up");
+        assert!(CaptureMetadata::from_json(&no_target).is_none());
+        let angles = no_target.replacen("{\n", "{\n  \"camera_yaw\": 0,\n  \"camera_pitch\": 0,\n", 1);
+        let camera = CaptureMetadata::from_json(&angles).expect("cámara con ángulos").camera;
+        assert_eq!(camera.position, BASE_EYE + Vector3::new(0.25, -1.0, 3.5));
+        assert_eq!(camera.forward(), Vector3::new(0.0, 0.0, 1.0));
+    }
+}
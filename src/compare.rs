@@ -0,0 +1,80 @@
+//! Comparación A/B: la misma escena con dos variantes (otro shader u otros parámetros),
+//! la A a la izquierda de un divisor vertical móvil y la B a la derecha. A diferencia del
+//! estéreo, cada variante se dibuja a pantalla completa y sólo se recorta al combinar.
+use raylib::prelude::*;
+use crate::framebuffer::Framebuffer;
+
+/// Qué variante se está dibujando.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Variant {
+    A,
+    B,
+}
+
+/// Color de la línea del divisor.
+pub const DIVIDER_COLOR: Color = Color::new(255, 255, 255, 255);
+
+/// Dibuja `scene` una vez por variante y deja en `fb` las columnas `x < divider` de la A y
+/// el resto de la B, con una línea de 1 px en el divisor. `divider` se limita al ancho.
+pub fn render_split<F>(fb: &mut Framebuffer, mut scene: F, divider: u32)
+where
+    F: FnMut(&mut Framebuffer, Variant),
+{
+    let divider = divider.min(fb.width);
+    let mut b = Framebuffer::new(fb.width, fb.height, fb.background_color);
+
+    scene(fb, Variant::A);
+    scene(&mut b, Variant::B);
+    fb.copy_columns_from(&b, divider);
+
+    if divider < fb.width {
+        for y in 0..fb.height as i32 {
+            fb.set_pixel_with_color(divider as i32, y, DIVIDER_COLOR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::shader::lock_uniforms;
+    use crate::triangle::{draw_filled_triangle, RasterOptions, ShaderType};
+
+    /// Un triángulo de frente que cubre toda la pantalla, con el shader de la variante.
+    fn draw(fb: &mut Framebuffer, shader: ShaderType) {
+        let (a, b, c) = (Vector3::new(-10.0, -10.0, 0.0), Vector3::new(0.0, 10.0, 0.0), Vector3::new(10.0, -10.0, 0.0));
+        draw_filled_triangle(fb, a, b, c, &Camera::default(), &shader, 0.0, &RasterOptions::default());
+    }
+
+    #[test]
+    fn left_of_divider_is_shader_a_and_right_is_shader_b() {
+        let _uniforms = lock_uniforms();
+        let shader = |v: Variant| if v == Variant::A { ShaderType::Rocky } else { ShaderType::Gas };
+        let reference = |v: Variant| {
+            let mut fb = Framebuffer::new(32, 32, Color::BLACK);
+            draw(&mut fb, shader(v));
+            fb.pixels()
+        };
+        let (only_a, only_b) = (reference(Variant::A), reference(Variant::B));
+
+        let mut fb = Framebuffer::new(32, 32, Color::BLACK);
+        render_split(&mut fb, |fb, v| draw(fb, shader(v)), 12);
+        let split = fb.pixels();
+
+        let (left, right) = (16 * 32 + 5, 16 * 32 + 25);
+        assert_ne!(only_a[left], only_b[left]);
+        assert_ne!(only_a[right], only_b[right]);
+        for y in 0..32 {
+            for x in 0..32 {
+                let i = y * 32 + x;
+                let want = match x {
+                    0..12 => only_a[i],
+                    12 => DIVIDER_COLOR,
+                    _ => only_b[i],
+                };
+                assert_eq!(split[i], want, "({x}, {y})");
+            }
+        }
+    }
+}
@@ -1,6 +1,144 @@
 use raylib::prelude::*;
+use crate::camera::BASE_EYE;
+
+/// Amplitud (en niveles de 0..255) del umbral de Bayer de `quantize_to_palette`.
+pub const DITHER_SPREAD: f32 = 48.0;
+
+/// Paleta de 8 colores "espacial" para `quantize_to_palette`: negros azulados, tierra,
+/// naranja de lava, azules de gas/hielo y blanco.
+pub const SPACE_PALETTE: [Color; 8] = [
+    Color::new(8, 8, 20, 255),
+    Color::new(40, 36, 72, 255),
+    Color::new(110, 60, 40, 255),
+    Color::new(220, 110, 40, 255),
+    Color::new(250, 210, 120, 255),
+    Color::new(60, 110, 190, 255),
+    Color::new(150, 200, 240, 255),
+    Color::new(245, 245, 250, 255),
+];
+
+/// Ancho y alto en celdas de los glifos de `draw_text`; entre letras queda una columna libre.
+pub const GLYPH_W: i32 = 5;
+pub const GLYPH_H: i32 = 7;
+
+/// Fuente de mapa de bits 5x7: una fila por byte, el bit 4 es la columna izquierda.
+/// Solo mayúsculas, dígitos y la puntuación del HUD; el resto se dibuja como espacio.
+const FONT_5X7: [(char, [u8; 7]); 51] = [
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    ('A', [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('B', [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
+    ('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
+    ('D', [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C]),
+    ('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
+    ('F', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+    ('G', [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F]),
+    ('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('I', [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F]),
+    ('M', [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('P', [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
+    ('Q', [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D]),
+    ('R', [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
+    ('S', [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E]),
+    ('T', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A]),
+    ('X', [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04]),
+    ('Z', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08]),
+    (':', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00]),
+    (';', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x04, 0x08]),
+    ('-', [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00]),
+    ('+', [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00]),
+    ('=', [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00]),
+    ('(', [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02]),
+    (')', [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08]),
+    ('/', [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]),
+    ('|', [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('%', [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03]),
+    ('!', [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04]),
+    ('?', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+    ('_', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F]),
+];
+
+/// Filas del glifo de `c`: minúsculas y vocales con tilde usan la mayúscula sin tilde.
+fn glyph(c: char) -> Option<&'static [u8; 7]> {
+    let c = match c {
+        'á' | 'Á' => 'A',
+        'é' | 'É' => 'E',
+        'í' | 'Í' => 'I',
+        'ó' | 'Ó' => 'O',
+        'ú' | 'Ú' | 'ü' | 'Ü' => 'U',
+        'ñ' | 'Ñ' => 'N',
+        c => c.to_ascii_uppercase(),
+    };
+    FONT_5X7.iter().find(|(g, _)| *g == c).map(|(_, rows)| rows)
+}
+
+/// Espacio de color de las imágenes exportadas. El buffer de 8 bits ya está codificado para
+/// pantalla (lo que decodifica `postprocess::color_to_linear`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum ColorSpace {
+    /// Tal cual se ve en pantalla (codificado con gamma).
+    #[default]
+    Srgb,
+    /// Valores lineales en 8 bits, para componer en un pipeline lineal.
+    Linear,
+}
+
+impl ColorSpace {
+    /// Convierte un color del buffer (codificado) a este espacio; el alfa no cambia.
+    pub fn encode(self, c: Color) -> Color {
+        match self {
+            ColorSpace::Srgb => c,
+            ColorSpace::Linear => {
+                let l = crate::postprocess::color_to_linear(c);
+                let f = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+                Color::new(f(l.x), f(l.y), f(l.z), c.a)
+            }
+        }
+    }
+
+    /// Nombre en los metadatos de captura.
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorSpace::Srgb => "srgb",
+            ColorSpace::Linear => "linear",
+        }
+    }
+
+    /// Inverso de `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "srgb" => Some(ColorSpace::Srgb),
+            "linear" => Some(ColorSpace::Linear),
+            _ => None,
+        }
+    }
+}
+
+/// Factor máximo de supersampling por eje (4 = 16 muestras por píxel: 16x la memoria).
+pub const MAX_SUPERSAMPLE: u32 = 4;
 
 /// Framebuffer simple con z-buffer y textura GPU opcional.
+/// Con supersampling (`with_supersample`) `width`/`height` son la resolución interna, en la
+/// que se rasteriza todo; `image_data`/`render_to_file` la reducen a `display_size`.
 /// Ahora `texture` es pública para que `main`/UI pueda actualizarla.
 pub struct Framebuffer {
     pub width: u32,
@@ -10,6 +148,18 @@ pub struct Framebuffer {
     pub background_color: Color,
     pub current_color: Color,
     pub texture: Option<Texture2D>, // pública para acceso desde main
+    /// Buffer HDR lineal opcional (RGB sin límite superior, 1.0 = blanco de pantalla).
+    /// Si existe, los post-procesos que miden brillo (bloom) lo leen en vez del color de 8 bits.
+    pub hdr_buffer: Option<Vec<Vector3>>,
+    /// Muestras por eje de cada píxel de salida (1 = sin SSAA).
+    pub supersample: u32,
+}
+
+/// `src` mezclado sobre `dst` según el alfa de `src`; el resultado queda opaco.
+pub(crate) fn blend_over(src: Color, dst: Color) -> Color {
+    let a = src.a as f32 / 255.0;
+    let mix = |s: u8, d: u8| (s as f32 * a + d as f32 * (1.0 - a)).round() as u8;
+    Color::new(mix(src.r, dst.r), mix(src.g, dst.g), mix(src.b, dst.b), 255)
 }
 
 impl Framebuffer {
@@ -25,13 +175,101 @@ impl Framebuffer {
             background_color,
             current_color: Color::WHITE,
             texture: None,
+            hdr_buffer: None,
+            supersample: 1,
+        }
+    }
+
+    /// Framebuffer de `width x height` píxeles de salida que rasteriza a `factor` veces esa
+    /// resolución por eje (SSAA); `factor` se limita a `1..=MAX_SUPERSAMPLE`.
+    pub fn with_supersample(width: u32, height: u32, background_color: Color, factor: u32) -> Self {
+        let factor = factor.clamp(1, MAX_SUPERSAMPLE);
+        Self { supersample: factor, ..Self::new(width * factor, height * factor, background_color) }
+    }
+
+    /// Tamaño de la imagen de salida (la resolución interna dividida por `supersample`).
+    pub fn display_size(&self) -> (u32, u32) {
+        (self.width / self.supersample, self.height / self.supersample)
+    }
+
+    /// Cambia el factor de supersampling conservando el tamaño de salida (los buffers se
+    /// recrean vacíos; la textura, que mide lo mismo, se conserva).
+    pub fn set_supersample(&mut self, factor: u32) {
+        let factor = factor.clamp(1, MAX_SUPERSAMPLE);
+        if factor == self.supersample {
+            return;
         }
+        let (w, h) = self.display_size();
+        self.supersample = factor;
+        self.reallocate(w * factor, h * factor);
     }
 
-    /// Limpia color y Z-buffer
+    /// Adopta una imagen existente como color buffer (sin GPU), con z-buffer vacío del mismo
+    /// tamaño. Útil para probar post-procesos sobre una imagen conocida.
+    pub fn from_image(mut img: Image) -> Self {
+        // El mismo formato (RGBA de 8 bits) que el color buffer de `new`
+        img.set_format(PixelFormat::PIXELFORMAT_UNCOMPRESSED_R8G8B8A8);
+        let (width, height) = (img.width().max(0) as u32, img.height().max(0) as u32);
+        Self {
+            width,
+            height,
+            color_buffer: img,
+            z_buffer: vec![f32::INFINITY; (width * height) as usize],
+            background_color: Color::BLACK,
+            current_color: Color::WHITE,
+            texture: None,
+            hdr_buffer: None,
+            supersample: 1,
+        }
+    }
+
+    /// Cambia el tamaño de salida (con supersampling la resolución interna es `supersample`
+    /// veces eso): color, Z-buffer y (si existe) el buffer HDR se recrean vacíos y la
+    /// textura se descarta para que se vuelva a crear con el tamaño nuevo. El color de fondo
+    /// se conserva.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if (width, height) == self.display_size() {
+            return;
+        }
+        self.reallocate(width * self.supersample, height * self.supersample);
+        self.texture = None;
+    }
+
+    /// Recrea vacíos los buffers por píxel con la resolución interna `width x height`.
+    fn reallocate(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.color_buffer = Image::gen_image_color(width as i32, height as i32, self.background_color);
+        self.z_buffer = vec![f32::INFINITY; (width * height) as usize];
+        if self.hdr_buffer.is_some() {
+            self.hdr_buffer = Some(vec![Vector3::new(0.0, 0.0, 0.0); (width * height) as usize]);
+        }
+    }
+
+    /// Limpia color, Z-buffer y (si existe) el buffer HDR
     pub fn clear(&mut self) {
         self.color_buffer.clear_background(self.background_color);
         self.z_buffer.fill(f32::INFINITY);
+        if let Some(hdr) = &mut self.hdr_buffer {
+            hdr.fill(Vector3::new(0.0, 0.0, 0.0));
+        }
+    }
+
+    /// Crea (una vez) el buffer HDR lineal.
+    pub fn enable_hdr(&mut self) {
+        if self.hdr_buffer.is_none() {
+            self.hdr_buffer = Some(vec![Vector3::new(0.0, 0.0, 0.0); (self.width * self.height) as usize]);
+        }
+    }
+
+    /// Escribe un valor HDR lineal (no hace nada si no hay buffer HDR).
+    pub fn set_pixel_hdr(&mut self, x: i32, y: i32, value: Vector3) {
+        if x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 {
+            let idx = (y as u32 * self.width + x as u32) as usize;
+            if let Some(hdr) = &mut self.hdr_buffer {
+                hdr[idx] = value;
+            }
+        }
     }
 
     pub fn set_current_color(&mut self, color: Color) {
@@ -49,13 +287,46 @@ impl Framebuffer {
         }
     }
 
-    /// Dibuja un píxel con color explícito
+    /// Dibuja un píxel con color explícito (lo reemplaza tal cual, alfa incluido).
     pub fn set_pixel_with_color(&mut self, x: i32, y: i32, color: Color) {
         if x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 {
             self.color_buffer.draw_pixel(x, y, color);
         }
     }
 
+    /// Como `set_pixel_with_color`, pero con alfa < 255 mezcla `color` sobre el que ya hay
+    /// (el resultado queda opaco). Es lo que usan los fragmentos translúcidos.
+    pub fn blend_pixel(&mut self, x: i32, y: i32, color: Color) {
+        if color.a == 255 || self.index(x, y).is_none() {
+            self.set_pixel_with_color(x, y, color);
+            return;
+        }
+        let dst = self.color_buffer.get_color(x, y);
+        self.color_buffer.draw_pixel(x, y, blend_over(color, dst));
+    }
+
+    /// Color en `(x, y)`; `None` fuera de la pantalla. Lee sólo ese píxel con
+    /// `Image::get_color`, que pide `&mut` aunque no modifica la imagen.
+    pub fn get_pixel(&mut self, x: i32, y: i32) -> Option<Color> {
+        self.index(x, y)?;
+        // `width`, `height` y `color_buffer` son públicos y pueden no coincidir
+        if x >= self.color_buffer.width() || y >= self.color_buffer.height() {
+            return None;
+        }
+        Some(self.color_buffer.get_color(x, y))
+    }
+
+    /// Profundidad guardada en `(x, y)` (infinito = nada dibujado); `None` fuera de la pantalla.
+    pub fn depth_at(&self, x: i32, y: i32) -> Option<f32> {
+        self.z_buffer.get(self.index(x, y)?).copied()
+    }
+
+    /// Índice de `(x, y)` en los buffers por píxel, si está dentro de la pantalla.
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        (x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32)
+            .then(|| (y as u32 * self.width + x as u32) as usize)
+    }
+
     /// Dibuja un píxel controlando profundidad.
     pub fn set_pixel_depth(&mut self, x: i32, y: i32, depth: f32) {
         if x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 {
@@ -67,24 +338,486 @@ impl Framebuffer {
         }
     }
 
-    /// Inicializa la textura GPU desde la imagen (una sola vez).
+    /// Normales en espacio de vista reconstruidas del z-buffer por diferencias finitas
+    /// (misma convención que el rasterizador: una cara hacia la cámara tiene z < 0).
+    /// En cada eje se usa el vecino con menor salto de profundidad, y saltos mayores a
+    /// `MAX_DEPTH_STEP` (siluetas) se ignoran, así los bordes no dan normales disparatadas.
+    /// Los píxeles de fondo (z infinito) quedan en cero. `focal` es la de la cámara con que
+    /// se rasterizó (`Camera::focal`).
+    pub fn depth_to_screen_normals(&self, focal: f32) -> Vec<Vector3> {
+        const MAX_DEPTH_STEP: f32 = 0.25;
+        let (w, h) = (self.width as i32, self.height as i32);
+        let depth_at = |x: i32, y: i32| -> Option<f32> {
+            if x < 0 || y < 0 || x >= w || y >= h {
+                return None;
+            }
+            let z = self.z_buffer[(y * w + x) as usize];
+            z.is_finite().then_some(z)
+        };
+        // Derivada en un eje: la diferencia más suave entre el lado negativo y el positivo
+        let slope = |z: f32, back: Option<f32>, fwd: Option<f32>| -> f32 {
+            let d_back = back.map(|b| z - b).filter(|d| d.abs() <= MAX_DEPTH_STEP);
+            let d_fwd = fwd.map(|f| f - z).filter(|d| d.abs() <= MAX_DEPTH_STEP);
+            match (d_back, d_fwd) {
+                (Some(a), Some(b)) => if a.abs() < b.abs() { a } else { b },
+                (Some(a), None) | (None, Some(a)) => a,
+                (None, None) => 0.0,
+            }
+        };
+
+        let mut normals = vec![Vector3::new(0.0, 0.0, 0.0); self.z_buffer.len()];
+        for y in 0..h {
+            for x in 0..w {
+                let Some(z) = depth_at(x, y) else { continue };
+                // Tamaño de un píxel en unidades de mundo a esa profundidad (inverso de `project`)
+                let px_w = 2.0 * (z - BASE_EYE.z) / (focal * w as f32);
+                let px_h = 2.0 * (z - BASE_EYE.z) / (focal * h as f32);
+                let dzdx = slope(z, depth_at(x - 1, y), depth_at(x + 1, y)) / px_w;
+                // Y de pantalla crece hacia abajo, la del mundo hacia arriba
+                let dzdy = -slope(z, depth_at(x, y - 1), depth_at(x, y + 1)) / px_h;
+                normals[(y * w + x) as usize] = Vector3::new(dzdx, dzdy, -1.0).normalized();
+            }
+        }
+        normals
+    }
+
+    /// Oclusión ambiental en espacio de pantalla: por cada píxel con geometría toma vecinos en
+    /// un anillo de `radius` píxeles, los reconstruye en espacio de vista y cuenta cuánto
+    /// tapan el hemisferio de la normal (de `depth_to_screen_normals`). Oscurece el color hasta
+    /// `intensity` (0..1). `focal` es la de la cámara (como en `depth_to_screen_normals`).
+    /// Es caro: pensado para capturas, no para cada frame.
+    pub fn apply_ssao(&mut self, radius: f32, intensity: f32, focal: f32) {
+        const DIRECTIONS: usize = 8;
+        const BIAS: f32 = 0.05;
+        let (w, h) = (self.width as i32, self.height as i32);
+        let normals = self.depth_to_screen_normals(focal);
+        let pixels = self.pixels();
+
+        // Punto de vista (inverso de `project`) del centro del píxel (x, y) a profundidad z
+        let unproject = |x: i32, y: i32, z: f32| {
+            let nx = (x as f32 + 0.5 - w as f32 / 2.0) / (w as f32 / 2.0);
+            let ny = -(y as f32 + 0.5 - h as f32 / 2.0) / (h as f32 / 2.0);
+            let d = (z - BASE_EYE.z) / focal;
+            Vector3::new(nx * d, ny * d, z)
+        };
+
+        for y in 0..h {
+            for x in 0..w {
+                let idx = (y * w + x) as usize;
+                let z = self.z_buffer[idx];
+                if !z.is_finite() {
+                    continue;
+                }
+                let p = unproject(x, y, z);
+                let n = normals[idx];
+                // Alcance en mundo equivalente a `radius` píxeles a esta profundidad
+                let range = radius * 2.0 * (z - BASE_EYE.z) / (focal * w as f32);
+
+                let mut occlusion = 0.0;
+                let mut samples = 0;
+                for k in 0..DIRECTIONS * 2 {
+                    // dos anillos (radio completo y mitad), girados medio paso entre sí
+                    let ring = if k < DIRECTIONS { 1.0 } else { 0.5 };
+                    let a = (k as f32 + ring * 0.5) * std::f32::consts::TAU / DIRECTIONS as f32;
+                    let sx = x + (a.cos() * radius * ring).round() as i32;
+                    let sy = y + (a.sin() * radius * ring).round() as i32;
+                    if sx < 0 || sy < 0 || sx >= w || sy >= h {
+                        continue;
+                    }
+                    samples += 1;
+                    let sz = self.z_buffer[(sy * w + sx) as usize];
+                    if !sz.is_finite() {
+                        continue;
+                    }
+                    let v = unproject(sx, sy, sz) - p;
+                    let dist = v.length();
+                    if dist < 1e-6 {
+                        continue;
+                    }
+                    // Tapa si está del lado de la normal; pesa menos mientras más lejos está
+                    let facing = (n.dot(v / dist) - BIAS).max(0.0);
+                    occlusion += facing / (1.0 + (dist / range).powi(2));
+                }
+                if samples == 0 || occlusion <= 0.0 {
+                    continue;
+                }
+
+                let shade = 1.0 - intensity.clamp(0.0, 1.0) * (occlusion / samples as f32).min(1.0);
+                let c = pixels[idx];
+                let dim = |v: u8| (v as f32 * shade) as u8;
+                self.set_pixel_with_color(x, y, Color::new(dim(c.r), dim(c.g), dim(c.b), c.a));
+            }
+        }
+    }
+
+    /// Copia sólo el color de una imagen suelta (`pixels`, filas de `width`) con su esquina
+    /// superior izquierda en `(x0, y0)`; lo que cae fuera se descarta. La profundidad no cambia.
+    pub fn blit_pixels(&mut self, pixels: &[Color], width: u32, x0: i32, y0: i32) {
+        let width = width.max(1);
+        for (i, &color) in pixels.iter().enumerate() {
+            let (dx, dy) = (x0 + (i as u32 % width) as i32, y0 + (i as u32 / width) as i32);
+            if dx >= 0 && dy >= 0 && dx < self.width as i32 && dy < self.height as i32 {
+                self.color_buffer.draw_pixel(dx, dy, color);
+            }
+        }
+    }
+
+    /// Copia color y profundidad de `src` con su esquina superior izquierda en `(x0, y0)`.
+    pub fn blit_from(&mut self, src: &Framebuffer, x0: i32, y0: i32) {
+        let pixels = src.pixels();
+        for y in 0..src.height as i32 {
+            for x in 0..src.width as i32 {
+                let (dx, dy) = (x0 + x, y0 + y);
+                if dx < 0 || dy < 0 || dx >= self.width as i32 || dy >= self.height as i32 {
+                    continue;
+                }
+                let si = (y as u32 * src.width + x as u32) as usize;
+                let di = (dy as u32 * self.width + dx as u32) as usize;
+                self.color_buffer.draw_pixel(dx, dy, pixels[si]);
+                self.z_buffer[di] = src.z_buffer[si];
+            }
+        }
+    }
+
+    /// Escribe `text` con la fuente 5x7 integrada (sin depender de la ventana ni de raylib),
+    /// cada celda del glifo como un cuadrado de `scale` píxeles. `(x, y)` es la esquina superior
+    /// izquierda; `\n` baja una línea. Lo que cae fuera de la pantalla se recorta.
+    pub fn draw_text(&mut self, text: &str, x: i32, y: i32, scale: i32, color: Color) {
+        let scale = scale.max(1);
+        let (mut cx, mut cy) = (x, y);
+        for c in text.chars() {
+            if c == '\n' {
+                cx = x;
+                cy += (GLYPH_H + 2) * scale;
+                continue;
+            }
+            if let Some(rows) = glyph(c) {
+                for (row, bits) in rows.iter().enumerate() {
+                    for col in 0..GLYPH_W {
+                        if bits & (0x10 >> col) == 0 {
+                            continue;
+                        }
+                        for sy in 0..scale {
+                            for sx in 0..scale {
+                                self.set_pixel_with_color(cx + col * scale + sx, cy + row as i32 * scale + sy, color);
+                            }
+                        }
+                    }
+                }
+            }
+            cx += (GLYPH_W + 1) * scale;
+        }
+    }
+
+    /// Lleva cada píxel al color más cercano de `palette` (distancia RGB al cuadrado; en
+    /// empate gana el primero). Con `dither` se suma antes un umbral de Bayer 4x4 (±`DITHER_SPREAD`/2
+    /// por canal), así los degradados alternan entre colores vecinos en vez de hacer bandas.
+    /// Es determinista: el mismo frame da siempre el mismo resultado. Paleta vacía = no hace nada.
+    pub fn quantize_to_palette(&mut self, palette: &[Color], dither: bool) {
+        const BAYER: [[f32; 4]; 4] = [
+            [0.0, 8.0, 2.0, 10.0],
+            [12.0, 4.0, 14.0, 6.0],
+            [3.0, 11.0, 1.0, 9.0],
+            [15.0, 7.0, 13.0, 5.0],
+        ];
+        if palette.is_empty() { return; }
+        let pixels = self.pixels();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let c = pixels[(y * self.width + x) as usize];
+                let offset = if dither {
+                    ((BAYER[(y % 4) as usize][(x % 4) as usize] + 0.5) / 16.0 - 0.5) * DITHER_SPREAD
+                } else {
+                    0.0
+                };
+                let (r, g, b) = (c.r as f32 + offset, c.g as f32 + offset, c.b as f32 + offset);
+                let dist = |p: &Color| {
+                    let (dr, dg, db) = (p.r as f32 - r, p.g as f32 - g, p.b as f32 - b);
+                    dr * dr + dg * dg + db * db
+                };
+                let nearest = palette
+                    .iter()
+                    .fold((palette[0], f32::MAX), |best, p| {
+                        let d = dist(p);
+                        if d < best.1 { (*p, d) } else { best }
+                    })
+                    .0;
+                self.color_buffer.draw_pixel(x as i32, y as i32, Color::new(nearest.r, nearest.g, nearest.b, c.a));
+            }
+        }
+    }
+
+    /// Copia color y profundidad de las columnas `x >= x0` de `src` (mismo tamaño) sobre las
+    /// propias. Es lo que arma la pantalla partida de `compare`.
+    pub fn copy_columns_from(&mut self, src: &Framebuffer, x0: u32) {
+        let pixels = src.pixels();
+        let w = self.width.min(src.width);
+        for y in 0..self.height.min(src.height) {
+            for x in x0.min(w)..w {
+                let si = (y * src.width + x) as usize;
+                let di = (y * self.width + x) as usize;
+                self.color_buffer.draw_pixel(x as i32, y as i32, pixels[si]);
+                self.z_buffer[di] = src.z_buffer[si];
+            }
+        }
+    }
+
+    /// Anaglifo rojo/cian: el canal rojo sale del ojo izquierdo y verde+azul del derecho.
+    /// Ambas imágenes deben medir lo mismo (se usa el tamaño de `left`).
+    pub fn composite_anaglyph(left: &Image, right: &Image) -> Image {
+        let (w, h) = (left.width(), left.height());
+        let mut out = Image::gen_image_color(w, h, Color::BLACK);
+        let l = left.get_image_data();
+        let r = right.get_image_data();
+        for y in 0..h {
+            for x in 0..w {
+                let i = (y * w + x) as usize;
+                let (lc, rc) = (l[i], r.get(i).copied().unwrap_or(Color::BLACK));
+                out.draw_pixel(x, y, Color::new(lc.r, rc.g, rc.b, 255));
+            }
+        }
+        out
+    }
+
+    /// Inicializa la textura GPU (del tamaño de salida) desde la imagen (una sola vez).
     /// Si ya existe, no la vuelve a crear.
     pub fn init_texture(&mut self, window: &mut RaylibHandle, thread: &RaylibThread) {
         if self.texture.is_none() {
-            if let Ok(tex) = window.load_texture_from_image(thread, &self.color_buffer) {
+            let (w, h) = self.display_size();
+            let image = Image::gen_image_color(w as i32, h as i32, self.background_color);
+            if let Ok(tex) = window.load_texture_from_image(thread, &image) {
                 self.texture = Some(tex);
             }
         }
     }
 
-    /// Actualiza la textura existente con los datos actuales del color buffer.
-    /// Usa el método de Texture2D update (separado en main).
-    pub fn image_data(&self) -> Vec<Color> {
+    /// Color buffer a la resolución interna (lo que escribió el rasterizador).
+    pub fn pixels(&self) -> Vec<Color> {
         self.color_buffer.get_image_data().to_vec()
     }
 
-    /// Exporta a archivo (para capturas)
-    pub fn render_to_file(&self, path: &str) {
-        self.color_buffer.export_image(path);
+    /// Imagen de salida (`display_size`): con supersampling, el promedio de cada bloque de
+    /// `supersample x supersample` muestras. Es lo que se sube a la textura.
+    pub fn image_data(&self) -> Vec<Color> {
+        let pixels = self.pixels();
+        let f = self.supersample;
+        if f <= 1 {
+            return pixels;
+        }
+        let (w, h) = self.display_size();
+        let n = (f * f) as f32;
+        let mut out = Vec::with_capacity((w * h) as usize);
+        for y in 0..h {
+            for x in 0..w {
+                let mut sum = [0.0f32; 4];
+                for sy in 0..f {
+                    for sx in 0..f {
+                        let c = pixels[((y * f + sy) * self.width + x * f + sx) as usize];
+                        sum[0] += c.r as f32;
+                        sum[1] += c.g as f32;
+                        sum[2] += c.b as f32;
+                        sum[3] += c.a as f32;
+                    }
+                }
+                let avg = |v: f32| (v / n).round() as u8;
+                out.push(Color::new(avg(sum[0]), avg(sum[1]), avg(sum[2]), avg(sum[3])));
+            }
+        }
+        out
+    }
+
+    /// Exporta a archivo (para capturas), al tamaño de salida y en el espacio de color `space`.
+    pub fn render_to_file(&self, path: &str, space: ColorSpace) {
+        if self.supersample <= 1 && space == ColorSpace::Srgb {
+            self.color_buffer.export_image(path);
+            return;
+        }
+        let (w, h) = self.display_size();
+        let mut image = Image::gen_image_color(w as i32, h as i32, self.background_color);
+        for (i, c) in self.export_data(space).into_iter().enumerate() {
+            image.draw_pixel(i as i32 % w as i32, i as i32 / w as i32, c);
+        }
+        image.export_image(path);
+    }
+
+    /// Los píxeles que escribe `render_to_file`: `image_data` codificado en `space`.
+    pub fn export_data(&self, space: ColorSpace) -> Vec<Color> {
+        self.image_data().into_iter().map(|c| space.encode(c)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_pixel_with_color_overwrites_and_blend_pixel_mixes() {
+        let mut fb = Framebuffer::new(2, 1, Color::BLACK);
+        let half_red = Color::new(255, 0, 0, 128);
+
+        fb.set_pixel_with_color(0, 0, half_red);
+        fb.blend_pixel(1, 0, half_red);
+        let px = fb.pixels();
+        assert_eq!(px[0], half_red);
+        assert_eq!(px[1], Color::new(128, 0, 0, 255));
+
+        // Opaco: las dos escriben lo mismo
+        fb.blend_pixel(1, 0, Color::BLUE);
+        assert_eq!(fb.pixels()[1], Color::BLUE);
+        // Fuera de la pantalla no hace nada
+        fb.blend_pixel(5, 5, half_red);
+    }
+
+    #[test]
+    fn white_left_eye_only_contributes_red() {
+        let left = Image::gen_image_color(2, 1, Color::WHITE);
+        let right = Image::gen_image_color(2, 1, Color::BLACK);
+        let out = Framebuffer::from_image(Framebuffer::composite_anaglyph(&left, &right)).pixels();
+        assert!(out.iter().all(|&c| c == Color::new(255, 0, 0, 255)));
+
+        // Y el derecho sólo verde + azul
+        let out = Framebuffer::from_image(Framebuffer::composite_anaglyph(&right, &left)).pixels();
+        assert!(out.iter().all(|&c| c == Color::new(0, 255, 255, 255)));
+    }
+
+    #[test]
+    fn from_image_keeps_gradient() {
+        let gradient = |x: i32, y: i32| Color::new((x * 60) as u8, (y * 60) as u8, 128, 255);
+        let mut img = Image::gen_image_color(4, 4, Color::BLACK);
+        for y in 0..4 {
+            for x in 0..4 {
+                img.draw_pixel(x, y, gradient(x, y));
+            }
+        }
+
+        let mut fb = Framebuffer::from_image(img);
+        assert_eq!((fb.width, fb.height), (4, 4));
+        assert_eq!(fb.z_buffer.len(), 16);
+        for y in 0..4 {
+            for x in 0..4 {
+                assert_eq!(fb.get_pixel(x, y), Some(gradient(x, y)));
+            }
+        }
+        assert_eq!(fb.get_pixel(4, 0), None);
+    }
+
+    #[test]
+    fn get_pixel_and_depth_at_read_back_and_reject_out_of_range() {
+        let mut fb = Framebuffer::new(8, 6, Color::BLACK);
+        fb.set_current_color(Color::RED);
+        fb.set_pixel_depth(3, 2, 1.5);
+        assert_eq!(fb.get_pixel(3, 2), Some(Color::RED));
+        assert_eq!(fb.depth_at(3, 2), Some(1.5));
+        // Sin dibujar: fondo y profundidad infinita
+        assert_eq!(fb.get_pixel(0, 0), Some(Color::BLACK));
+        assert_eq!(fb.depth_at(0, 0), Some(f32::INFINITY));
+        for (x, y) in [(-1, 0), (0, -1), (8, 0), (0, 6)] {
+            assert_eq!(fb.get_pixel(x, y), None);
+            assert_eq!(fb.depth_at(x, y), None);
+        }
+
+        // Una imagen más chica asignada a mano no se lee fuera de sus datos; una en otro
+        // formato se lee convertida
+        fb.color_buffer = Image::gen_image_color(2, 2, Color::BLUE);
+        assert_eq!(fb.get_pixel(1, 1), Some(Color::BLUE));
+        assert_eq!(fb.get_pixel(3, 2), None);
+        let mut rgb = Image::gen_image_color(8, 6, Color::BLUE);
+        rgb.set_format(PixelFormat::PIXELFORMAT_UNCOMPRESSED_R8G8B8);
+        fb.color_buffer = rgb;
+        assert_eq!(fb.get_pixel(7, 5), Some(Color::BLUE));
+    }
+
+    #[test]
+    fn mid_gray_exports_differently_in_srgb_and_linear() {
+        let gray = Color::new(128, 128, 128, 255);
+        let fb = Framebuffer::new(2, 2, gray);
+        let srgb = fb.export_data(ColorSpace::Srgb);
+        let linear = fb.export_data(ColorSpace::Linear);
+        assert_eq!(srgb, vec![gray; 4]);
+        assert_ne!(srgb[0], linear[0]);
+        // El gris medio en sRGB es ~21.6% lineal: ~55 en 8 bits; el alfa no se toca
+        assert!((50..=60).contains(&linear[0].r), "{:?}", linear[0]);
+        assert_eq!((linear[0].r, linear[0].a), (linear[0].b, 255));
+    }
+
+    #[test]
+    fn depth_ramp_normal_tilts_along_ramp() {
+        // Más lejos hacia la derecha: la superficie mira a la cámara y hacia +X
+        let mut fb = Framebuffer::new(16, 16, Color::BLACK);
+        for y in 0..16 {
+            for x in 0..16 {
+                fb.z_buffer[y * 16 + x] = 1.0 + 0.01 * x as f32;
+            }
+        }
+        let z = 1.0 + 0.01 * 8.0;
+        for focal in [1.0, 2.0] {
+            let n = fb.depth_to_screen_normals(focal)[8 * 16 + 8];
+            assert!(n.x > 0.0 && n.z < 0.0 && n.y.abs() < 1e-6, "{n:?}");
+            // dz/dx en mundo: 0.01 por píxel, y un píxel mide 2 (z - ojo) / (focal w)
+            let dzdx = 0.01 / (2.0 * (z - BASE_EYE.z) / (focal * 16.0));
+            assert!((n.x / -n.z - dzdx).abs() < 1e-4, "{} vs {dzdx}", n.x / -n.z);
+        }
+    }
+
+    /// Framebuffer gris de 32x32 con profundidad `depth(x)` (igual en cada fila) y SSAO.
+    fn ssao_on(depth: impl Fn(f32) -> f32) -> Vec<Color> {
+        let mut fb = Framebuffer::new(32, 32, Color::new(200, 200, 200, 255));
+        for y in 0..32 {
+            for x in 0..32 {
+                fb.z_buffer[y * 32 + x] = depth(x as f32);
+            }
+        }
+        fb.apply_ssao(4.0, 1.0, 1.0);
+        fb.pixels()
+    }
+
+    #[test]
+    fn ssao_darkens_crease_but_not_flat_plane() {
+        let flat = ssao_on(|_| 1.0);
+        assert!(flat.iter().all(|&c| c == Color::new(200, 200, 200, 255)));
+
+        // Valle: el fondo (x = 16) está más lejos y las paredes se acercan a la cámara
+        let crease = ssao_on(|x| 1.5 - 0.05 * (x - 16.0).abs());
+        let (bottom, wall) = (crease[16 * 32 + 16], crease[16 * 32 + 26]);
+        assert!(bottom.r < 190, "{bottom:?}");
+        assert!(bottom.r < wall.r);
+    }
+
+    #[test]
+    fn quantized_gradient_uses_only_palette_colors_and_dither_breaks_up_flat_areas() {
+        let (w, h) = (64, 8);
+        let gradient = |dither: bool| {
+            let mut fb = Framebuffer::new(w, h, Color::BLACK);
+            for y in 0..h as i32 {
+                for x in 0..w as i32 {
+                    let v = (x * 255 / (w as i32 - 1)) as u8;
+                    fb.set_pixel_with_color(x, y, Color::new(v, v, v, 255));
+                }
+            }
+            fb.quantize_to_palette(&SPACE_PALETTE, dither);
+            fb.pixels()
+        };
+        for dither in [false, true] {
+            let out = gradient(dither);
+            assert!(out.iter().all(|c| SPACE_PALETTE.contains(c)), "dither = {dither}");
+        }
+
+        // Gris plano a mitad de camino entre los dos primeros colores de la paleta
+        let (a, b) = (SPACE_PALETTE[0], SPACE_PALETTE[1]);
+        let mid = |p: u8, q: u8| ((p as u16 + q as u16) / 2) as u8;
+        let flat = |dither: bool| {
+            let mut fb = Framebuffer::new(8, 8, Color::new(mid(a.r, b.r), mid(a.g, b.g), mid(a.b, b.b), 255));
+            fb.quantize_to_palette(&SPACE_PALETTE, dither);
+            fb.pixels()
+        };
+        let mixed_block = |px: &[Color]| {
+            (0..4).flat_map(|by| (0..4).map(move |bx| (bx * 2, by * 2))).any(|(x, y)| {
+                let block = [px[y * 8 + x], px[y * 8 + x + 1], px[(y + 1) * 8 + x], px[(y + 1) * 8 + x + 1]];
+                block.iter().any(|&c| c != block[0])
+            })
+        };
+        assert!(!mixed_block(&flat(false)));
+        assert!(mixed_block(&flat(true)));
     }
 }
@@ -1,7 +1,14 @@
 use raylib::prelude::*;
 use crate::obj_loader::ObjModel;
+use crate::sampling::{hash01, sample_unit_square, unit_sphere_point, Distribution};
+use std::collections::HashMap;
 use std::f32::consts::PI;
 
+/// Subdivisiones de la icoesfera de cada asteroide (1 = 42 vértices, 80 caras).
+const ASTEROID_SUBDIVISIONS: u32 = 1;
+/// Radio de las rocas del cinturón, como fracción del ancho del cinturón (mínimo y máximo).
+const ASTEROID_SIZE: (f32, f32) = (0.04, 0.1);
+
 /// Genera una luna esférica proceduralmente (buena resolución configurable)
 pub fn generate_moon(radius: f32, segments: u32) -> ObjModel {
     let mut vertices = Vec::new();
@@ -35,25 +42,156 @@ pub fn generate_moon(radius: f32, segments: u32) -> ObjModel {
         }
     }
 
-    ObjModel { vertices, faces }
+    ObjModel::new(vertices, faces)
+}
+
+/// Esfera geodésica: un icosaedro de 12 vértices cuyos triángulos se parten en 4 `subdivisions`
+/// veces, proyectando cada vértice nuevo a la esfera. A diferencia de `generate_moon` los
+/// triángulos tienen casi el mismo tamaño en todas partes (sin amontonarse en los polos).
+/// Los puntos medios se comparten entre caras vecinas, así la malla queda cerrada:
+/// 10 * 4^n + 2 vértices y 20 * 4^n caras, todas con la cara frontal hacia afuera.
+pub fn generate_icosphere(radius: f32, subdivisions: u32) -> ObjModel {
+    let t = (1.0 + 5.0f32.sqrt()) * 0.5;
+    let mut vertices: Vec<Vector3> = [
+        (-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+        (0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+        (t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0),
+    ]
+    .iter()
+    .map(|&(x, y, z)| Vector3::new(x, y, z).normalized() * radius)
+    .collect();
+    let mut faces: Vec<[usize; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // Punto medio de cada arista, indexado por (menor, mayor) para que ambas caras lo compartan
+        let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut midpoint = |a: usize, b: usize, vertices: &mut Vec<Vector3>| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                vertices.push(((vertices[a] + vertices[b]) * 0.5).normalized() * radius);
+                vertices.len() - 1
+            })
+        };
+        let mut next = Vec::with_capacity(faces.len() * 4);
+        for [a, b, c] in faces {
+            let ab = midpoint(a, b, &mut vertices);
+            let bc = midpoint(b, c, &mut vertices);
+            let ca = midpoint(c, a, &mut vertices);
+            next.extend([[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]);
+        }
+        faces = next;
+    }
+
+    ObjModel::new(vertices, faces.into_iter().map(|f| f.to_vec()).collect())
+}
+
+/// Hunde `count` cráteres en direcciones al azar desde el centro del modelo (pensado para
+/// `generate_moon`/`generate_icosphere`). Cada cráter es un cuenco: los vértices a menos de su
+/// radio angular bajan hacia el centro con una caída suave, `depth * (1 - s²)²` con `s` la
+/// distancia angular relativa, así el borde no deja escalón. `max_depth` es la fracción del
+/// radio del cráter más hondo; donde dos se solapan manda el más profundo. El mismo `seed` da
+/// los mismos cráteres. Si el modelo ya tenía normales se recalculan; si no, las calcula
+/// `Body::new` como siempre.
+pub fn displace_craters(model: &mut ObjModel, count: usize, max_depth: f32, seed: u32) {
+    if model.vertices.is_empty() || count == 0 { return; }
+
+    let (min, max) = model.bounding_box();
+    let center = (min + max) * 0.5;
+    // (dirección, radio angular, profundidad): muchos chicos y pocos grandes
+    let craters: Vec<(Vector3, f32, f32)> = (0..count as u32)
+        .map(|i| {
+            let dir = unit_sphere_point(hash01(4 * i, seed), hash01(4 * i + 1, seed));
+            let size = hash01(4 * i + 2, seed);
+            (dir, 0.2 + 0.3 * size * size, max_depth * (0.3 + 0.7 * hash01(4 * i + 3, seed)))
+        })
+        .collect();
+
+    for v in model.vertices.iter_mut() {
+        let offset = *v - center;
+        let r = offset.length();
+        if r < 1e-9 { continue; }
+        let dir = offset / r;
+        let mut dent: f32 = 0.0;
+        for &(d, size, depth) in &craters {
+            let angle = dir.dot(d).clamp(-1.0, 1.0).acos();
+            if angle < size {
+                let s = angle / size;
+                dent = dent.max(depth * (1.0 - s * s) * (1.0 - s * s));
+            }
+        }
+        *v = center + dir * r * (1.0 - dent).max(0.2);
+    }
+    if !model.normals.is_empty() {
+        model.compute_smooth_normals();
+    }
+}
+
+/// Cinturón de `count` rocas chicas entre los radios `inner` y `outer` del plano XZ. Cada roca
+/// es una icoesfera de pocos vértices deformada con `make_irregular` (forma propia por roca) y
+/// ya escalada; se devuelve con su posición en el cinturón (con un poco de espesor en Y) y un
+/// factor de giro en 0.5..2 para que no roten todas igual. Las posiciones salen de
+/// `sample_unit_square` con `distribution`: con `Halton` el anillo se ve parejo, sin los
+/// grumos del azar puro (`Uniform`). El mismo `seed` da el mismo campo.
+pub fn generate_asteroid_field(
+    count: usize,
+    inner: f32,
+    outer: f32,
+    distribution: Distribution,
+    seed: u32,
+) -> Vec<(ObjModel, Vector3, f32)> {
+    let (inner, outer) = (inner.min(outer).max(0.0), inner.max(outer).max(0.0));
+    let width = (outer - inner).max(1e-3);
+    let rock = generate_icosphere(1.0, ASTEROID_SUBDIVISIONS);
+
+    sample_unit_square(distribution, count, seed)
+        .into_iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let rock_seed = seed ^ (i as u32).wrapping_mul(0x9E37_79B9);
+            let mut mesh = rock.clone();
+            mesh.make_irregular(0.35, 2.0, rock_seed);
+            let size = ASTEROID_SIZE.0 + (ASTEROID_SIZE.1 - ASTEROID_SIZE.0) * hash01(0, rock_seed);
+            mesh.scale_to_radius(size * width);
+
+            // Radio con densidad pareja por área (no se amontonan en el borde interior)
+            let radius = (inner * inner + (outer * outer - inner * inner) * p.y).sqrt();
+            let angle = p.x * 2.0 * PI;
+            let height = (hash01(1, rock_seed) - 0.5) * 0.1 * width;
+            let position = Vector3::new(radius * angle.cos(), height, radius * angle.sin());
+            (mesh, position, 0.5 + 1.5 * hash01(2, rock_seed))
+        })
+        .collect()
 }
 
-/// Genera anillos como un disco con agujero (plano XZ)
-pub fn generate_rings(inner_radius: f32, outer_radius: f32, segments: u32) -> ObjModel {
+/// Genera anillos como un disco con agujero (plano XZ).
+/// Con `thickness = 0` es un disco de una sola cara (normal hacia -Y). Con `thickness > 0` es una
+/// losa: cara superior (+Y) e inferior (-Y) separadas `thickness`, más las paredes interior y
+/// exterior, así el anillo se ve desde ambos lados y el lado en sombra queda oscuro.
+pub fn generate_rings(inner_radius: f32, outer_radius: f32, segments: u32, thickness: f32) -> ObjModel {
     let mut vertices = Vec::new();
     let mut faces = Vec::new();
 
-    // Generamos dos anillos (círculos)
-    for radius in [inner_radius, outer_radius] {
-        for i in 0..=segments {
-            let angle = (i as f32 * 2.0 * PI) / segments as f32;
-            let x = radius * angle.cos();
-            let z = radius * angle.sin();
-            vertices.push(Vector3::new(x, 0.0, z));
+    let half = thickness.max(0.0) * 0.5;
+    let layers: &[f32] = if half > 0.0 { &[-half, half] } else { &[0.0] };
+
+    // Por capa generamos dos anillos (círculos): interior y exterior
+    for &y in layers {
+        for radius in [inner_radius, outer_radius] {
+            for i in 0..=segments {
+                let angle = (i as f32 * 2.0 * PI) / segments as f32;
+                let x = radius * angle.cos();
+                let z = radius * angle.sin();
+                vertices.push(Vector3::new(x, y, z));
+            }
         }
     }
 
     let segs = (segments + 1) as usize;
+    let layer = 2 * segs; // vértices por capa
     for i in 0..segments as usize {
         let inner_current = i;
         let inner_next = i + 1;
@@ -61,38 +199,432 @@ pub fn generate_rings(inner_radius: f32, outer_radius: f32, segments: u32) -> Ob
         let outer_next = i + 1 + segs;
         faces.push(vec![inner_current, outer_current, inner_next]);
         faces.push(vec![inner_next, outer_current, outer_next]);
+
+        if half > 0.0 {
+            // Cara superior: mismo patrón con el sentido invertido (normal hacia +Y)
+            let (ic, inx, oc, on) = (inner_current + layer, inner_next + layer, outer_current + layer, outer_next + layer);
+            faces.push(vec![ic, inx, oc]);
+            faces.push(vec![inx, on, oc]);
+
+            // Pared exterior (normal hacia afuera) e interior (hacia el centro)
+            faces.push(vec![outer_current, on, outer_next]);
+            faces.push(vec![outer_current, oc, on]);
+            faces.push(vec![inner_current, inner_next, inx]);
+            faces.push(vec![inner_current, inx, ic]);
+        }
+    }
+
+    ObjModel::new(vertices, faces)
+}
+
+/// Toro (dona) acostado en el plano XZ: un tubo de radio `minor_radius` cuyo centro recorre el
+/// círculo de radio `major_radius`. Es cerrado, así que de canto no desaparece como el disco de
+/// `generate_rings`. Sin vértices duplicados en las costuras: la cuadrícula da la vuelta en
+/// ambos sentidos, son `major_segments * minor_segments` vértices y todas las caras miran
+/// hacia afuera del tubo (el culling deja la mitad de adelante). Menos de 3 segmentos en
+/// cualquiera de los dos sentidos se toman como 3: con 1 o 2 el tubo o el anillo se aplanan
+/// en caras degeneradas, y 3 es lo mínimo que todavía encierra volumen.
+pub fn generate_torus(major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> ObjModel {
+    let (major, minor) = (major_segments.max(3) as usize, minor_segments.max(3) as usize);
+    let mut vertices = Vec::with_capacity(major * minor);
+    let mut faces = Vec::with_capacity(major * minor * 2);
+
+    for i in 0..major {
+        let u = i as f32 * 2.0 * PI / major as f32;
+        for j in 0..minor {
+            let v = j as f32 * 2.0 * PI / minor as f32;
+            let ring = major_radius + minor_radius * v.cos();
+            vertices.push(Vector3::new(ring * u.cos(), minor_radius * v.sin(), ring * u.sin()));
+        }
     }
 
-    ObjModel { vertices, faces }
+    let index = |i: usize, j: usize| (i % major) * minor + j % minor;
+    for i in 0..major {
+        for j in 0..minor {
+            let (a, b, c, d) = (index(i, j), index(i + 1, j), index(i, j + 1), index(i + 1, j + 1));
+            faces.push(vec![a, c, b]);
+            faces.push(vec![b, c, d]);
+        }
+    }
+
+    ObjModel::new(vertices, faces)
+}
+
+/// Cuadrado plano de lado 2 en z = 0 (x, y en [-1, 1]): dos triángulos que miran a la
+/// cámara (normal -Z). Para ver el shader como textura plana, sin la distorsión de la esfera.
+pub fn generate_quad() -> ObjModel {
+    let vertices = vec![
+        Vector3::new(-1.0, -1.0, 0.0),
+        Vector3::new(1.0, -1.0, 0.0),
+        Vector3::new(1.0, 1.0, 0.0),
+        Vector3::new(-1.0, 1.0, 0.0),
+    ];
+    // Antihorario visto desde la cámara (ver convenciones en `triangle`)
+    let faces = vec![vec![0, 2, 1], vec![0, 3, 2]];
+    ObjModel::new(vertices, faces)
 }
 
-/// Aplica rotaciones en X, Y, escala y traslación
+/// Aplica rotaciones en X, Y, escala y traslación.
+/// Con la feature `parallel` los vértices se transforman con rayon (mismo resultado que la versión serial).
 pub fn transform_model(
     model: &ObjModel,
     translation: Vector3,
     rotation_y: f32,
     rotation_x: f32,
     scale: f32,
+) -> Vec<Vector3> {
+    #[cfg(feature = "parallel")]
+    {
+        transform_model_parallel(model, translation, rotation_y, rotation_x, scale)
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        transform_model_serial(model, translation, rotation_y, rotation_x, scale)
+    }
+}
+
+/// Versión serial de `transform_model` (siempre disponible, sirve de referencia).
+pub fn transform_model_serial(
+    model: &ObjModel,
+    translation: Vector3,
+    rotation_y: f32,
+    rotation_x: f32,
+    scale: f32,
 ) -> Vec<Vector3> {
     model
         .vertices
         .iter()
-        .map(|v| {
-            let (mut x, mut y, mut z) = (v.x * scale, v.y * scale, v.z * scale);
-
-            // Rotación en X
-            let new_y = y * rotation_x.cos() - z * rotation_x.sin();
-            let new_z = y * rotation_x.sin() + z * rotation_x.cos();
-            y = new_y;
-            z = new_z;
-
-            // Rotación en Y
-            let new_x = x * rotation_y.cos() + z * rotation_y.sin();
-            let new_z2 = -x * rotation_y.sin() + z * rotation_y.cos();
-            x = new_x;
-            z = new_z2;
-
-            Vector3::new(x + translation.x, y + translation.y, z + translation.z)
-        })
+        .map(|v| transform_vertex(*v, translation, rotation_y, rotation_x, scale))
+        .collect()
+}
+
+/// `transform_model` con un "vertex shader": `displace(v, i)` recibe cada vértice ya
+/// transformado y su índice, y devuelve la posición final (wobble, respiración, etc).
+/// Siempre serial, porque el closure puede tener estado.
+pub fn transform_model_with<F>(
+    model: &ObjModel,
+    translation: Vector3,
+    rotation_y: f32,
+    rotation_x: f32,
+    scale: f32,
+    mut displace: F,
+) -> Vec<Vector3>
+where
+    F: FnMut(Vector3, usize) -> Vector3,
+{
+    model
+        .vertices
+        .iter()
+        .enumerate()
+        .map(|(i, v)| displace(transform_vertex(*v, translation, rotation_y, rotation_x, scale), i))
+        .collect()
+}
+
+/// Versión paralela de `transform_model`: cada vértice es independiente.
+#[cfg(feature = "parallel")]
+pub fn transform_model_parallel(
+    model: &ObjModel,
+    translation: Vector3,
+    rotation_y: f32,
+    rotation_x: f32,
+    scale: f32,
+) -> Vec<Vector3> {
+    use rayon::prelude::*;
+    model
+        .vertices
+        .par_iter()
+        .map(|v| transform_vertex(*v, translation, rotation_y, rotation_x, scale))
+        .collect()
+}
+
+/// Transforma un solo vértice: escala, rotación en X, rotación en Y y traslación.
+pub fn transform_vertex(
+    v: Vector3,
+    translation: Vector3,
+    rotation_y: f32,
+    rotation_x: f32,
+    scale: f32,
+) -> Vector3 {
+    let (mut x, mut y, mut z) = (v.x * scale, v.y * scale, v.z * scale);
+
+    // Rotación en X
+    let new_y = y * rotation_x.cos() - z * rotation_x.sin();
+    let new_z = y * rotation_x.sin() + z * rotation_x.cos();
+    y = new_y;
+    z = new_z;
+
+    // Rotación en Y
+    let new_x = x * rotation_y.cos() + z * rotation_y.sin();
+    let new_z2 = -x * rotation_y.sin() + z * rotation_y.cos();
+    x = new_x;
+    z = new_z2;
+
+    Vector3::new(x + translation.x, y + translation.y, z + translation.z)
+}
+
+/// `transform_vertex` como matriz: escala, rotación en X, rotación en Y y traslación. En
+/// raylib `a * b` aplica primero `a`, así que la de un hijo se compone como `hijo * padre`.
+pub fn model_matrix(translation: Vector3, rotation_y: f32, rotation_x: f32, scale: f32) -> Matrix {
+    Matrix::scale(scale, scale, scale)
+        * Matrix::rotate_x(rotation_x)
+        * Matrix::rotate_y(rotation_y)
+        * Matrix::translate(translation.x, translation.y, translation.z)
+}
+
+/// Transforma todos los vértices con `transform` (p. ej. la de `Scene::world_transform`).
+/// Con la feature `parallel` usa rayon, como `transform_model`.
+pub fn transform_model_matrix(model: &ObjModel, transform: Matrix) -> Vec<Vector3> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        model.vertices.par_iter().map(|v| v.transform_with(transform)).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        model.vertices.iter().map(|v| v.transform_with(transform)).collect()
+    }
+}
+
+/// Rota normales con la parte lineal de `transform` (sin traslación) y las normaliza, así
+/// la escala (uniforme) no les cambia el largo.
+pub fn transform_normals_matrix(normals: &[Vector3], transform: Matrix) -> Vec<Vector3> {
+    let linear = Matrix { m12: 0.0, m13: 0.0, m14: 0.0, ..transform };
+    normals.iter().map(|n| n.transform_with(linear).normalized()).collect()
+}
+
+/// Rota normales con la misma rotación que `transform_vertex` (sin escala ni traslación).
+pub fn transform_normals(normals: &[Vector3], rotation_y: f32, rotation_x: f32) -> Vec<Vector3> {
+    let origin = Vector3::new(0.0, 0.0, 0.0);
+    normals
+        .iter()
+        .map(|&n| transform_vertex(n, origin, rotation_y, rotation_x, 1.0))
         .collect()
 }
+
+/// Extremos (sur, norte) del eje de giro de un cuerpo de radio `radius` (en espacio del modelo),
+/// alargado `extend` veces el radio, con la misma transformación que `transform_vertex`.
+pub fn spin_axis(
+    radius: f32,
+    extend: f32,
+    translation: Vector3,
+    rotation_y: f32,
+    rotation_x: f32,
+    scale: f32,
+) -> (Vector3, Vector3) {
+    let pole = Vector3::new(0.0, radius * extend, 0.0);
+    let south = transform_vertex(-pole, translation, rotation_y, rotation_x, scale);
+    let north = transform_vertex(pole, translation, rotation_y, rotation_x, scale);
+    (south, north)
+}
+
+/// Caché de `transform_model_matrix` para un cuerpo: sólo recalcula los vértices cuando
+/// cambia la matriz (dirty flag), así un cuerpo quieto no se re-transforma cada frame.
+#[derive(Debug, Clone, Default)]
+pub struct TransformCache {
+    key: Option<Matrix>,
+    vertices: Vec<Vector3>,
+    recomputes: u64,
+}
+
+impl TransformCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Devuelve los vértices transformados, recalculando sólo si cambió la matriz.
+    pub fn get(&mut self, model: &ObjModel, transform: Matrix) -> &[Vector3] {
+        if self.key != Some(transform) || self.vertices.len() != model.vertices.len() {
+            self.vertices = transform_model_matrix(model, transform);
+            self.key = Some(transform);
+            self.recomputes += 1;
+        }
+        &self.vertices
+    }
+
+    /// Fuerza el recálculo en el próximo `get` (p. ej. si se editó el modelo).
+    pub fn invalidate(&mut self) {
+        self.key = None;
+    }
+
+    /// Cuántas veces se ha recalculado la transformación.
+    pub fn recomputes(&self) -> u64 {
+        self.recomputes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unos cientos de vértices y una transformación sin nada trivial.
+    fn sample() -> (ObjModel, Vector3) {
+        let model = generate_icosphere(1.0, 3);
+        assert!(model.vertices.len() >= 300);
+        (model, Vector3::new(0.5, -1.25, 2.0))
+    }
+
+    #[test]
+    fn transform_model_matches_serial() {
+        let (model, translation) = sample();
+        let serial = transform_model_serial(&model, translation, 0.7, -0.3, 1.5);
+        assert_eq!(transform_model(&model, translation, 0.7, -0.3, 1.5), serial);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_transform_matches_serial() {
+        let (model, translation) = sample();
+        let serial = transform_model_serial(&model, translation, 0.7, -0.3, 1.5);
+        assert_eq!(transform_model_parallel(&model, translation, 0.7, -0.3, 1.5), serial);
+    }
+
+    #[test]
+    fn transform_cache_skips_unchanged_bodies() {
+        let (model, translation) = sample();
+        let mut cache = TransformCache::new();
+        let first = cache.get(&model, model_matrix(translation, 0.7, -0.3, 1.5)).to_vec();
+        for _ in 0..3 {
+            assert_eq!(cache.get(&model, model_matrix(translation, 0.7, -0.3, 1.5)), first.as_slice());
+        }
+        assert_eq!(cache.recomputes(), 1);
+
+        cache.get(&model, model_matrix(translation, 0.8, -0.3, 1.5));
+        assert_eq!(cache.recomputes(), 2);
+        cache.invalidate();
+        cache.get(&model, model_matrix(translation, 0.8, -0.3, 1.5));
+        assert_eq!(cache.recomputes(), 3);
+    }
+
+    #[test]
+    fn model_matrix_matches_transform_vertex() {
+        let (model, translation) = sample();
+        let matrix = transform_model_matrix(&model, model_matrix(translation, 0.7, -0.3, 1.5));
+        let euler = transform_model(&model, translation, 0.7, -0.3, 1.5);
+        for (m, e) in matrix.iter().zip(&euler) {
+            assert!((*m - *e).length() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn spin_axis_endpoints_project_to_expected_positions() {
+        use crate::triangle::project_point;
+        let close = |a: Vector2, b: Vector2| (a - b).length() < 1e-3;
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+
+        // Sin inclinación: vertical por el centro, 1.2 / 3 de media pantalla arriba y abajo
+        let (south, north) = spin_axis(1.0, 1.2, origin, 0.0, 0.0, 1.0);
+        assert!(close(project_point(north, 100, 100), Vector2::new(50.0, 30.0)));
+        assert!(close(project_point(south, 100, 100), Vector2::new(50.0, 70.0)));
+
+        // Inclinado 90° en X: el eje apunta a la cámara y los dos polos caen en el centro
+        let (south, north) = spin_axis(1.0, 1.2, origin, 0.0, std::f32::consts::FRAC_PI_2, 1.0);
+        assert!(close(project_point(north, 100, 100), Vector2::new(50.0, 50.0)));
+        assert!(close(project_point(south, 100, 100), Vector2::new(50.0, 50.0)));
+
+        // Con traslación y escala el eje se mueve con el cuerpo
+        let (south, north) = spin_axis(1.0, 1.0, Vector3::new(1.0, 0.0, 0.0), 0.0, 0.0, 0.5);
+        assert_eq!((south + north) * 0.5, Vector3::new(1.0, 0.0, 0.0));
+        assert!(((north - south).length() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn thick_ring_doubles_surface_faces_plus_edges() {
+        let segments = 24;
+        let flat = generate_rings(1.2, 2.0, segments, 0.0);
+        let thick = generate_rings(1.2, 2.0, segments, 0.1);
+        // Dos paredes (interior y exterior) de dos triángulos por segmento
+        let edges = 4 * segments as usize;
+        assert_eq!(flat.faces.len(), 2 * segments as usize);
+        assert_eq!(thick.faces.len(), 2 * flat.faces.len() + edges);
+        assert_eq!(thick.vertices.len(), 2 * flat.vertices.len());
+        let ys: Vec<f32> = thick.vertices.iter().map(|v| v.y).collect();
+        assert!(ys.iter().all(|&y| (y.abs() - 0.05).abs() < 1e-6));
+    }
+
+    #[test]
+    fn transform_model_with_applies_closure_after_transform() {
+        let (model, translation) = sample();
+        let offset = Vector3::new(0.0, 0.25, -1.0);
+        let mut seen = Vec::new();
+        let displaced = transform_model_with(&model, translation, 0.7, -0.3, 1.5, |v, i| {
+            seen.push(i);
+            v + offset
+        });
+        let plain = transform_model(&model, translation, 0.7, -0.3, 1.5);
+        assert_eq!(seen, (0..model.vertices.len()).collect::<Vec<_>>());
+        for (d, p) in displaced.iter().zip(&plain) {
+            assert!((*d - (*p + offset)).length() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn quad_is_two_triangles_covering_the_viewport() {
+        use crate::framebuffer::Framebuffer;
+        use crate::triangle::{draw_filled_view_triangle, is_front_facing, DebugView, RasterOptions, ShaderType};
+        let quad = generate_quad();
+        assert_eq!(quad.faces.len(), 2);
+        assert!(quad.faces.iter().all(|f| f.len() == 3));
+
+        // A distancia 1 del ojo (como la vista previa plana) x, y en [-1, 1] llenan la pantalla
+        let view: Vec<Vector3> = quad.vertices.iter().map(|v| Vector3::new(v.x, v.y, -2.0)).collect();
+        let options = RasterOptions { debug_view: Some(DebugView::Normals), ..RasterOptions::default() };
+        let mut fb = Framebuffer::new(32, 32, Color::BLACK);
+        for f in &quad.faces {
+            let (a, b, c) = (view[f[0]], view[f[1]], view[f[2]]);
+            assert!(is_front_facing(a, b, c));
+            draw_filled_view_triangle(&mut fb, a, b, c, &ShaderType::Rocky, 0.0, &options);
+        }
+        assert!(fb.pixels().iter().all(|&c| c != Color::BLACK));
+    }
+
+    #[test]
+    fn asteroid_positions_follow_the_chosen_distribution() {
+        let (inner, outer, seed) = (1.5, 2.5, 7);
+        for distribution in [Distribution::Uniform, Distribution::Halton] {
+            let field = generate_asteroid_field(16, inner, outer, distribution, seed);
+            let samples = sample_unit_square(distribution, 16, seed);
+            assert_eq!(field.len(), samples.len());
+            for ((_, position, _), p) in field.iter().zip(&samples) {
+                // De vuelta al cuadrado unitario: ángulo -> x, radio² -> y
+                let angle = position.z.atan2(position.x).rem_euclid(2.0 * PI) / (2.0 * PI);
+                let r2 = (position.x * position.x + position.z * position.z - inner * inner) / (outer * outer - inner * inner);
+                assert!((angle - p.x).abs() < 1e-3 || (angle - p.x).abs() > 1.0 - 1e-3, "{distribution:?}: {angle} != {}", p.x);
+                assert!((r2 - p.y).abs() < 1e-3, "{distribution:?}: {r2} != {}", p.y);
+            }
+        }
+        let positions = |d| generate_asteroid_field(8, inner, outer, d, seed).into_iter().map(|r| r.1).collect::<Vec<_>>();
+        assert_ne!(positions(Distribution::Uniform), positions(Distribution::Halton));
+    }
+
+    #[test]
+    fn torus_has_a_closed_grid_facing_outward() {
+        let (major, minor) = (24, 12);
+        let torus = generate_torus(1.0, 0.3, major, minor);
+        assert_eq!(torus.vertices.len(), (major * minor) as usize);
+        assert_eq!(torus.faces.len(), 2 * (major * minor) as usize);
+        // Con menos de 3 segmentos se usan 3
+        let tiny = generate_torus(1.0, 0.3, 1, 2);
+        assert_eq!((tiny.vertices.len(), tiny.faces.len()), (9, 18));
+
+        // Inclinado hacia la cámara: una cara se ve de frente justo cuando su lado de afuera
+        // del tubo mira al ojo (sin contar las casi de canto, donde decide el redondeo)
+        let tilt = 0.6;
+        let view = |v: Vector3| transform_vertex(v, Vector3::zero(), 0.0, tilt, 1.0);
+        let mut front = 0;
+        for face in &torus.faces {
+            let [a, b, c] = [face[0], face[1], face[2]].map(|i| torus.vertices[i]);
+            let centroid = (a + b + c) / 3.0;
+            let ring = Vector3::new(centroid.x, 0.0, centroid.z).normalized();
+            let outward = view(centroid - ring).normalized();
+            let to_face = (view(centroid) - crate::camera::BASE_EYE).normalized();
+            let facing = outward.dot(to_face);
+            if facing.abs() < 0.1 {
+                continue;
+            }
+            let is_front = crate::triangle::is_front_facing(view(a), view(b), view(c));
+            assert_eq!(is_front, facing < 0.0, "cara {face:?}");
+            front += is_front as usize;
+        }
+        assert!(front > torus.faces.len() / 4, "{front} caras de frente");
+    }
+}
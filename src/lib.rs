@@ -0,0 +1,22 @@
+//! Rasterizador por software del laboratorio: framebuffer, carga de OBJ,
+//! geometría procedural y shaders de planetas. `main` es sólo una `RenderApp`
+//! más; cualquier otra app puede reutilizar la misma infraestructura.
+pub mod app;
+pub mod camera;
+pub mod capture;
+pub mod compare;
+pub mod framebuffer;
+pub mod geometria;
+pub mod line;
+pub mod obj_loader;
+pub mod palette;
+pub mod postprocess;
+pub mod profiler;
+pub mod quality;
+pub mod sampling;
+pub mod scene;
+pub mod shader;
+pub mod skybox;
+pub mod stereo;
+pub mod tiled;
+pub mod triangle;
@@ -1,4 +1,5 @@
 use raylib::prelude::*;
+use crate::camera::BASE_EYE;
 use crate::framebuffer::Framebuffer;
 
 /// Bresenham simple (mantengo tu versión, sólo pulido)
@@ -43,3 +44,52 @@ pub fn draw_polygon(framebuffer: &mut Framebuffer, points: &[Vector2]) {
         line(framebuffer, start, end);
     }
 }
+
+/// Bresenham con profundidad: interpola 1/d (d = distancia al ojo en Z, igual que el relleno) y
+/// escribe con `set_pixel_depth` restando `bias`, para que la arista quede justo delante de su
+/// propia cara pero siga tapada por geometría más cercana.
+pub fn line_depth(
+    framebuffer: &mut Framebuffer,
+    start: Vector2,
+    end: Vector2,
+    z_start: f32,
+    z_end: f32,
+    bias: f32,
+) {
+    let mut x0 = start.x as i32;
+    let mut y0 = start.y as i32;
+    let x1 = end.x as i32;
+    let y1 = end.y as i32;
+
+    let dx = (x1 - x0).abs();
+    let dy = -(y1 - y0).abs();
+    let sx = if x0 < x1 { 1 } else { -1 };
+    let sy = if y0 < y1 { 1 } else { -1 };
+    let mut err = dx + dy;
+
+    let steps = dx.max(-dy).max(1) as f32;
+    let iz_start = 1.0 / (z_start - BASE_EYE.z);
+    let iz_end = 1.0 / (z_end - BASE_EYE.z);
+    let mut step = 0.0;
+
+    loop {
+        let t = step / steps;
+        let depth = 1.0 / (iz_start + (iz_end - iz_start) * t) + BASE_EYE.z;
+        framebuffer.set_pixel_depth(x0, y0, depth - bias);
+
+        if x0 == x1 && y0 == y1 {
+            break;
+        }
+
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x0 += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y0 += sy;
+        }
+        step += 1.0;
+    }
+}
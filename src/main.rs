@@ -1,196 +1,1406 @@
 #![allow(unused_imports)]
-mod framebuffer;
-mod line;
-mod obj_loader;
-mod shader;
-mod triangle;
-mod geometria;
-
 use raylib::prelude::*;
-use framebuffer::Framebuffer;
-use obj_loader::ObjModel;
-use triangle::ShaderType;
-use geometria::{generate_moon, generate_rings, transform_model};
+use lab4::app::{self, RenderApp};
+use lab4::framebuffer::{ColorSpace, Framebuffer, MAX_SUPERSAMPLE, SPACE_PALETTE};
+use lab4::obj_loader::ObjModel;
+use lab4::profiler::{FrameProfiler, Stage};
+use lab4::quality::QualityController;
+use lab4::shader::{self, GlobeGrid, Light, PlanetShader, PlanetShine, RingShadow, Uniforms, DEFAULT_LIGHT_DIR};
+use lab4::camera::{Camera, OrbitCamera};
+use lab4::capture::CaptureMetadata;
+use lab4::sampling::{seed_from_name, Distribution};
+use lab4::palette::Harmony;
+use lab4::skybox::{render_skybox, Starfield, STAR_COUNT};
+use lab4::postprocess::{apply_bloom, apply_exposure, BloomSettings, DEFAULT_EXPOSURE};
+use lab4::compare::{render_split, Variant};
+use lab4::stereo::{render_anaglyph, render_stereo, stereo_cameras, DEFAULT_EYE_SEPARATION};
+use lab4::line::line_depth;
+use lab4::scene::{draw_model, Body, Orbit, Scene};
+use lab4::triangle::{self, ShaderType};
+use lab4::geometria::{displace_craters, generate_asteroid_field, generate_icosphere, generate_quad, generate_rings, spin_axis, transform_vertex};
+use std::collections::VecDeque;
 use std::f32::consts::PI;
+use std::sync::Arc;
 
-fn main() {
-    let (mut window, thread) = raylib::init()
-        .size(800, 600)
-        .title("Laboratorio 4 - Cuerpos Celestes Procedurales")
-        .build();
+/// Configuración de cada planeta de la demo: modelo, shader y cuerpos acompañantes.
+struct PlanetConfig {
+    name: &'static str,
+    model_desc: &'static str,
+    crystal_model: bool, // usa crystal_planet.obj en vez de sphere-1.obj
+    shader: Arc<dyn PlanetShader>,
+    moon: Option<ShaderType>,  // shader de la luna orbitando, si tiene
+    rings: Option<ShaderType>, // shader de los anillos, si tiene
+    belt: bool,                // cinturón de asteroides alrededor (ver `generate_asteroid_field`)
+}
 
-    let mut fb = Framebuffer::new(800, 600, Color::new(5, 5, 15, 255));
+impl PlanetConfig {
+    /// Planeta de `sphere-1.obj` con `shader` y sin acompañantes.
+    fn new(name: &'static str, shader: Arc<dyn PlanetShader>) -> Self {
+        Self { name, model_desc: "sphere-1.obj", crystal_model: false, shader, moon: None, rings: None, belt: false }
+    }
+}
 
-    println!("Cargando sphere-1.obj ...");
-    let model_sphere = ObjModel::load("sphere-1.obj")
-        .expect("No se pudo cargar sphere-1.obj");
+/// Los planetas que recorre TAB, en orden.
+fn default_planets() -> Vec<PlanetConfig> {
+    vec![
+        PlanetConfig {
+            model_desc: "sphere-1.obj + Luna Procedural",
+            moon: Some(ShaderType::Ice),
+            ..PlanetConfig::new("Rocos", ShaderType::Rocky.into())
+        },
+        PlanetConfig {
+            model_desc: "sphere-1.obj + Anillos Procedurales",
+            rings: Some(ShaderType::Crystal),
+            ..PlanetConfig::new("Gaseoso", ShaderType::Gas.into())
+        },
+        PlanetConfig {
+            model_desc: "crystal_planet.obj",
+            crystal_model: true,
+            ..PlanetConfig::new("Adicional", ShaderType::Crystal.into())
+        },
+        PlanetConfig { belt: true, ..PlanetConfig::new("Puntos Extra", ShaderType::Lava.into()) },
+        PlanetConfig { belt: true, ..PlanetConfig::new("Puntos Extra", ShaderType::Ice.into()) },
+        PlanetConfig::new("Toon", ShaderType::Toon.into()),
+        // Un shader fuera de `ShaderType`: cualquier `PlanetShader` entra así
+        PlanetConfig::new("Cristal facetado", Arc::new(shader::CristalShader)),
+        // Colores de `generate_palette`, con la semilla de su nombre
+        PlanetConfig::new("Paleta", Arc::new(shader::PaletteShader::new(seed_from_name("Paleta"), Harmony::Triadic))),
+    ]
+}
 
-    let model_crystal = ObjModel::load("crystal_planet.obj")
-        .unwrap_or_else(|_| model_sphere.clone());
+/// Geometría de los anillos: radios, grosor de la losa (0 = disco plano de una cara),
+/// inclinación en X y fracción del giro del planeta con que rotan.
+const RING_INNER: f32 = 1.35;
+const RING_OUTER: f32 = 2.1;
+const RING_THICKNESS: f32 = 0.03;
+const RING_TILT: f32 = 0.35;
+const RING_SPIN: f32 = 0.3;
 
-    let moon_model = generate_moon(0.3, 24);
-    let rings_model = generate_rings(1.35, 2.1, 128);
+/// Cinturón de asteroides: rocas por defecto (`--asteroids N`), radios y velocidad orbital en
+/// el borde interior (las de afuera van más lento, como en una órbita de Kepler).
+const ASTEROID_COUNT: usize = 60;
+const BELT_INNER: f32 = 1.7;
+const BELT_OUTER: f32 = 2.4;
+const BELT_SPEED: f32 = 0.4;
 
-    println!(
-        "Modelos listos. Vertices luna: {}, anillos: {}",
-        moon_model.vertices.len(),
-        rings_model.vertices.len()
-    );
+/// Factor por pulsación de -/= (1/4 de stop) y límite de la exposición (y de su inverso).
+const EXPOSURE_STEP: f32 = 1.189_207;
+const MAX_EXPOSURE: f32 = 16.0;
 
-    let mut angle_y = 0.0f32;
-    let mut scale = 1.5f32;
-    let mut current_planet = 0usize;
-    let mut auto_rotate = true;
-    let mut time = 0.0f32;
-    let mut orbital_angle = 0.0f32;
-
-    window.set_target_fps(60);
-
-    let planet_names = vec![
-        "Rocos",
-        "Gaseoso",
-        "Adicional",
-        "Puntos Extra",
-        "Puntos Extra",
-    ];
-
-    let planet_models = vec![
-        "sphere-1.obj + Luna Procedural",
-        "sphere-1.obj + Anillos Procedurales",
-        "crystal_planet.obj",
-        "sphere-1.obj",
-        "sphere-1.obj",
-    ];
+/// Radio (píxeles) e intensidad del SSAO (tecla O).
+const SSAO_RADIUS: f32 = 6.0;
+const SSAO_INTENSITY: f32 = 0.6;
 
-    println!("\n=== CONTROLES ===");
-    println!("TAB: Cambiar planeta | P: Pausar rotación | W/S: Zoom | A/D: Rotar | R: Reiniciar | C: Captura");
+/// Órbita con el mouse: radianes por píxel arrastrado, factor por paso de la rueda y
+/// radianes por frame con las flechas.
+const ORBIT_SENSITIVITY: f32 = 0.008;
+const DOLLY_STEP: f32 = 0.9;
+const ORBIT_KEY_SPEED: f32 = 0.02;
+
+/// Luz principal cálida + relleno frío desde el lado opuesto (`--key-fill`).
+const KEY_FILL_LIGHTS: [Light; 2] = [
+    Light { direction: DEFAULT_LIGHT_DIR, color: Color::new(255, 214, 170, 255), intensity: 1.0 },
+    Light { direction: Vector3::new(-0.7, -0.3, 0.4), color: Color::new(140, 170, 255, 255), intensity: 0.35 },
+];
+
+/// Deformación de la luna (ver `ObjModel::make_irregular`): fracción del radio y bultos por radio.
+const MOON_IRREGULARITY: f32 = 0.15;
+const MOON_LUMPINESS: f32 = 1.6;
+/// Cráteres de la luna (ver `displace_craters`): cantidad y profundidad máxima (fracción del radio).
+const MOON_CRATERS: usize = 14;
+const MOON_CRATER_DEPTH: f32 = 0.06;
+
+/// Radianes por frame que gira la luz alrededor del planeta con 9 / 0.
+const LIGHT_ORBIT_SPEED: f32 = 0.02;
+
+/// Paso (rad) de la inclinación del plano de las órbitas con 2/3, hasta ±90°.
+const SYSTEM_TILT_STEP: f32 = PI / 12.0;
+
+/// Fracción de la pantalla que ocupa la escena al encuadrar (tecla E) si no se pasa `--frame-fill`.
+const DEFAULT_FRAME_FILL: f32 = 0.8;
+
+/// Colores de luz que recorre la tecla I: la misma escena bajo distintas estrellas.
+const LIGHT_PRESETS: [(&str, Color); 4] = [
+    ("blanca", Color::new(255, 255, 255, 255)),
+    ("atardecer", Color::new(255, 190, 130, 255)),
+    ("blanca fría", Color::new(205, 222, 255, 255)),
+    ("enana roja", Color::new(255, 110, 70, 255)),
+];
+
+/// Frames por ráfaga de capturas si no se pasa `--burst`.
+const DEFAULT_BURST_FRAMES: u32 = 10;
+
+/// Ráfaga de capturas: cuántos frames faltan y el número del próximo archivo.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+struct Burst {
+    remaining: u32, // 0 = ninguna en curso
+    index: u32,     // numeración de los archivos
+}
+
+impl Burst {
+    fn new(frames: u32) -> Self {
+        Self { remaining: frames, index: 0 }
+    }
+
+    /// Nombre del archivo de este frame (`burst_000.png`, `burst_001.png`, ...) y avanza la
+    /// cuenta; `None` si la ráfaga ya terminó.
+    fn tick(&mut self) -> Option<String> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let name = format!("burst_{:03}.png", self.index);
+        self.index += 1;
+        self.remaining -= 1;
+        Some(name)
+    }
+}
+
+/// Salida estéreo de la demo.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum StereoMode {
+    Off,
+    SideBySide,
+    Anaglyph,
+}
+
+/// Cuantización a `SPACE_PALETTE` (look retro).
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum PaletteMode {
+    Off,
+    Nearest,
+    Dithered,
+}
+
+/// Demo de planetas: una `Scene` con el planeta actual y sus acompañantes, más los modelos
+/// precargados para rearmarla al cambiar de planeta y las opciones de visualización.
+struct PlanetDemo {
+    model_sphere: ObjModel,
+    model_crystal: ObjModel,
+    moon_model: ObjModel,
+    rings_model: ObjModel,
+    quad_model: ObjModel,
+    planets: Vec<PlanetConfig>, // los que recorre TAB; se pueden agregar más (`PlanetConfig::new`)
+    current_planet: usize,
+    scene: Scene,
+    moon_body: Option<usize>,  // índice en `scene.bodies`
+    rings_body: Option<usize>, // índice en `scene.bodies`
+    profiler: FrameProfiler,
+    quality: Option<QualityController>,
+    show_skybox: bool,
+    flat_preview: bool, // shader del planeta sobre un cuadrado que llena la pantalla
+    show_axis: bool,
+    show_grid: bool,   // rejilla de latitud/longitud sobre el planeta
+    grid_spacing: f32, // grados entre líneas de la rejilla
+    bloom: bool,
+    ssao: bool,
+    exposure: f32,
+    palette: PaletteMode,
+    stereo: StereoMode,
+    eye_separation: f32,
+    compare: Option<usize>, // planeta de `planets` cuyo shader va como B en la pantalla partida (None = apagada)
+    split: f32,                  // posición del divisor, fracción del ancho
+    show_moon: bool,
+    show_trail: bool,
+    moon_trail: VecDeque<Vector3>, // últimas posiciones de la luna (mundo), la más nueva al final
+    burst_frames: u32,    // cuántos frames captura una ráfaga
+    burst: Burst,         // ráfaga en curso
+    frame: u32,           // frames actualizados desde el inicio (va en los metadatos)
+    auto_frame: bool,     // encuadrar al iniciar y al cambiar de planeta (`--auto-frame`)
+    frame_fill: f32,      // fracción de la pantalla que ocupa la escena encuadrada
+    orbit: OrbitCamera,   // mueve `scene.camera` (mouse / flechas / encuadre)
+    light_preset: usize,  // índice en `LIGHT_PRESETS`
+    light_angle: Option<f32>, // giro de la luz alrededor de Y (None = la luz propia de cada shader)
+    planet_albedo: Color, // color promedio del planeta iluminado (planetshine sobre la luna)
+    ssaa: u32,            // supersampling fijo (`--ssaa`); con `--adaptive` lo decide la calidad
+    color_space: ColorSpace, // de las capturas y la hoja de contacto (`--linear-export`)
+    annotate_captures: bool, // quemar nombre y estado en las capturas (`--annotated-captures`)
+    asteroid_count: usize,   // rocas de los cinturones (`--asteroids`)
+    distribution: Distribution, // reparto de estrellas y rocas (`--uniform-sampling`)
+    stars: Starfield,        // estrellas del fondo (B), con `distribution`
+}
+
+/// Índice del planeta en `scene.bodies` (siempre el primero).
+const PLANET_BODY: usize = 0;
+
+/// Copia de `sphere-1.obj` dentro del ejecutable, por si se corre desde otro directorio.
+const BUILTIN_SPHERE: &str = include_str!("../sphere-1.obj");
+
+/// Z (vista) de la vista previa plana: a distancia 1 del ojo, x/y en [-1, 1] llenan la pantalla.
+const FLAT_PREVIEW_Z: f32 = -2.0;
+
+/// Zoom inicial de la escena.
+const DEFAULT_ZOOM: f32 = 1.5;
+
+impl PlanetDemo {
+    fn new(profile: bool, adaptive: bool, burst_frames: u32, camera: Camera, max_faces: Option<usize>, frame_fill: Option<f32>) -> Self {
+        println!("Cargando sphere-1.obj ...");
+        let mut model_sphere = ObjModel::load("sphere-1.obj").unwrap_or_else(|e| {
+            println!("No se pudo cargar sphere-1.obj ({}), se usa la copia embebida", e);
+            ObjModel::load_from_reader(BUILTIN_SPHERE.as_bytes()).expect("sphere-1.obj embebido inválido")
+        });
+
+        // Cada OBJ viene en su propia escala: se normaliza y se lleva al radio de la esfera
+        // para que el mismo encuadre (zoom = 1.5) sirva para ambos
+        let mut model_crystal = ObjModel::load_lenient("crystal_planet.obj")
+            .map(|mut m| {
+                m.scale_to_radius(model_sphere.bounding_radius());
+                m
+            })
+            .unwrap_or_else(|_| model_sphere.clone());
+
+        // Presupuesto de caras para OBJ pesados (después de escalar: la rejilla es relativa)
+        if let Some(budget) = max_faces {
+            for (name, model) in [("sphere-1.obj", &mut model_sphere), ("crystal_planet.obj", &mut model_crystal)] {
+                let before = model.faces.len();
+                model.decimate(budget);
+                if model.faces.len() < before {
+                    println!("{}: {} -> {} caras", name, before, model.faces.len());
+                }
+            }
+        }
+
+        let mut moon_model = generate_icosphere(0.3, 3);
+        moon_model.make_irregular(MOON_IRREGULARITY, MOON_LUMPINESS, seed_from_name("Luna"));
+        displace_craters(&mut moon_model, MOON_CRATERS, MOON_CRATER_DEPTH, seed_from_name("Luna"));
+        let rings_model = generate_rings(RING_INNER, RING_OUTER, 128, RING_THICKNESS);
+
+        println!(
+            "Modelos listos. Vertices luna: {}, anillos: {}",
+            moon_model.vertices.len(),
+            rings_model.vertices.len()
+        );
+
+        let mut scene = Scene::new(camera);
+        scene.zoom = DEFAULT_ZOOM;
+
+        let mut demo = Self {
+            model_sphere,
+            model_crystal,
+            moon_model,
+            rings_model,
+            quad_model: generate_quad(),
+            planets: default_planets(),
+            current_planet: 0,
+            scene,
+            moon_body: None,
+            rings_body: None,
+            profiler: FrameProfiler::new(profile),
+            // Objetivo 60 FPS; el último nivel agrega SSAA 2x si sobra tiempo
+            quality: adaptive.then(|| QualityController::new(16.6)),
+            show_skybox: false,
+            flat_preview: false,
+            show_axis: false,
+            show_grid: false,
+            grid_spacing: DEFAULT_GRID_SPACING,
+            bloom: false,
+            ssao: false,
+            exposure: DEFAULT_EXPOSURE,
+            palette: PaletteMode::Off,
+            stereo: StereoMode::Off,
+            eye_separation: DEFAULT_EYE_SEPARATION,
+            compare: None,
+            split: 0.5,
+            show_moon: true,
+            show_trail: false,
+            moon_trail: VecDeque::with_capacity(MOON_TRAIL_LEN),
+            burst_frames,
+            burst: Burst::default(),
+            frame: 0,
+            auto_frame: frame_fill.is_some(),
+            frame_fill: frame_fill.unwrap_or(DEFAULT_FRAME_FILL),
+            orbit: OrbitCamera::default(),
+            light_preset: 0,
+            light_angle: None,
+            planet_albedo: Color::BLACK,
+            ssaa: 1,
+            color_space: ColorSpace::Srgb,
+            annotate_captures: false,
+            asteroid_count: ASTEROID_COUNT,
+            distribution: Distribution::Halton,
+            stars: Starfield::new(STAR_COUNT, Distribution::Halton, seed_from_name("Estrellas")),
+        };
+        demo.load_planet(0);
+        demo
+    }
+
+    /// Cambia cómo se reparten las estrellas y las rocas de los cinturones; rehace el cielo y
+    /// recarga el planeta actual.
+    fn set_distribution(&mut self, distribution: Distribution) {
+        self.distribution = distribution;
+        self.stars = Starfield::new(STAR_COUNT, distribution, seed_from_name("Estrellas"));
+        self.load_planet(self.current_planet);
+    }
+
+    /// Gira la luz principal de la escena `angle` radianes alrededor de Y desde
+    /// `DEFAULT_LIGHT_DIR` (si la escena no tiene luces, agrega una del color actual);
+    /// `None` vuelve a la luz propia de cada shader, o a la dirección original con `--key-fill`.
+    fn set_light_angle(&mut self, angle: Option<f32>) {
+        self.light_angle = angle;
+        let Some(angle) = angle else {
+            if self.scene.lights.len() == 1 {
+                self.scene.lights.clear();
+            } else if let Some(light) = self.scene.lights.first_mut() {
+                light.direction = DEFAULT_LIGHT_DIR.normalized();
+            }
+            return;
+        };
+        let dir = transform_vertex(DEFAULT_LIGHT_DIR, Vector3::zero(), angle, 0.0, 1.0).normalized();
+        match self.scene.lights.first_mut() {
+            Some(light) => light.direction = dir,
+            None => self.scene.lights.push(Light { color: shader::light_color(), ..Light::directional(dir) }),
+        }
+    }
+
+    /// Acerca/aleja la cámara (sin girarla) para que los cuerpos visibles ocupen `frame_fill`
+    /// de la pantalla, sea cual sea la escala u origen del modelo. La órbita pasa a girar
+    /// alrededor del centro encuadrado.
+    fn frame_scene(&mut self) {
+        if let Some((center, radius)) = self.scene.bounding_sphere() {
+            self.orbit.target = center;
+            self.orbit.distance = self.scene.camera.framing_distance(radius, self.frame_fill);
+            self.orbit.apply(&mut self.scene.camera);
+        }
+    }
+
+    /// Agrega a `scene` el planeta con su luna y anillos (si tiene). Devuelve los índices de
+    /// la luna y de los anillos; el planeta queda en `PLANET_BODY` si la escena estaba vacía.
+    fn add_planet_bodies(&self, scene: &mut Scene, planet: &PlanetConfig) -> (Option<usize>, Option<usize>) {
+        let mesh = if planet.crystal_model { &self.model_crystal } else { &self.model_sphere };
+        let mut body = Body::new(planet.name, mesh.clone(), planet.shader.clone());
+        body.spin = PLANET_SPIN;
+        body.sphere = !planet.crystal_model;
+        let planet_body = scene.add_body(body);
+
+        // Luna, anillos y rocas cuelgan del planeta y heredan su giro: sus velocidades van
+        // relativas a él (restando `PLANET_SPIN`)
+        let moon_body = planet.moon.map(|shader| {
+            let mut moon = Body::new("Luna", self.moon_model.clone(), shader);
+            moon.orbit = Some(Orbit::new(2.5, 0.5, 1.2 - PLANET_SPIN));
+            moon.parent = Some(planet_body);
+            moon.scale = 0.6;
+            moon.spin = PLANET_SPIN * (0.5 - 1.0);
+            moon.visible = self.show_moon;
+            scene.add_body(moon)
+        });
+
+        let rings_body = planet.rings.map(|shader| {
+            let mut rings = Body::new("Anillos", self.rings_model.clone(), shader);
+            rings.parent = Some(planet_body);
+            rings.tilt = RING_TILT;
+            rings.spin = PLANET_SPIN * (RING_SPIN - 1.0);
+            scene.add_body(rings)
+        });
+
+        if planet.belt {
+            let field = generate_asteroid_field(self.asteroid_count, BELT_INNER, BELT_OUTER, self.distribution, seed_from_name(planet.name));
+            for (mesh, position, spin) in field {
+                let radius = position.x.hypot(position.z);
+                let mut rock = Body::new("Asteroide", mesh, ShaderType::Rocky);
+                rock.orbit = Some(Orbit {
+                    angle: position.z.atan2(position.x),
+                    ..Orbit::new(radius, position.y, BELT_SPEED * (BELT_INNER / radius).powf(1.5) - PLANET_SPIN)
+                });
+                rock.parent = Some(planet_body);
+                rock.spin = PLANET_SPIN * (spin - 1.0);
+                scene.add_body(rock);
+            }
+        }
+
+        (moon_body, rings_body)
+    }
+
+    /// Rearma los cuerpos de la escena para el planeta `index` de `planets`. El giro y el
+    /// pulso del planeta se conservan entre planetas.
+    fn load_planet(&mut self, index: usize) {
+        self.current_planet = index;
+        let (rotation_y, pulse) = self
+            .scene
+            .bodies
+            .get(PLANET_BODY)
+            .map_or((0.0, 0.0), |b| (b.rotation_y, b.pulse));
+
+        let mut scene = std::mem::replace(&mut self.scene, Scene::new(Camera::default()));
+        scene.bodies.clear();
+        (self.moon_body, self.rings_body) = self.add_planet_bodies(&mut scene, &self.planets[index]);
+        scene.bodies[PLANET_BODY].rotation_y = rotation_y;
+        scene.bodies[PLANET_BODY].pulse = pulse;
+        self.planet_albedo = planet_albedo(&scene);
+        self.scene = scene;
+        self.moon_trail.clear();
+        if self.auto_frame {
+            self.frame_scene();
+        }
+    }
+
+    /// Hoja de contacto: cada planeta de `planets` en su celda de `cell_w x cell_h`, de
+    /// izquierda a derecha en `cols` columnas, todos desde el mismo ángulo y con el modo de
+    /// render actual. Con `parallel` las celdas se renderizan a la vez: cada escena lleva sus
+    /// anillos, planetshine y rejilla en `Scene::uniforms` en vez de los globales.
+    fn render_planet_grid(&self, planets: &[PlanetConfig], cell_w: u32, cell_h: u32, cols: u32) -> Image {
+        let cols = cols.max(1);
+        let rows = (planets.len() as u32).div_ceil(cols).max(1);
+        let mut grid = Framebuffer::new(cols * cell_w, rows * cell_h, BACKGROUND);
+
+        // Armar las escenas es barato y usa `self`; se hace en serie
+        let scenes: Vec<Scene> = planets.iter().map(|planet| self.grid_scene(planet)).collect();
+        let (show_skybox, stars) = (self.show_skybox, &self.stars);
+        // Cada celda devuelve sus colores: el `Image` del framebuffer no se puede mandar entre hilos
+        let render_cell = |mut scene: Scene| {
+            let mut cell = Framebuffer::new(cell_w, cell_h, BACKGROUND);
+            if show_skybox {
+                render_skybox(&mut cell, &scene.camera, |d| shader::starfield_env(d, stars));
+            }
+            scene.render(&mut cell);
+            cell.pixels()
+        };
+        #[cfg(feature = "parallel")]
+        let cells: Vec<Vec<Color>> = {
+            use rayon::prelude::*;
+            scenes.into_par_iter().map(render_cell).collect()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let cells: Vec<Vec<Color>> = scenes.into_iter().map(render_cell).collect();
+
+        for (i, cell) in cells.iter().enumerate() {
+            let (col, row) = (i as u32 % cols, i as u32 / cols);
+            grid.blit_pixels(cell, cell_w, (col * cell_w) as i32, (row * cell_h) as i32);
+        }
+        grid.color_buffer
+    }
+
+    /// Escena de una celda de la hoja de contacto: `planet` con encuadre fijo y el estado
+    /// de los shaders propio (`Scene::uniforms`).
+    fn grid_scene(&self, planet: &PlanetConfig) -> Scene {
+        // Encuadre fijo, pero con el mismo recorte near/far y fov que la vista interactiva
+        let Camera { near, far, fov, .. } = self.scene.camera;
+        let camera = Camera { near, far, fov, ..Camera::default() };
+        let mut scene = Scene::new(camera);
+        scene.lights = self.scene.lights.clone();
+        scene.zoom = DEFAULT_ZOOM;
+        scene.time = self.scene.time;
+        scene.mode = self.scene.mode;
+        scene.smooth = self.scene.smooth;
+        scene.analytic_normals = self.scene.analytic_normals;
+        scene.camera_relative = self.scene.camera_relative;
+        scene.dither_strength = self.scene.dither_strength;
+        scene.impostor_distance = self.scene.impostor_distance;
+        scene.system_tilt = self.scene.system_tilt;
+        let (moon_body, rings_body) = self.add_planet_bodies(&mut scene, planet);
+        if let Some(orbit) = moon_body.and_then(|m| scene.bodies[m].orbit.as_mut()) {
+            orbit.angle = GRID_MOON_ANGLE;
+        }
+
+        scene.uniforms = Some(Uniforms {
+            ring_shadow: ring_shadow(&scene, rings_body, &scene.camera),
+            planet_shine: planet_shine(&scene, planet_albedo(&scene), &scene.camera),
+            globe_grid: self.show_grid.then(|| globe_grid(&scene, self.grid_spacing, &scene.camera)).flatten(),
+            ..Uniforms::default()
+        });
+        scene
+    }
+
+    /// Vista previa plana: el shader del planeta sobre `quad_model` a distancia 1 del ojo,
+    /// donde x, y en [-1, 1] llenan justo la pantalla. Ignora la cámara y los demás cuerpos.
+    fn draw_flat_preview(&self, fb: &mut Framebuffer) {
+        let view: Vec<Vector3> = self
+            .quad_model
+            .vertices
+            .iter()
+            .map(|v| Vector3::new(v.x, v.y, FLAT_PREVIEW_Z))
+            .collect();
+        let scene = &self.scene;
+        let shader = &*scene.bodies[PLANET_BODY].shader;
+        draw_model(fb, &scene.camera, &self.quad_model, &view, None, None, shader, scene.time, scene.mode, scene.dither_strength, Some(scene.frame_uniforms()));
+    }
+
+    /// Lo necesario para volver a renderizar el frame actual.
+    fn capture_metadata(&self) -> CaptureMetadata {
+        let planet = self.planets[self.current_planet].name;
+        CaptureMetadata {
+            frame: self.frame,
+            planet: planet.to_string(),
+            planet_index: self.current_planet,
+            seed: seed_from_name(planet),
+            noise_seed: shader::noise_seed(),
+            time: self.scene.time,
+            angle: self.scene.bodies.get(PLANET_BODY).map_or(0.0, |b| b.rotation_y),
+            zoom: self.scene.zoom,
+            mode: self.scene.mode.name().to_string(),
+            smooth: self.scene.smooth,
+            camera: self.scene.camera,
+            light_direction: self.scene.lights.first().map_or(Vector3::new(0.0, 0.0, 0.0), |l| l.direction),
+            color_space: self.color_space,
+        }
+    }
+
+    /// Guarda el frame en `path` y sus metadatos al lado (mismo nombre, `.json`).
+    fn save_capture(&self, fb: &Framebuffer, path: &str) {
+        self.capture_frame(fb).as_ref().unwrap_or(fb).render_to_file(path, self.color_space);
+        if let Err(e) = self.capture_metadata().save_beside(path) {
+            println!("No se pudieron guardar los metadatos de {}: {}", path, e);
+        }
+    }
+
+    /// Con `annotate_captures`, la copia anotada de `fb` que se guarda en su lugar.
+    fn capture_frame(&self, fb: &Framebuffer) -> Option<Framebuffer> {
+        self.annotate_captures.then(|| self.annotated(fb))
+    }
+
+    /// Copia de `fb` con el nombre del planeta y la línea de estado del HUD escritos encima
+    /// (con la fuente de `Framebuffer::draw_text`, escalada al supersampling del buffer).
+    fn annotated(&self, fb: &Framebuffer) -> Framebuffer {
+        let mut out = Framebuffer::from_image(fb.color_buffer.clone());
+        out.supersample = fb.supersample;
+        let s = fb.supersample as i32;
+        let bottom = out.height as i32;
+        out.draw_text(self.planets[self.current_planet].name, 10 * s, 10 * s, 2 * s, Color::WHITE);
+        out.draw_text(&self.status_line(), 10 * s, bottom - 20 * s, s, Color::LIGHTGRAY);
+        out
+    }
+
+    /// "Modo: ... | Exposición ... | Luz ...": la línea de estado del HUD.
+    fn status_line(&self) -> String {
+        let shading = match (self.scene.analytic_normals, self.scene.smooth) {
+            (true, _) => "normal exacta",
+            (false, true) => "suave",
+            (false, false) => "plano",
+        };
+        format!(
+            "Modo: {} ({}) | Exposición {:.2}x | Luz {}",
+            self.scene.mode.name(),
+            shading,
+            self.exposure,
+            LIGHT_PRESETS[self.light_preset].0
+        )
+    }
+
+    /// Dibuja fondo, cuerpos y ayudas visuales vistos desde `camera`.
+    fn draw_scene(&mut self, fb: &mut Framebuffer, camera: &Camera) {
+        if self.flat_preview {
+            self.draw_flat_preview(fb);
+            return;
+        }
+        if self.show_skybox {
+            render_skybox(fb, camera, |d| shader::starfield_env(d, &self.stars));
+        }
+
+        let zoom = self.scene.zoom;
+        shader::set_ring_shadow(ring_shadow(&self.scene, self.rings_body, camera));
+        shader::set_planet_shine(planet_shine(&self.scene, self.planet_albedo, camera));
+        shader::set_globe_grid(self.show_grid.then(|| globe_grid(&self.scene, self.grid_spacing, camera)).flatten());
+
+        self.scene.render_from(fb, camera, &mut self.profiler);
+
+        if self.show_axis {
+            let planet = &self.scene.bodies[PLANET_BODY];
+            let (south, north) = spin_axis(
+                planet.mesh.bounding_radius(),
+                1.4,
+                self.scene.body_position(PLANET_BODY),
+                planet.rotation_y,
+                planet.tilt,
+                planet.scale * zoom,
+            );
+            draw_spin_axis(fb, camera, south, north);
+        }
+
+        if self.show_trail && self.show_moon && self.moon_body.is_some() {
+            draw_trail(fb, camera, &self.moon_trail);
+        }
+    }
+}
+
+/// Sombra de los anillos sobre el planeta: el plano del cuerpo `rings_body` en espacio de vista.
+fn ring_shadow(scene: &Scene, rings_body: Option<usize>, camera: &Camera) -> Option<RingShadow> {
+    rings_body.map(|i| {
+        let origin = scene.body_position(i);
+        // Eje Y de los anillos en el mundo: su inclinación compuesta con el giro del planeta
+        let world = scene.world_transform(i);
+        let up = origin + Vector3::new(world.m4, world.m5, world.m6).normalized();
+        let center = camera.to_view(origin);
+        let scale = scene.world_scale(i) * scene.zoom;
+        RingShadow {
+            center,
+            normal: camera.to_view(up) - center,
+            inner: RING_INNER * scale,
+            outer: RING_OUTER * scale,
+        }
+    })
+}
+
+/// Grosor (grados de arco) y color de las líneas de la rejilla de latitud/longitud.
+const GLOBE_GRID_WIDTH: f32 = 0.8;
+const GLOBE_GRID_COLOR: Color = Color::new(230, 240, 255, 255);
+/// Separación (grados) de la rejilla con la tecla J si no se pasa `--grid-spacing`.
+const DEFAULT_GRID_SPACING: f32 = 15.0;
+
+/// Rejilla de latitud/longitud cada `spacing` grados sobre el planeta de `scene`, en espacio
+/// de vista; los ejes giran y se inclinan con el planeta.
+fn globe_grid(scene: &Scene, spacing: f32, camera: &Camera) -> Option<GlobeGrid> {
+    let planet = scene.bodies.get(PLANET_BODY)?;
+    let origin = scene.body_position(PLANET_BODY);
+    let center = camera.to_view(origin);
+    let axis = |x: f32, y: f32, z: f32| {
+        camera.to_view(transform_vertex(Vector3::new(x, y, z), origin, planet.rotation_y, planet.tilt, 1.0)) - center
+    };
+    Some(GlobeGrid {
+        center,
+        radius: planet.mesh.bounding_radius() * planet.scale * scene.zoom,
+        axes: [axis(1.0, 0.0, 0.0), axis(0.0, 1.0, 0.0), axis(0.0, 0.0, 1.0)],
+        spacing,
+        width: GLOBE_GRID_WIDTH,
+        color: GLOBE_GRID_COLOR,
+    })
+}
+
+/// Puntos con que se promedia el color del planeta para el planetshine.
+const ALBEDO_SAMPLES: u32 = 256;
+
+/// Dirección hacia la luz principal de `scene` (sin luces, la de la escena por defecto).
+fn main_light_dir(scene: &Scene) -> Vector3 {
+    scene.lights.first().map_or(DEFAULT_LIGHT_DIR, |l| l.direction).normalized()
+}
+
+/// Color promedio del lado iluminado del planeta de `scene` (negro si no hay planeta).
+fn planet_albedo(scene: &Scene) -> Color {
+    let Some(planet) = scene.bodies.get(PLANET_BODY) else {
+        return Color::BLACK;
+    };
+    triangle::average_lit_color(&*planet.shader, main_light_dir(scene), scene.time, ALBEDO_SAMPLES)
+}
+
+/// Planetshine del planeta de `scene` sobre los demás cuerpos, en espacio de vista.
+fn planet_shine(scene: &Scene, albedo: Color, camera: &Camera) -> Option<PlanetShine> {
+    let planet = scene.bodies.get(PLANET_BODY)?;
+    Some(PlanetShine {
+        center: camera.to_view(scene.body_position(PLANET_BODY)),
+        radius: planet.mesh.bounding_radius() * planet.scale * scene.zoom,
+        albedo,
+        // Los shaders iluminan con la luz fija en espacio de vista (no gira con la cámara)
+        light_dir: main_light_dir(scene),
+    })
+}
+
+/// Siguiente planeta (índice en `planets`) cuyo shader va como B en la comparación: los
+/// demás en orden, sin `current`, y después del último `None` (apagada).
+fn next_compare(compare: Option<usize>, current: usize, count: usize) -> Option<usize> {
+    let start = compare.map_or(0, |b| b + 1);
+    (start..count).find(|&i| i != current)
+}
+
+/// Color de fondo de la ventana y de las capturas.
+const BACKGROUND: Color = Color::new(5, 5, 15, 255);
+
+/// Celdas de la hoja de contacto (tecla K) y ángulo orbital fijo de la luna en ellas
+/// (detrás y a la derecha del planeta, visible en la celda).
+const GRID_CELL_W: u32 = 320;
+const GRID_CELL_H: u32 = 240;
+const GRID_COLS: u32 = 3;
+const GRID_MOON_ANGLE: f32 = 1.2;
+
+/// Giro del planeta (rad/s); la luna y los anillos giran a una fracción de esto.
+const PLANET_SPIN: f32 = 0.6;
+
+/// Amplitud (fracción del radio) de la respiración del planeta (tecla U).
+const PULSE_AMPLITUDE: f32 = 0.03;
+
+/// Posiciones que guarda la estela de la luna (a 60 FPS, ~1.5 s de órbita).
+const MOON_TRAIL_LEN: usize = 90;
+const TRAIL_COLOR: Color = Color::new(170, 200, 255, 255);
+
+/// Agrega `position` al final de la estela; si ya tiene `MOON_TRAIL_LEN` puntos descarta
+/// el más viejo (buffer circular).
+fn push_trail(trail: &mut VecDeque<Vector3>, position: Vector3) {
+    while trail.len() >= MOON_TRAIL_LEN {
+        trail.pop_front();
+    }
+    trail.push_back(position);
+}
+
+/// Brillo (0..1] del segmento `segment` (0 = el más viejo) de una estela de `points` puntos:
+/// crece con cada segmento hasta 1 en el más nuevo.
+fn trail_fade(segment: usize, points: usize) -> f32 {
+    (segment + 1) as f32 / points.saturating_sub(1).max(1) as f32
+}
+
+/// Estela como polilínea con profundidad; cada segmento se oscurece según su edad
+/// (el más viejo casi negro, el más nuevo con `TRAIL_COLOR` completo).
+fn draw_trail(fb: &mut Framebuffer, camera: &Camera, trail: &VecDeque<Vector3>) {
+    for (i, (&a, &b)) in trail.iter().zip(trail.iter().skip(1)).enumerate() {
+        let fade = trail_fade(i, trail.len());
+        let dim = |v: u8| (v as f32 * fade) as u8;
+        fb.set_current_color(Color::new(dim(TRAIL_COLOR.r), dim(TRAIL_COLOR.g), dim(TRAIL_COLOR.b), 255));
+
+        let (a, b) = (camera.to_view(a), camera.to_view(b));
+        if !camera.in_depth_range(a) || !camera.in_depth_range(b) {
+            continue;
+        }
+        let pa = camera.project(a, fb.width, fb.height);
+        let pb = camera.project(b, fb.width, fb.height);
+        line_depth(fb, pa, pb, a.z, b.z, 0.0);
+    }
+}
 
-    while !window.window_should_close() {
-        fb.clear();
-        time += 0.016;
-        orbital_angle += 0.02;
+/// Color del eje de giro y de la marca del polo norte.
+const AXIS_COLOR: Color = Color::new(230, 230, 240, 255);
+const NORTH_COLOR: Color = Color::new(255, 80, 60, 255);
+
+/// Dibuja el eje de giro (sur -> norte) con profundidad: la parte que atraviesa el planeta
+/// queda oculta. El polo norte lleva una marca de 3x3 píxeles.
+fn draw_spin_axis(fb: &mut Framebuffer, camera: &Camera, south: Vector3, north: Vector3) {
+    let (south, north) = (camera.to_view(south), camera.to_view(north));
+    if !camera.in_depth_range(south) || !camera.in_depth_range(north) {
+        return;
+    }
+    let p_south = camera.project(south, fb.width, fb.height);
+    let p_north = camera.project(north, fb.width, fb.height);
+
+    fb.set_current_color(AXIS_COLOR);
+    line_depth(fb, p_south, p_north, south.z, north.z, 0.0);
+
+    fb.set_current_color(NORTH_COLOR);
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            fb.set_pixel_depth(p_north.x as i32 + dx, p_north.y as i32 + dy, north.z);
+        }
+    }
+}
+
+impl RenderApp for PlanetDemo {
+    fn handle_input(&mut self, window: &RaylibHandle, fb: &mut Framebuffer) {
+        // Antes de dibujar: cambiar el factor recrea los buffers
+        fb.set_supersample(self.quality.as_ref().map_or(self.ssaa, |c| c.quality().ssaa));
 
         if window.is_key_pressed(KeyboardKey::KEY_TAB) {
-            current_planet = (current_planet + 1) % planet_names.len();
-            println!("Cambiado a: {}", planet_names[current_planet]);
+            self.load_planet((self.current_planet + 1) % self.planets.len());
+            let planet = &self.planets[self.current_planet];
+            println!("Cambiado a: {} ({})", planet.name, planet.model_desc);
         }
 
         if window.is_key_pressed(KeyboardKey::KEY_P) {
-            auto_rotate = !auto_rotate;
-            println!("Auto-rotación: {}", if auto_rotate { "ON" } else { "OFF" });
+            self.scene.auto_rotate = !self.scene.auto_rotate;
+            println!("Auto-rotación: {}", if self.scene.auto_rotate { "ON" } else { "OFF" });
         }
 
         if window.is_key_down(KeyboardKey::KEY_A) {
-            angle_y -= 0.02;
+            self.scene.bodies[PLANET_BODY].rotation_y -= 0.02;
         }
 
         if window.is_key_down(KeyboardKey::KEY_D) {
-            angle_y += 0.02;
+            self.scene.bodies[PLANET_BODY].rotation_y += 0.02;
         }
 
         if window.is_key_down(KeyboardKey::KEY_W) {
-            scale *= 1.02;
+            self.scene.zoom *= 1.02;
         }
 
         if window.is_key_down(KeyboardKey::KEY_S) {
-            scale /= 1.02;
+            self.scene.zoom /= 1.02;
         }
 
         if window.is_key_pressed(KeyboardKey::KEY_R) {
-            angle_y = 0.0;
-            scale = 1.5;
+            for body in &mut self.scene.bodies {
+                body.rotation_y = 0.0;
+            }
+            self.scene.zoom = DEFAULT_ZOOM;
+            self.orbit = OrbitCamera::default();
+            self.orbit.apply(&mut self.scene.camera);
+            self.set_light_angle(None);
+            self.scene.system_tilt = 0.0;
             println!("Vista reiniciada");
         }
 
-        if window.is_key_pressed(KeyboardKey::KEY_C) {
-            fb.render_to_file("capture.png");
-            println!("Captura guardada: capture.png");
+        // Órbita: arrastre con el botón izquierdo (salvo que mueva el divisor de la
+        // comparación) o flechas; la rueda acerca/aleja
+        let mut d_azimuth = 0.0;
+        let mut d_elevation = 0.0;
+        if self.compare.is_none() && window.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+            let delta = window.get_mouse_delta();
+            d_azimuth -= delta.x * ORBIT_SENSITIVITY;
+            d_elevation += delta.y * ORBIT_SENSITIVITY;
+        }
+        if window.is_key_down(KeyboardKey::KEY_LEFT) {
+            d_azimuth += ORBIT_KEY_SPEED;
+        }
+        if window.is_key_down(KeyboardKey::KEY_RIGHT) {
+            d_azimuth -= ORBIT_KEY_SPEED;
+        }
+        if window.is_key_down(KeyboardKey::KEY_UP) {
+            d_elevation += ORBIT_KEY_SPEED;
+        }
+        if window.is_key_down(KeyboardKey::KEY_DOWN) {
+            d_elevation -= ORBIT_KEY_SPEED;
+        }
+        let wheel = window.get_mouse_wheel_move();
+        if d_azimuth != 0.0 || d_elevation != 0.0 || wheel != 0.0 {
+            self.orbit.rotate(d_azimuth, d_elevation);
+            self.orbit.dolly(DOLLY_STEP.powf(wheel));
+            self.orbit.apply(&mut self.scene.camera);
+        }
+
+        if window.is_key_pressed(KeyboardKey::KEY_E) {
+            self.frame_scene();
+            println!("Escena encuadrada ({:.0}% de la pantalla)", self.frame_fill * 100.0);
         }
 
-        if auto_rotate {
-            angle_y += 0.01;
+        if window.is_key_pressed(KeyboardKey::KEY_M) {
+            self.scene.mode = self.scene.mode.next();
+            println!("Modo de render: {}", self.scene.mode.name());
         }
 
-        // --- Render principal ---
-        let current_model = if current_planet == 2 { &model_crystal } else { &model_sphere };
+        if window.is_key_pressed(KeyboardKey::KEY_F) {
+            self.scene.smooth = !self.scene.smooth;
+            println!("Sombreado: {}", if self.scene.smooth { "suave" } else { "plano" });
+        }
 
-        let rotated = transform_model(current_model, Vector3::new(0.0, 0.0, 0.0), angle_y, 0.0, scale);
+        if window.is_key_pressed(KeyboardKey::KEY_H) {
+            self.scene.analytic_normals = !self.scene.analytic_normals;
+            println!("Normales analíticas de esfera: {}", if self.scene.analytic_normals { "ON" } else { "OFF" });
+        }
 
-        let shader_type = match current_planet {
-            0 => ShaderType::Rocky,
-            1 => ShaderType::Gas,
-            2 => ShaderType::Crystal,
-            3 => ShaderType::Lava,
-            _ => ShaderType::Ice,
-        };
+        if window.is_key_pressed(KeyboardKey::KEY_Q) {
+            self.flat_preview = !self.flat_preview;
+            println!("Vista plana del shader: {}", if self.flat_preview { "ON" } else { "OFF" });
+        }
 
-        for face in &current_model.faces {
-            if face.len() < 3 { continue; }
-            for i in 1..(face.len() - 1) {
-                let v0 = rotated[face[0]];
-                let v1 = rotated[face[i]];
-                let v2 = rotated[face[i + 1]];
-                triangle::draw_filled_triangle(&mut fb, v0, v1, v2, shader_type, time);
+        if window.is_key_pressed(KeyboardKey::KEY_Z) {
+            self.palette = match self.palette {
+                PaletteMode::Off => PaletteMode::Nearest,
+                PaletteMode::Nearest => PaletteMode::Dithered,
+                PaletteMode::Dithered => PaletteMode::Off,
+            };
+            let name = match self.palette {
+                PaletteMode::Off => "OFF",
+                PaletteMode::Nearest => "más cercano",
+                PaletteMode::Dithered => "con dithering",
+            };
+            println!("Paleta: {}", name);
+        }
+
+        if window.is_key_pressed(KeyboardKey::KEY_I) {
+            self.light_preset = (self.light_preset + 1) % LIGHT_PRESETS.len();
+            let (name, color) = LIGHT_PRESETS[self.light_preset];
+            shader::set_light_color(color);
+            // La luz que agregó el giro (la única) también cambia de color
+            if let [light] = self.scene.lights.as_mut_slice() {
+                light.color = color;
             }
+            println!("Luz: {} ({}, {}, {})", name, color.r, color.g, color.b);
         }
 
-        if current_planet == 0 {
-            let moon_distance = 2.5;
-            let moon_x = orbital_angle.cos() * moon_distance;
-            let moon_z = orbital_angle.sin() * moon_distance;
-            let moon_transformed = transform_model(&moon_model, Vector3::new(moon_x * scale, 0.5 * scale, moon_z * scale), angle_y * 0.5, 0.0, scale * 0.6);
-            for face in &moon_model.faces {
-                if face.len() < 3 { continue; }
-                for i in 1..(face.len() - 1) {
-                    let v0 = moon_transformed[face[0]];
-                    let v1 = moon_transformed[face[i]];
-                    let v2 = moon_transformed[face[i + 1]];
-                    triangle::draw_filled_triangle(&mut fb, v0, v1, v2, ShaderType::Ice, time);
-                }
+        // 1: otra semilla de ruido (mismo planeta, otro aspecto); la secuencia es fija
+        if window.is_key_pressed(KeyboardKey::KEY_ONE) {
+            let seed = shader::noise_seed().wrapping_mul(0x9E37_79B9).wrapping_add(0x7F4A_7C15);
+            shader::set_noise_seed(seed);
+            println!("Semilla del ruido: {}", seed);
+        }
+
+        // 9 / 0: gira la luz alrededor del planeta (mueve el terminador)
+        let mut d_light = 0.0;
+        if window.is_key_down(KeyboardKey::KEY_NINE) {
+            d_light += LIGHT_ORBIT_SPEED;
+        }
+        if window.is_key_down(KeyboardKey::KEY_ZERO) {
+            d_light -= LIGHT_ORBIT_SPEED;
+        }
+        if d_light != 0.0 {
+            self.set_light_angle(Some(self.light_angle.unwrap_or(0.0) + d_light));
+        }
+
+        // 2 / 3: inclina el plano de todas las órbitas (de canto a verlas de frente)
+        let mut d_tilt = 0.0;
+        if window.is_key_pressed(KeyboardKey::KEY_TWO) {
+            d_tilt -= SYSTEM_TILT_STEP;
+        }
+        if window.is_key_pressed(KeyboardKey::KEY_THREE) {
+            d_tilt += SYSTEM_TILT_STEP;
+        }
+        if d_tilt != 0.0 {
+            self.scene.system_tilt = (self.scene.system_tilt + d_tilt).clamp(-PI / 2.0, PI / 2.0);
+            println!("Inclinación de las órbitas: {:.0}°", self.scene.system_tilt.to_degrees());
+        }
+
+        if window.is_key_pressed(KeyboardKey::KEY_B) {
+            self.show_skybox = !self.show_skybox;
+            println!("Fondo estelar: {}", if self.show_skybox { "ON" } else { "OFF" });
+        }
+
+        if window.is_key_pressed(KeyboardKey::KEY_X) {
+            self.show_axis = !self.show_axis;
+            println!("Eje de giro: {}", if self.show_axis { "ON" } else { "OFF" });
+        }
+
+        if window.is_key_pressed(KeyboardKey::KEY_J) {
+            self.show_grid = !self.show_grid;
+            println!("Rejilla lat/long: {}", if self.show_grid { "ON" } else { "OFF" });
+        }
+
+        if window.is_key_pressed(KeyboardKey::KEY_U) {
+            let planet = &mut self.scene.bodies[PLANET_BODY];
+            planet.pulse = if planet.pulse == 0.0 { PULSE_AMPLITUDE } else { 0.0 };
+            println!("Pulso: {}", if planet.pulse != 0.0 { "ON" } else { "OFF" });
+        }
+
+        if window.is_key_pressed(KeyboardKey::KEY_L) {
+            self.show_moon = !self.show_moon;
+            if let Some(i) = self.moon_body {
+                self.scene.bodies[i].visible = self.show_moon;
             }
+            println!("Luna: {}", if self.show_moon { "ON" } else { "OFF" });
         }
 
-        if current_planet == 1 {
-            let rings_transformed = transform_model(&rings_model, Vector3::new(0.0, 0.0, 0.0), angle_y * 0.3, 0.35, scale);
-            for face in &rings_model.faces {
-                if face.len() < 3 { continue; }
-                for i in 1..(face.len() - 1) {
-                    let v0 = rings_transformed[face[0]];
-                    let v1 = rings_transformed[face[i]];
-                    let v2 = rings_transformed[face[i + 1]];
-                    triangle::draw_filled_triangle(&mut fb, v0, v1, v2, ShaderType::Crystal, time);
-                }
+        if window.is_key_pressed(KeyboardKey::KEY_T) {
+            self.show_trail = !self.show_trail;
+            println!("Estela de la luna: {}", if self.show_trail { "ON" } else { "OFF" });
+        }
+
+        if window.is_key_pressed(KeyboardKey::KEY_G) {
+            self.bloom = !self.bloom;
+            println!("Bloom: {}", if self.bloom { "ON" } else { "OFF" });
+        }
+
+        if window.is_key_pressed(KeyboardKey::KEY_O) {
+            self.ssao = !self.ssao;
+            println!("SSAO: {}", if self.ssao { "ON" } else { "OFF" });
+        }
+
+        if window.is_key_pressed(KeyboardKey::KEY_V) {
+            self.stereo = match self.stereo {
+                StereoMode::Off => StereoMode::SideBySide,
+                StereoMode::SideBySide => StereoMode::Anaglyph,
+                StereoMode::Anaglyph => StereoMode::Off,
+            };
+            let name = match self.stereo {
+                StereoMode::Off => "OFF",
+                StereoMode::SideBySide => "lado a lado",
+                StereoMode::Anaglyph => "anaglifo",
+            };
+            println!("Estéreo: {}", name);
+        }
+
+        // Comparación A/B: Y recorre los shaders de los demás planetas como B y después apaga
+        if window.is_key_pressed(KeyboardKey::KEY_Y) {
+            self.compare = next_compare(self.compare, self.current_planet, self.planets.len());
+            match self.compare {
+                Some(b) => println!("Comparar: A {} | B {}", self.scene.bodies[PLANET_BODY].shader.name(), self.planets[b].shader.name()),
+                None => println!("Comparar: OFF"),
+            }
+        }
+        if self.compare.is_some() {
+            // Divisor: arrastre con el mouse (la imagen va con letterbox) o , y .
+            if window.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+                let (out_w, out_h) = fb.display_size();
+                let rect = app::letterbox_rect(window.get_screen_width(), window.get_screen_height(), out_w, out_h);
+                self.split = ((window.get_mouse_position().x - rect.x) / rect.width.max(1.0)).clamp(0.0, 1.0);
+            }
+            if window.is_key_down(KeyboardKey::KEY_COMMA) {
+                self.split = (self.split - 0.005).max(0.0);
+            }
+            if window.is_key_down(KeyboardKey::KEY_PERIOD) {
+                self.split = (self.split + 0.005).min(1.0);
             }
         }
 
-        // --- Render final ---
-        let pixels: Vec<Color> = fb.image_data();
-        let mut raw: Vec<u8> = Vec::with_capacity(pixels.len() * 4);
-        for c in pixels {
-            raw.extend_from_slice(&[c.r, c.g, c.b, c.a]);
+        // [ / ]: separación entre ojos
+        if window.is_key_down(KeyboardKey::KEY_LEFT_BRACKET) {
+            self.eye_separation = (self.eye_separation - 0.002).max(0.0);
+        }
+        if window.is_key_down(KeyboardKey::KEY_RIGHT_BRACKET) {
+            self.eye_separation = (self.eye_separation + 0.002).min(1.0);
         }
 
-        if fb.texture.is_none() {
-            fb.init_texture(&mut window, &thread);
+        // Exposición en pasos de 1/4 de stop
+        if window.is_key_pressed(KeyboardKey::KEY_EQUAL) {
+            self.exposure = (self.exposure * EXPOSURE_STEP).min(MAX_EXPOSURE);
+        }
+        if window.is_key_pressed(KeyboardKey::KEY_MINUS) {
+            self.exposure = (self.exposure / EXPOSURE_STEP).max(1.0 / MAX_EXPOSURE);
         }
 
-        if let Some(tex) = &mut fb.texture {
-            tex.update_texture(&raw);
+        if window.is_key_pressed(KeyboardKey::KEY_C) {
+            self.save_capture(fb, "capture.png");
+            println!("Captura guardada: capture.png (+ capture.json)");
+        }
 
-            let mut d = window.begin_drawing(&thread);
-            d.clear_background(Color::BLACK);
-            d.draw_texture(tex, 0, 0, Color::WHITE);
+        if window.is_key_pressed(KeyboardKey::KEY_K) {
+            let sheet = self.render_planet_grid(&self.planets, GRID_CELL_W, GRID_CELL_H, GRID_COLS);
+            Framebuffer::from_image(sheet).render_to_file("planet_grid.png", self.color_space);
+            println!("Hoja de contacto guardada: planet_grid.png");
+        }
 
-            d.draw_text(&planet_names[current_planet], 10, 10, 20, Color::WHITE);
-            d.draw_text(
-                "Controles: TAB planeta | P pausa | W/S zoom | A/D rotar | R reiniciar | C captura",
-                10,
-                570,
-                14,
-                Color::LIGHTGRAY,
-            );
+        if window.is_key_pressed(KeyboardKey::KEY_N) && self.burst.remaining == 0 {
+            self.burst = Burst::new(self.burst_frames);
+            println!("Ráfaga: capturando {} frames", self.burst_frames);
+        }
+
+        // Ráfaga en curso: un archivo numerado por frame hasta llegar a cero
+        if let Some(name) = self.burst.tick() {
+            self.save_capture(fb, &name);
+            if self.burst.remaining == 0 {
+                println!("Ráfaga terminada: {} archivos burst_*.png (+ .json)", self.burst.index);
+            }
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.scene.update(dt);
+        self.frame += 1;
+
+        // Estela de la luna: buffer circular de las últimas MOON_TRAIL_LEN posiciones
+        if let Some(i) = self.moon_body {
+            let moon = self.scene.body_position(i);
+            push_trail(&mut self.moon_trail, moon);
         }
     }
 
+    fn draw(&mut self, fb: &mut Framebuffer) {
+        let frame_start = std::time::Instant::now();
+
+        let camera = self.scene.camera;
+        let (left, right) = stereo_cameras(&camera, self.eye_separation);
+        match self.stereo {
+            StereoMode::Off => match self.compare {
+                Some(b) => {
+                    let shader_a = self.scene.bodies[PLANET_BODY].shader.clone();
+                    let shader_b = self.planets[b].shader.clone();
+                    let divider = (self.split * fb.width as f32) as u32;
+                    render_split(
+                        fb,
+                        |half_fb, variant| {
+                            let shader = if variant == Variant::A { &shader_a } else { &shader_b };
+                            self.scene.bodies[PLANET_BODY].shader = shader.clone();
+                            self.draw_scene(half_fb, &camera);
+                        },
+                        divider,
+                    );
+                    self.scene.bodies[PLANET_BODY].shader = shader_a;
+                }
+                None => self.draw_scene(fb, &camera),
+            },
+            StereoMode::SideBySide => render_stereo(fb, |eye_fb, cam| self.draw_scene(eye_fb, cam), &left, &right),
+            StereoMode::Anaglyph => render_anaglyph(fb, |eye_fb, cam| self.draw_scene(eye_fb, cam), &left, &right),
+        }
+
+        // Calidad adaptativa: se mide el trabajo de CPU, no el frame completo (que incluye la espera de FPS)
+        if let Some(controller) = &mut self.quality {
+            let q = controller.update(frame_start.elapsed().as_secs_f32() * 1000.0);
+            shader::set_max_octaves(q.max_octaves);
+        }
+    }
+
+    fn post_process(&mut self, fb: &mut Framebuffer) {
+        // Exposición primero: SSAO y bloom trabajan sobre la imagen ya resuelta. La demo no
+        // llena el buffer HDR (los shaders dan 8 bits), así que la exposición escala el color
+        // linealizado y con la de por defecto no hay nada que hacer. Con buffer HDR siempre se
+        // resuelve: el tone mapping cambia la imagen aunque la exposición sea 1
+        if self.exposure != DEFAULT_EXPOSURE || fb.hdr_buffer.is_some() {
+            apply_exposure(fb, self.exposure);
+        }
+        // Antes del bloom: la oclusión oscurece el color base, no el brillo agregado
+        if self.ssao {
+            fb.apply_ssao(SSAO_RADIUS, SSAO_INTENSITY, self.scene.camera.focal());
+        }
+        if self.bloom {
+            // Sin buffer HDR el color lineal llega a 1.0 como máximo: se baja el umbral
+            let settings = if fb.hdr_buffer.is_some() {
+                BloomSettings::default()
+            } else {
+                BloomSettings { threshold: 0.75, ..BloomSettings::default() }
+            };
+            apply_bloom(fb, &settings);
+        }
+        // Al final, así el bloom también queda dentro de la paleta
+        match self.palette {
+            PaletteMode::Off => {}
+            PaletteMode::Nearest => fb.quantize_to_palette(&SPACE_PALETTE, false),
+            PaletteMode::Dithered => fb.quantize_to_palette(&SPACE_PALETTE, true),
+        }
+    }
+
+    fn overlay(&mut self, d: &mut RaylibDrawHandle) {
+        // Las líneas de abajo van pegadas al borde inferior de la ventana (570 con 600 de alto)
+        let bottom = d.get_screen_height();
+        d.draw_text(self.planets[self.current_planet].name, 10, 10, 20, Color::WHITE);
+        d.draw_text(
+            "Controles: TAB planeta | P pausa | W/S zoom | A/D rotar | mouse/flechas orbitar, rueda acercar | E encuadrar | M modo | F suave/plano | H normal exacta | Q vista plana | Z paleta | I luz | 1 semilla | 9/0 girar luz | 2/3 inclinar órbitas | B fondo | X eje | J rejilla | U pulso | L luna | T estela | G bloom | O SSAO | -/= exposición | V estéreo | Y comparar (, . divisor) | R reiniciar | C captura | N ráfaga | K hoja",
+            10,
+            bottom - 30,
+            14,
+            Color::LIGHTGRAY,
+        );
+        d.draw_text(&self.status_line(), 10, bottom - 46, 14, Color::LIGHTGRAY);
+        if let Some(b) = self.compare {
+            let (a, b) = (self.scene.bodies[PLANET_BODY].shader.name(), self.planets[b].shader.name());
+            d.draw_text(&format!("A: {} | B: {}", a, b), 10, bottom - 78, 14, Color::YELLOW);
+        }
+        if self.burst.remaining > 0 {
+            d.draw_text(&format!("Ráfaga: faltan {} frames", self.burst.remaining), 10, bottom - 62, 14, Color::ORANGE);
+        }
+        if self.profiler.enabled() {
+            d.draw_text(&self.profiler.report(), 10, 34, 14, Color::LIGHTGRAY);
+        }
+        if let Some(controller) = &self.quality {
+            let q = controller.quality();
+            d.draw_text(&format!("Calidad {} (octavas {}, SSAA {}x)", controller.level(), q.max_octaves, q.ssaa), 10, 52, 14, Color::LIGHTGRAY);
+        }
+    }
+
+    fn profiler(&mut self) -> Option<&mut FrameProfiler> {
+        Some(&mut self.profiler)
+    }
+}
+
+fn main() {
+    // `--profile`: mide y reporta el tiempo por etapa del pipeline
+    let profile = std::env::args().any(|a| a == "--profile");
+    // `--adaptive`: baja/sube la calidad según el tiempo de frame
+    let adaptive = std::env::args().any(|a| a == "--adaptive");
+    // `--burst N`: frames por ráfaga de capturas (tecla N)
+    let burst = arg_value("--burst").unwrap_or(DEFAULT_BURST_FRAMES);
+    // `--near X` / `--far Y`: distancias de recorte (desde el ojo)
+    let mut camera = Camera::default();
+    let near = arg_value("--near").unwrap_or(camera.near);
+    let far = arg_value("--far").unwrap_or(camera.far);
+    if near > 0.0 && far > near {
+        camera.near = near;
+        camera.far = far;
+    } else {
+        println!("near/far inválidos ({} / {}), se usan {} / {}", near, far, camera.near, camera.far);
+    }
+    // `--fov G`: campo de visión en grados (90 = la proyección original)
+    if let Some(fov) = arg_value::<f32>("--fov") {
+        if fov > 1.0 && fov < 179.0 {
+            camera.fov = fov.to_radians();
+        } else {
+            println!("fov inválido ({}), se usa {}", fov, camera.fov.to_degrees());
+        }
+    }
+    // `--fragment-dither S`: ruido de ±S/2 niveles por fragmento contra el banding
+    let dither = arg_value("--fragment-dither").unwrap_or(0.0f32).max(0.0);
+    // `--impostor-distance D`: los cuerpos a más de D del ojo se dibujan como impostor
+    let impostor_distance = arg_value::<f32>("--impostor-distance").filter(|d| *d > 0.0);
+    // `--drift-speed S`: deriva continental del planeta rocoso (para capturas aceleradas)
+    shader::set_continent_drift(arg_value("--drift-speed").unwrap_or(0.0f32));
+    // `--camera-relative`: transforma relativo al ojo (precisión con cuerpos lejanos)
+    let camera_relative = std::env::args().any(|a| a == "--camera-relative");
+    // `--max-faces N`: decima los OBJ cargados hasta como mucho N caras
+    let max_faces = arg_value("--max-faces");
+    // `--auto-frame`: encuadra la escena al iniciar y al cambiar de planeta;
+    // `--frame-fill F` (0..1) cuánto de la pantalla ocupa (implica `--auto-frame`)
+    let frame_fill = arg_value::<f32>("--frame-fill")
+        .filter(|f| *f > 0.0 && *f <= 1.0)
+        .or_else(|| std::env::args().any(|a| a == "--auto-frame").then_some(DEFAULT_FRAME_FILL));
+    let mut demo = PlanetDemo::new(profile, adaptive, burst, camera, max_faces, frame_fill);
+    demo.scene.camera_relative = camera_relative;
+    demo.scene.dither_strength = dither;
+    demo.scene.impostor_distance = impostor_distance;
+    // `--key-fill`: luz principal cálida + relleno frío en vez de la luz única de cada shader
+    if std::env::args().any(|a| a == "--key-fill") {
+        demo.scene.lights = KEY_FILL_LIGHTS.iter().map(|l| Light { direction: l.direction.normalized(), ..*l }).collect();
+    }
+    // `--ssaa N`: N x N muestras por píxel (1..=4; sin efecto con `--adaptive`)
+    demo.ssaa = arg_value("--ssaa").unwrap_or(1u32).clamp(1, MAX_SUPERSAMPLE);
+    // `--noise-seed N`: semilla del ruido de los shaders (0 = los planetas originales)
+    shader::set_noise_seed(arg_value("--noise-seed").unwrap_or(0u32));
+    // `--linear-export`: capturas en RGB lineal (para componer) en vez de sRGB
+    if std::env::args().any(|a| a == "--linear-export") {
+        demo.color_space = ColorSpace::Linear;
+    }
+    // `--uniform-sampling`: estrellas y rocas al azar (con grumos) en vez de Halton
+    if std::env::args().any(|a| a == "--uniform-sampling") {
+        demo.set_distribution(Distribution::Uniform);
+    }
+    // `--asteroids N`: rocas de los cinturones de asteroides (0 = sin cinturón)
+    if let Some(count) = arg_value::<usize>("--asteroids") {
+        demo.asteroid_count = count;
+        demo.load_planet(demo.current_planet);
+    }
+    // `--annotated-captures`: las capturas (C y ráfagas) llevan el nombre y el estado del HUD
+    demo.annotate_captures = std::env::args().any(|a| a == "--annotated-captures");
+    // `--grid-spacing DEG`: enciende la rejilla de latitud/longitud con líneas cada DEG grados
+    if let Some(spacing) = arg_value::<f32>("--grid-spacing").filter(|s| *s > 0.0 && *s <= 90.0) {
+        demo.show_grid = true;
+        demo.grid_spacing = spacing;
+    }
+
+    println!("\n=== CONTROLES ===");
+    println!("TAB: Cambiar planeta | P: Pausar rotación | W/S: Zoom | A/D: Rotar | Mouse (arrastrar) o flechas: Orbitar | Rueda: Acercar/alejar | E: Encuadrar | M: Modo de render | F: Suave/Plano | H: Normal exacta | Q: Vista plana | Z: Paleta | I: Color de luz | 1: Semilla | 9/0: Girar luz | 2/3: Inclinar órbitas | B: Fondo | X: Eje | J: Rejilla | U: Pulso | L: Luna | T: Estela | G: Bloom | O: SSAO | -/=: Exposición | V: Estéreo | Y: Comparar shaders (mouse o , . mueven el divisor) | R: Reiniciar | C: Captura | N: Ráfaga | K: Hoja de planetas");
+
+    // `--resize`: el framebuffer sigue el tamaño de la ventana en vez de escalarse con letterbox
+    let scaling = if std::env::args().any(|a| a == "--resize") { app::Scaling::Resize } else { app::Scaling::Letterbox };
+    app::run_with(
+        &mut demo,
+        "Laboratorio 4 - Cuerpos Celestes Procedurales",
+        800,
+        600,
+        BACKGROUND,
+        scaling,
+    );
+
     println!("Salida.");
 }
+
+/// Valor que sigue a `flag` en la línea de comandos (`--flag valor`), si existe y se puede leer.
+fn arg_value<T: std::str::FromStr>(flag: &str) -> Option<T> {
+    std::env::args().skip_while(|a| a != flag).nth(1).and_then(|v| v.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trail_keeps_the_newest_points_and_fades_toward_the_oldest() {
+        let mut trail = VecDeque::new();
+        for k in 0..MOON_TRAIL_LEN + 7 {
+            push_trail(&mut trail, Vector3::new(k as f32, 0.0, 0.0));
+        }
+        assert_eq!(trail.len(), MOON_TRAIL_LEN);
+        assert_eq!(trail.front().map(|p| p.x), Some(7.0));
+        assert_eq!(trail.back().map(|p| p.x), Some((MOON_TRAIL_LEN + 6) as f32));
+
+        let fades: Vec<f32> = (0..MOON_TRAIL_LEN - 1).map(|i| trail_fade(i, MOON_TRAIL_LEN)).collect();
+        assert!(fades.windows(2).all(|w| w[0] < w[1]), "{fades:?}");
+        assert!(fades[0] > 0.0 && fades[fades.len() - 1] == 1.0);
+    }
+
+    #[test]
+    fn burst_names_one_file_per_frame_then_stops() {
+        let mut burst = Burst::new(3);
+        let names: Vec<String> = (0..3).map_while(|_| burst.tick()).collect();
+        assert_eq!(names, ["burst_000.png", "burst_001.png", "burst_002.png"]);
+        assert_eq!(burst.tick(), None);
+        assert_eq!(burst, Burst { remaining: 0, index: 3 });
+        assert_eq!(Burst::default().tick(), None);
+    }
+
+    #[test]
+    fn moon_shader_comes_from_the_planet_config() {
+        let demo = PlanetDemo::new(false, false, DEFAULT_BURST_FRAMES, Camera::default(), None, None);
+        let moon_pixels = |moon: ShaderType| {
+            let planet = PlanetConfig {
+                name: "Prueba",
+                model_desc: "",
+                crystal_model: false,
+                shader: ShaderType::Rocky.into(),
+                moon: Some(moon),
+                rings: None,
+                belt: false,
+            };
+            let mut scene = demo.grid_scene(&planet);
+            // Sólo la luna: lo que no es fondo es ella
+            scene.bodies[PLANET_BODY].visible = false;
+            let mut fb = Framebuffer::new(128, 96, Color::BLACK);
+            scene.render(&mut fb);
+            fb.pixels()
+        };
+        let (ice, rocky) = (moon_pixels(ShaderType::Ice), moon_pixels(ShaderType::Rocky));
+        let covered: Vec<usize> = (0..ice.len()).filter(|&i| ice[i] != Color::BLACK).collect();
+        assert!(covered.len() > 20, "la luna cubre {} píxeles", covered.len());
+        assert_eq!(covered, (0..rocky.len()).filter(|&i| rocky[i] != Color::BLACK).collect::<Vec<_>>());
+        let differing = covered.iter().filter(|&&i| ice[i] != rocky[i]).count();
+        assert!(differing > covered.len() / 2, "{differing} de {} píxeles distintos", covered.len());
+    }
+
+    #[test]
+    fn hdr_buffer_is_resolved_at_every_exposure() {
+        let mut demo = PlanetDemo::new(false, false, DEFAULT_BURST_FRAMES, Camera::default(), None, None);
+        let mut resolved = |exposure: f32| {
+            demo.exposure = exposure;
+            let mut fb = Framebuffer::new(2, 1, Color::BLACK);
+            fb.enable_hdr();
+            fb.set_pixel_hdr(0, 0, Vector3::new(2.0, 2.0, 2.0));
+            demo.post_process(&mut fb);
+            fb.pixels()[0]
+        };
+        // Con la exposición por defecto también sale del HDR (no queda el negro de 8 bits)
+        let at_default = resolved(DEFAULT_EXPOSURE);
+        assert_ne!(at_default, Color::BLACK);
+        // Y sin salto al moverla apenas
+        let nudged = resolved(DEFAULT_EXPOSURE * 1.001);
+        assert!(at_default.r.abs_diff(nudged.r) <= 1, "{at_default:?} -> {nudged:?}");
+    }
+
+    #[test]
+    fn compare_cycles_the_other_planets_then_turns_off() {
+        let count = default_planets().len();
+        for current in 0..count {
+            let mut seen = Vec::new();
+            let mut compare = next_compare(None, current, count);
+            while let Some(b) = compare {
+                assert!(seen.len() < count, "Y no vuelve a apagar desde el planeta {current}");
+                seen.push(b);
+                compare = next_compare(compare, current, count);
+            }
+            assert_eq!(seen, (0..count).filter(|&i| i != current).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn light_angle_moves_the_scene_light_without_globals() {
+        let mut demo = PlanetDemo::new(false, false, DEFAULT_BURST_FRAMES, Camera::default(), None, None);
+        assert!(demo.scene.frame_uniforms().lights.is_none());
+        demo.set_light_angle(Some(std::f32::consts::PI));
+        let lights = demo.scene.frame_uniforms().lights.expect("el giro agrega una luz a la escena");
+        let dir = lights.as_slice()[0].direction;
+        let expected = Vector3::new(-DEFAULT_LIGHT_DIR.x, DEFAULT_LIGHT_DIR.y, -DEFAULT_LIGHT_DIR.z).normalized();
+        assert!((dir - expected).length() < 1e-4, "{dir:?}");
+        // Otro giro mueve la misma luz, y `None` la quita
+        demo.set_light_angle(Some(0.0));
+        assert_eq!(demo.scene.lights.len(), 1);
+        assert!((demo.scene.lights[0].direction - DEFAULT_LIGHT_DIR.normalized()).length() < 1e-4);
+        demo.set_light_angle(None);
+        assert!(demo.scene.lights.is_empty());
+
+        // Con --key-fill se gira la principal y el relleno se queda
+        demo.scene.lights = KEY_FILL_LIGHTS.to_vec();
+        demo.set_light_angle(Some(1.0));
+        assert_eq!(demo.scene.lights[1], KEY_FILL_LIGHTS[1]);
+        demo.set_light_angle(None);
+        assert_eq!(demo.scene.lights.len(), 2);
+        assert!((demo.scene.lights[0].direction - DEFAULT_LIGHT_DIR.normalized()).length() < 1e-4);
+    }
+
+    #[test]
+    fn planet_grid_has_expected_size_and_a_planet_in_every_cell() {
+        let demo = PlanetDemo::new(false, false, DEFAULT_BURST_FRAMES, Camera::default(), None, None);
+        let (cell_w, cell_h, cols) = (64, 48, 2);
+        let sheet = demo.render_planet_grid(&demo.planets[..4], cell_w, cell_h, cols);
+        assert_eq!((sheet.width(), sheet.height()), (128, 96));
+
+        let pixels = sheet.get_image_data();
+        let pixels = &pixels[..];
+        for cell in 0..4 {
+            let (x0, y0) = (cell % cols * cell_w, cell / cols * cell_h);
+            let drawn = (y0..y0 + cell_h)
+                .flat_map(|y| (x0..x0 + cell_w).map(move |x| pixels[(y * cols * cell_w + x) as usize]))
+                .filter(|&c| c != BACKGROUND)
+                .count();
+            // El planeta, centrado en la celda, y no sólo algún píxel suelto
+            let center = pixels[((y0 + cell_h / 2) * cols * cell_w + x0 + cell_w / 2) as usize];
+            assert_ne!(center, BACKGROUND, "celda {cell}");
+            assert!(drawn > (cell_w * cell_h / 30) as usize, "celda {cell}: {drawn} píxeles");
+        }
+    }
+
+    #[test]
+    fn annotated_capture_writes_text_over_the_background() {
+        let mut demo = PlanetDemo::new(false, false, DEFAULT_BURST_FRAMES, Camera::default(), None, None);
+        let (w, h) = (200, 120);
+        let fb = Framebuffer::new(w, h, BACKGROUND);
+        assert!(demo.capture_frame(&fb).is_none());
+
+        demo.annotate_captures = true;
+        let pixels = demo.capture_frame(&fb).expect("captura anotada").export_data(ColorSpace::Srgb);
+        let at = |x: u32, y: u32| pixels[(y * w + x) as usize];
+        // Nombre del planeta arriba a la izquierda (escala 2: 14 px de alto) y estado abajo
+        let name_w = demo.planets[demo.current_planet].name.chars().count() as u32 * 12;
+        let lit = |xs: std::ops::Range<u32>, ys: std::ops::Range<u32>| {
+            ys.flat_map(|y| xs.clone().map(move |x| (x, y))).filter(|&(x, y)| at(x, y) != BACKGROUND).count()
+        };
+        assert!(lit(10..10 + name_w, 10..24) > 20);
+        assert!(lit(10..w, h - 20..h - 13) > 20);
+        // Fuera del texto el fondo queda igual
+        assert_eq!(lit(0..w, 30..h - 25), 0);
+    }
+}
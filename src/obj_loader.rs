@@ -1,54 +1,902 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use raylib::prelude::*;
+use crate::sampling::{hash01, unit_sphere_point, value_noise3};
 
-/// Modelo OBJ muy simple: lee vértices y caras (no usa normales ni UVs).
-/// Las caras en el OBJ pueden venir como "f v/vt/vn" - sólo tomo el índice de vértice.
+/// Error al cargar un OBJ. Las líneas son 1-based, como en un editor.
+#[derive(Debug)]
+pub enum ObjError {
+    IoError(std::io::Error),
+    /// Coordenada de `v`/`vn` que no es un número (o que falta: `token` vacío).
+    ParseFloat { line: usize, token: String },
+    /// Línea `v`/`vn` con menos de 3 coordenadas (`found` = las que trae).
+    MissingCoords { line: usize, found: usize },
+    /// Cara con menos de 3 esquinas válidas o con un índice fuera de rango.
+    BadFace { line: usize },
+    /// Cara agregada con `add_face` que no cabe en el modelo (menos de 3 esquinas o un índice
+    /// >= `vertex_count`).
+    InvalidFace { indices: Vec<usize>, vertex_count: usize },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::IoError(e) => write!(f, "{}", e),
+            ObjError::ParseFloat { line, token } => write!(f, "línea {}: número inválido {:?}", line, token),
+            ObjError::MissingCoords { line, found } => {
+                write!(f, "línea {}: se esperaban 3 coordenadas (x y z) y hay {}", line, found)
+            }
+            ObjError::BadFace { line } => write!(f, "línea {}: cara inválida", line),
+            ObjError::InvalidFace { indices, vertex_count } => {
+                write!(f, "cara inválida {:?} (el modelo tiene {} vértices)", indices, vertex_count)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+impl From<std::io::Error> for ObjError {
+    fn from(e: std::io::Error) -> Self {
+        ObjError::IoError(e)
+    }
+}
+
+/// Abolladuras grandes que hunde `ObjModel::make_irregular`.
+const IRREGULAR_DENTS: u32 = 4;
+
+/// Nombre del grupo para caras que aparecen antes de cualquier `o`/`g`.
+pub const DEFAULT_GROUP: &str = "default";
+
+/// Marca en `face_normals` para una esquina que no trae `vn` (p. ej. "f 1/1/1 2/2 3//3").
+pub const NO_NORMAL: usize = usize::MAX;
+
+/// Modelo OBJ muy simple: lee vértices, normales (`vn`) y caras (no usa UVs).
+/// Las caras en el OBJ pueden venir como "f v/vt/vn": se guarda el índice de vértice y, si el
+/// archivo trae normales, el de normal (o `NO_NORMAL` en las esquinas sin él).
+/// Los `o`/`g` se guardan como grupo de cada cara (ver `submeshes`) y los `s` como grupo de
+/// suavizado (0 = "s off"; si el archivo no trae ningún `s`, todo queda en el grupo 1).
 #[derive(Debug, Clone)]
 pub struct ObjModel {
     pub vertices: Vec<Vector3>,
     pub faces: Vec<Vec<usize>>, // índices 0-based
+    pub groups: Vec<String>,    // nombres de grupo, sin repetir
+    pub face_groups: Vec<usize>, // índice en `groups` de cada cara
+    pub face_smoothing: Vec<u32>, // grupo de suavizado de cada cara
+    pub normals: Vec<Vector3>,  // pool de normales (vacío si no hay)
+    pub face_normals: Vec<Vec<usize>>, // por cara, índice en `normals` de cada esquina (vacío si no hay)
+}
+
+/// Cómo se pondera la normal de cada cara al promediar normales suaves en un vértice.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NormalWeighting {
+    /// Por área: las caras grandes dominan (comportamiento por defecto).
+    Area,
+    /// Por ángulo de la esquina: robusto con triángulos de tamaños muy distintos.
+    Angle,
+    /// Todas las caras pesan igual.
+    Uniform,
+}
+
+/// Parte de un modelo (un `o`/`g` del OBJ). Las caras apuntan al pool de vértices del modelo.
+#[derive(Debug, Clone)]
+pub struct SubMesh {
+    pub name: String,
+    pub faces: Vec<Vec<usize>>,
 }
 
 impl ObjModel {
-    pub fn load(path: &str) -> Result<Self, std::io::Error> {
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
+    /// Modelo de un solo grupo (para geometría procedural).
+    pub fn new(vertices: Vec<Vector3>, faces: Vec<Vec<usize>>) -> Self {
+        let face_groups = vec![0; faces.len()];
+        let face_smoothing = vec![1; faces.len()];
+        Self {
+            vertices,
+            faces,
+            groups: vec![DEFAULT_GROUP.to_string()],
+            face_groups,
+            face_smoothing,
+            normals: Vec::new(),
+            face_normals: Vec::new(),
+        }
+    }
+
+    /// Calcula normales por esquina respetando los grupos de suavizado: las caras del mismo
+    /// grupo comparten la normal promediada (ponderada por área) en cada vértice, y las caras
+    /// con "s off" usan su normal plana. Las caras de área cero no aportan. Reemplaza
+    /// `normals`/`face_normals`; `Body::new` la llama si el OBJ no trae `vn`.
+    pub fn compute_smooth_normals(&mut self) {
+        self.compute_smooth_normals_ex(NormalWeighting::Area);
+    }
+
+    /// Como `compute_smooth_normals`, eligiendo cómo pesa cada cara en la normal compartida.
+    pub fn compute_smooth_normals_ex(&mut self, weighting: NormalWeighting) {
+        let mut normals: Vec<Vector3> = Vec::new();
+        let mut shared: HashMap<(usize, u32), usize> = HashMap::new();
+        let mut face_normals = Vec::with_capacity(self.faces.len());
+
+        for (fi, face) in self.faces.iter().enumerate() {
+            let n = self.face_normal(face);
+            let group = self.face_smoothing.get(fi).copied().unwrap_or(1);
+
+            if group == 0 {
+                normals.push(n);
+                face_normals.push(vec![normals.len() - 1; face.len()]);
+                continue;
+            }
+
+            // Caras degeneradas no aportan (su normal no tiene dirección)
+            let unit = if n.length() > 1e-12 { n.normalized() } else { Vector3::new(0.0, 0.0, 0.0) };
+
+            let corners = face
+                .iter()
+                .enumerate()
+                .map(|(k, &v)| {
+                    let idx = *shared.entry((v, group)).or_insert_with(|| {
+                        normals.push(Vector3::new(0.0, 0.0, 0.0));
+                        normals.len() - 1
+                    });
+                    normals[idx] += match weighting {
+                        NormalWeighting::Area => n,
+                        NormalWeighting::Uniform => unit,
+                        NormalWeighting::Angle => unit * self.corner_angle(face, k),
+                    };
+                    idx
+                })
+                .collect();
+            face_normals.push(corners);
+        }
+
+        for n in normals.iter_mut() {
+            if n.length() > 1e-12 {
+                *n = n.normalized();
+            }
+        }
+
+        self.normals = normals;
+        self.face_normals = face_normals;
+    }
+
+    /// Ángulo interior (radianes) de la esquina `k` de una cara.
+    fn corner_angle(&self, face: &[usize], k: usize) -> f32 {
+        let n = face.len();
+        let p = self.vertices[face[k]];
+        let a = self.vertices[face[(k + n - 1) % n]] - p;
+        let b = self.vertices[face[(k + 1) % n]] - p;
+        let denom = a.length() * b.length();
+        if denom < 1e-12 { return 0.0; }
+        (a.dot(b) / denom).clamp(-1.0, 1.0).acos()
+    }
+
+    /// Normal geométrica (sin normalizar, su largo es 2x el área) de una cara en abanico.
+    /// Mismo sentido que la normal de `draw_filled_view_triangle`.
+    fn face_normal(&self, face: &[usize]) -> Vector3 {
+        let mut n = Vector3::new(0.0, 0.0, 0.0);
+        if face.len() < 3 { return n; }
+        let v0 = self.vertices[face[0]];
+        for i in 1..(face.len() - 1) {
+            let e1 = self.vertices[face[i]] - v0;
+            let e2 = self.vertices[face[i + 1]] - v0;
+            n += e1.cross(e2);
+        }
+        n
+    }
+
+    /// Posición del vértice `i`. Entra en pánico (con el tamaño del modelo en el mensaje) si
+    /// no existe.
+    #[track_caller]
+    pub fn vertex(&self, i: usize) -> Vector3 {
+        self.vertices[self.checked_vertex(i)]
+    }
+
+    /// Mueve el vértice `i` a `v`. Las normales no se tocan: después de editar la forma hay que
+    /// volver a llamar a `compute_smooth_normals`. Entra en pánico si `i` no existe.
+    #[track_caller]
+    pub fn set_vertex(&mut self, i: usize, v: Vector3) {
+        let i = self.checked_vertex(i);
+        self.vertices[i] = v;
+    }
+
+    /// Índices (0-based) de las esquinas de la cara `i`. Entra en pánico si no existe.
+    #[track_caller]
+    pub fn face(&self, i: usize) -> &[usize] {
+        match self.faces.get(i) {
+            Some(face) => face,
+            None => panic!("cara {} fuera de rango (el modelo tiene {} caras)", i, self.faces.len()),
+        }
+    }
+
+    /// Agrega una cara (en el grupo por defecto, grupo de suavizado 1, sin normales propias) y
+    /// devuelve su índice. Se rechaza si tiene menos de 3 esquinas o algún índice no es un
+    /// vértice del modelo; en ese caso el modelo no cambia.
+    pub fn add_face(&mut self, indices: Vec<usize>) -> Result<usize, ObjError> {
+        let vertex_count = self.vertices.len();
+        if indices.len() < 3 || indices.iter().any(|&i| i >= vertex_count) {
+            return Err(ObjError::InvalidFace { indices, vertex_count });
+        }
+        // Los arreglos por cara tienen que seguir alineados con `faces`
+        if !self.face_normals.is_empty() {
+            self.face_normals.push(vec![NO_NORMAL; indices.len()]);
+        }
+        self.faces.push(indices);
+        self.face_groups.push(0);
+        self.face_smoothing.push(1);
+        Ok(self.faces.len() - 1)
+    }
+
+    #[track_caller]
+    fn checked_vertex(&self, i: usize) -> usize {
+        if i >= self.vertices.len() {
+            panic!("vértice {} fuera de rango (el modelo tiene {} vértices)", i, self.vertices.len());
+        }
+        i
+    }
+
+    /// `true` si cada esquina de cada cara tiene una normal válida en `normals`.
+    pub fn has_complete_normals(&self) -> bool {
+        self.face_normals.len() == self.faces.len()
+            && self
+                .faces
+                .iter()
+                .zip(&self.face_normals)
+                .all(|(f, n)| n.len() == f.len() && n.iter().all(|&i| i < self.normals.len()))
+    }
+
+    /// Separa el modelo en submeshes por grupo, en el orden en que aparecen en el archivo.
+    /// Los grupos sin caras se omiten.
+    pub fn submeshes(&self) -> Vec<SubMesh> {
+        let mut subs: Vec<SubMesh> = self
+            .groups
+            .iter()
+            .map(|name| SubMesh { name: name.clone(), faces: Vec::new() })
+            .collect();
+        for (face, &group) in self.faces.iter().zip(&self.face_groups) {
+            subs[group].faces.push(face.clone());
+        }
+        subs.retain(|s| !s.faces.is_empty());
+        subs
+    }
+
+    /// Carga el OBJ tal cual viene en el archivo. Un número o una cara inválidos son error
+    /// (con su línea); ver `load_lenient` para aceptar archivos sucios.
+    pub fn load(path: &str) -> Result<Self, ObjError> {
+        Self::load_with(path, false)
+    }
+
+    /// Carga permisiva: los números inválidos valen 0, las líneas `v` con menos de tres
+    /// coordenadas se saltan (los índices de las caras siguientes quedan corridos), las
+    /// esquinas con un índice fuera de rango se descartan y las caras que quedan con menos de
+    /// tres esquinas se saltan. Sólo falla si no se puede leer el archivo.
+    pub fn load_lenient(path: &str) -> Result<Self, ObjError> {
+        Self::parse(BufReader::new(File::open(path)?), false)
+    }
+
+    /// Como `load`, pero de cualquier lector (p. ej. un OBJ embebido con `include_str!`:
+    /// `load_from_reader(texto.as_bytes())`).
+    pub fn load_from_reader<R: BufRead>(reader: R) -> Result<Self, ObjError> {
+        Self::parse(reader, true)
+    }
+
+    /// Carga el OBJ; con `normalize = true` además lo centra y escala con `scale_to_unit`.
+    pub fn load_with(path: &str, normalize: bool) -> Result<Self, ObjError> {
+        let mut model = Self::load_from_reader(BufReader::new(File::open(path)?))?;
+        if normalize {
+            model.scale_to_unit();
+        }
+        Ok(model)
+    }
+
+    /// Centra el modelo en el origen (centro del bounding box) y lo escala para que quepa
+    /// justo en la esfera unitaria: el vértice más lejano queda a distancia 1.
+    pub fn scale_to_unit(&mut self) {
+        self.scale_to_radius(1.0);
+    }
+
+    /// Como `scale_to_unit`, pero el vértice más lejano queda a distancia `radius`.
+    pub fn scale_to_radius(&mut self, radius: f32) {
+        if self.vertices.is_empty() { return; }
+
+        let (min, max) = self.bounding_box();
+        let center = (min + max) * 0.5;
+        for v in self.vertices.iter_mut() {
+            *v -= center;
+        }
+
+        let current = self.bounding_radius();
+        if current > 1e-12 {
+            let k = radius / current;
+            for v in self.vertices.iter_mut() {
+                *v *= k;
+            }
+        }
+    }
+
+    /// Deforma el modelo en una "papa" (asteroides, lunas chicas): cada vértice se mueve en la
+    /// dirección desde el centro según ruido 3D de baja frecuencia (`lumpiness` ≈ bultos por
+    /// radio), y además se hunden unas pocas abolladuras grandes en direcciones al azar.
+    /// `amplitude` es la fracción del radio que se desplaza como mucho por cada efecto. Cambia
+    /// la silueta, no el detalle fino. El mismo `seed` da el mismo modelo. Recalcula las normales.
+    pub fn make_irregular(&mut self, amplitude: f32, lumpiness: f32, seed: u32) {
+        if self.vertices.is_empty() { return; }
+
+        let (min, max) = self.bounding_box();
+        let center = (min + max) * 0.5;
+        // (dirección, radio angular, profundidad) de cada abolladura
+        let dents: Vec<(Vector3, f32, f32)> = (0..IRREGULAR_DENTS)
+            .map(|i| {
+                let dir = unit_sphere_point(hash01(4 * i, seed), hash01(4 * i + 1, seed));
+                (dir, 0.35 + 0.4 * hash01(4 * i + 2, seed), 0.5 + 0.5 * hash01(4 * i + 3, seed))
+            })
+            .collect();
+
+        for v in self.vertices.iter_mut() {
+            let offset = *v - center;
+            let r = offset.length();
+            if r < 1e-9 { continue; }
+            let dir = offset / r;
+            // Función de la dirección: los vértices duplicados en las costuras siguen juntos
+            let mut k = 1.0 + amplitude * value_noise3(dir * lumpiness, seed);
+            for &(d, size, depth) in &dents {
+                let angle = dir.dot(d).clamp(-1.0, 1.0).acos();
+                if angle < size {
+                    let t = 1.0 - angle / size;
+                    k -= amplitude * depth * t * t * (3.0 - 2.0 * t);
+                }
+            }
+            *v = center + dir * r * k.max(0.2);
+        }
+        self.compute_smooth_normals();
+    }
+
+    /// Reduce el modelo a como mucho `target_faces` caras por agrupamiento de vértices (ver
+    /// `cluster_simplify`), buscando la rejilla más fina que cumple el presupuesto. Si el
+    /// modelo tenía normales se recalculan.
+    pub fn decimate(&mut self, target_faces: usize) {
+        if self.faces.len() <= target_faces || self.vertices.is_empty() { return; }
+
+        let (origin, extent) = self.cluster_bounds();
+
+        // De la rejilla más fina que puede cambiar algo hacia abajo, ~10% por paso
+        let mut res = ((self.vertices.len() as f32).cbrt() * 4.0).ceil() as usize;
+        let result = loop {
+            let result = self.cluster(origin, extent / res as f32);
+            if result.1.len() <= target_faces || res <= 1 { break result; }
+            res = (res * 9 / 10).min(res - 1);
+        };
+        self.apply_cluster(result);
+    }
+
+    /// LOD rápido: superpone una rejilla de `grid`³ celdas al bounding box y cada celda se
+    /// queda con un solo vértice (el original más cercano al promedio de la celda, así sigue
+    /// sobre la superficie). Se descartan las caras que colapsan. Si el modelo tenía normales
+    /// se recalculan.
+    pub fn cluster_simplify(&mut self, grid: u32) {
+        if self.vertices.is_empty() { return; }
+        let (origin, extent) = self.cluster_bounds();
+        let result = self.cluster(origin, extent / grid.max(1) as f32);
+        self.apply_cluster(result);
+    }
+
+    /// Esquina mínima del bounding box y su lado mayor (lado de la rejilla de `cluster`).
+    /// Se agranda apenas para que los vértices del borde máximo caigan en la última celda.
+    fn cluster_bounds(&self) -> (Vector3, f32) {
+        let (min, max) = self.bounding_box();
+        let size = max - min;
+        (min, size.x.max(size.y).max(size.z).max(1e-6) * 1.0001)
+    }
+
+    /// Reemplaza geometría y datos por cara con el resultado de `cluster`.
+    fn apply_cluster(&mut self, (vertices, faces, kept): (Vec<Vector3>, Vec<Vec<usize>>, Vec<usize>)) {
+        let had_normals = !self.normals.is_empty();
+        self.face_groups = kept.iter().map(|&f| self.face_groups.get(f).copied().unwrap_or(0)).collect();
+        self.face_smoothing = kept.iter().map(|&f| self.face_smoothing.get(f).copied().unwrap_or(1)).collect();
+        self.vertices = vertices;
+        self.faces = faces;
+        self.normals.clear();
+        self.face_normals.clear();
+        if had_normals {
+            self.compute_smooth_normals();
+        }
+    }
 
+    /// Agrupamiento con celdas de lado `cell` a partir de `origin`. Devuelve los vértices que quedan, las
+    /// caras reindexadas y, por cada cara, el índice de la cara original.
+    fn cluster(&self, origin: Vector3, cell: f32) -> (Vec<Vector3>, Vec<Vec<usize>>, Vec<usize>) {
+        let key = |v: Vector3| {
+            let c = (v - origin) / cell;
+            (c.x.floor() as i32, c.y.floor() as i32, c.z.floor() as i32)
+        };
+
+        // Promedio de cada celda
+        let mut cells: HashMap<(i32, i32, i32), (Vector3, usize)> = HashMap::new();
+        for &v in &self.vertices {
+            let e = cells.entry(key(v)).or_insert((Vector3::new(0.0, 0.0, 0.0), 0));
+            e.0 += v;
+            e.1 += 1;
+        }
+
+        // Representante: el vértice original más cercano al promedio
+        let mut best: HashMap<(i32, i32, i32), (usize, f32)> = HashMap::new();
+        for (i, &v) in self.vertices.iter().enumerate() {
+            let k = key(v);
+            let (sum, count) = cells[&k];
+            let d = (v - sum / count as f32).length();
+            let e = best.entry(k).or_insert((i, f32::MAX));
+            if d < e.1 {
+                *e = (i, d);
+            }
+        }
+
+        let mut new_index: HashMap<(i32, i32, i32), usize> = HashMap::new();
+        let mut vertices = Vec::new();
+        let remap: Vec<usize> = self
+            .vertices
+            .iter()
+            .map(|&v| {
+                let k = key(v);
+                *new_index.entry(k).or_insert_with(|| {
+                    vertices.push(self.vertices[best[&k].0]);
+                    vertices.len() - 1
+                })
+            })
+            .collect();
+
+        let mut faces = Vec::new();
+        let mut kept = Vec::new();
+        for (fi, face) in self.faces.iter().enumerate() {
+            let mut f: Vec<usize> = face.iter().map(|&i| remap[i]).collect();
+            f.dedup();
+            while f.len() > 1 && f.first() == f.last() {
+                f.pop();
+            }
+            if f.len() >= 3 {
+                faces.push(f);
+                kept.push(fi);
+            }
+        }
+        (vertices, faces, kept)
+    }
+
+    /// Mezcla las posiciones de `self` (t = 0) y `other` (t = 1), que deben tener la misma
+    /// topología (p. ej. la malla original y su versión suavizada o desplazada). Caras,
+    /// grupos y demás salen de `self`; si `self` tenía normales se recalculan con las
+    /// posiciones mezcladas. Si `other` tiene menos vértices, los que sobran no se mueven.
+    pub fn lerp_positions(&self, other: &ObjModel, t: f32) -> ObjModel {
+        let mut out = self.clone();
+        for (v, &target) in out.vertices.iter_mut().zip(&other.vertices) {
+            *v += (target - *v) * t;
+        }
+        if !self.normals.is_empty() {
+            out.compute_smooth_normals();
+        }
+        out
+    }
+
+    /// Esquinas (mínima, máxima) de la caja alineada a los ejes que contiene los vértices.
+    /// Sin vértices, ambas en el origen.
+    pub fn bounding_box(&self) -> (Vector3, Vector3) {
+        let Some(&first) = self.vertices.first() else {
+            return (Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+        };
+        self.vertices.iter().fold((first, first), |(min, max), v| {
+            (
+                Vector3::new(min.x.min(v.x), min.y.min(v.y), min.z.min(v.z)),
+                Vector3::new(max.x.max(v.x), max.y.max(v.y), max.z.max(v.z)),
+            )
+        })
+    }
+
+    /// Centra el modelo (centro del bounding box al origen) y lo escala para que su eje más
+    /// largo vaya justo de -1 a 1; los otros quedan dentro. A diferencia de `scale_to_unit`
+    /// mide la caja, no la esfera: un cubo queda con las caras en ±1, no con las esquinas.
+    pub fn normalize(&mut self) {
+        if self.vertices.is_empty() { return; }
+
+        let (min, max) = self.bounding_box();
+        let center = (min + max) * 0.5;
+        let size = max - min;
+        let half = size.x.max(size.y).max(size.z) * 0.5;
+        let k = if half > 1e-12 { 1.0 / half } else { 1.0 };
+        for v in self.vertices.iter_mut() {
+            *v = (*v - center) * k;
+        }
+    }
+
+    /// Distancia máxima de un vértice al origen.
+    pub fn bounding_radius(&self) -> f32 {
+        self.vertices.iter().map(|v| v.length()).fold(0.0, f32::max)
+    }
+
+    /// `strict = false` reproduce el comportamiento original: nada de lo que trae el archivo
+    /// es error.
+    fn parse<R: BufRead>(reader: R, strict: bool) -> Result<Self, ObjError> {
         let mut vertices = Vec::new();
+        let mut normals = Vec::new();
         let mut faces = Vec::new();
+        let mut face_normals = Vec::new();
+        let mut groups = vec![DEFAULT_GROUP.to_string()];
+        let mut face_groups = Vec::new();
+        let mut current_group = 0;
+        let mut face_smoothing = Vec::new();
+        let mut current_smoothing = 1;
 
-        for line in reader.lines() {
+        for (line_no, line) in reader.lines().enumerate() {
             let line = line?;
+            // Archivos de Windows: BOM UTF-8 al inicio y `\r` final (si no, "v" no coincide)
+            let line = if line_no == 0 { line.trim_start_matches('\u{feff}') } else { line.as_str() };
+            let line = line.trim_end_matches('\r');
             let parts: Vec<&str> = line.split_whitespace().collect();
             if parts.is_empty() { continue; }
+            let line = line_no + 1;
 
             match parts[0] {
-                "v" => {
-                    if parts.len() >= 4 {
-                        let x: f32 = parts[1].parse().unwrap_or(0.0);
-                        let y: f32 = parts[2].parse().unwrap_or(0.0);
-                        let z: f32 = parts[3].parse().unwrap_or(0.0);
-                        vertices.push(Vector3::new(x, y, z));
-                    }
-                }
+                // Permisivo: una `v` sin x, y, z se salta como siempre (las caras que siguen
+                // quedan corridas); estricto: `parse_coords` da `MissingCoords`
+                "v" if strict || parts.len() >= 4 => vertices.push(parse_coords(&parts, line, strict)?),
+                "vn" => normals.push(parse_coords(&parts, line, strict)?),
                 "f" => {
                     let mut face_indices = Vec::new();
+                    let mut corner_normals = Vec::new();
                     for p in &parts[1..] {
-                        // formato posible: "v", "v/vt", "v/vt/vn"
-                        let idx = p.split('/').next().unwrap_or("0").parse::<usize>().unwrap_or(0);
-                        if idx > 0 {
-                            face_indices.push(idx - 1); // convertir a 0-based
+                        let (v, _vt, vn) = parse_face_token(p);
+                        match v.and_then(|v| resolve_index(v, vertices.len())) {
+                            Some(idx) if idx < vertices.len() => {
+                                face_indices.push(idx);
+                                corner_normals.push(vn.and_then(|n| resolve_index(n, normals.len())).unwrap_or(NO_NORMAL));
+                            }
+                            _ if strict => return Err(ObjError::BadFace { line }),
+                            _ => {}
                         }
                     }
+                    if strict && face_indices.len() < 3 {
+                        return Err(ObjError::BadFace { line });
+                    }
                     if face_indices.len() >= 3 {
                         faces.push(face_indices);
+                        face_normals.push(corner_normals);
+                        face_groups.push(current_group);
+                        face_smoothing.push(current_smoothing);
                     }
                 }
+                "s" => {
+                    // "s off" / "s 0" = sombreado plano; "s N" = grupo de suavizado N
+                    current_smoothing = match parts.get(1) {
+                        Some(&"off") | None => 0,
+                        Some(v) => v.parse().unwrap_or(0),
+                    };
+                }
+                "o" | "g" => {
+                    // "g" sin nombre vuelve al grupo por defecto; nombres repetidos se unen
+                    let name = if parts.len() > 1 { parts[1..].join(" ") } else { DEFAULT_GROUP.to_string() };
+                    current_group = match groups.iter().position(|g| *g == name) {
+                        Some(i) => i,
+                        None => {
+                            groups.push(name);
+                            groups.len() - 1
+                        }
+                    };
+                }
                 _ => {}
             }
         }
 
-        Ok(ObjModel { vertices, faces })
+        Ok(ObjModel {
+            vertices,
+            faces,
+            groups,
+            face_groups,
+            face_smoothing,
+            // Sin ningún `vn` en el archivo no hay nada que indexar
+            face_normals: if normals.is_empty() { Vec::new() } else { face_normals },
+            normals,
+        })
+    }
+}
+
+/// Separa un token de cara en (v, vt, vn), tal cual vienen en el archivo (1-based, o
+/// negativos = relativos al final). Formas válidas:
+/// "v", "v/vt", "v//vn", "v/vt/vn" y "v/" (sin UV); un campo vacío o inválido da `None`.
+pub fn parse_face_token(tok: &str) -> (Option<i32>, Option<i32>, Option<i32>) {
+    let mut fields = tok.split('/');
+    let mut next = || fields.next().filter(|f| !f.is_empty()).and_then(|f| f.parse::<i32>().ok());
+    let v = next();
+    let vt = next();
+    let vn = next();
+    (v, vt, vn)
+}
+
+/// Las tres coordenadas de una línea `v`/`vn` (`parts[1..4]`; se ignora una `w` o un color
+/// extra). Sin `strict`, lo inválido o faltante vale 0.
+fn parse_coords(parts: &[&str], line: usize, strict: bool) -> Result<Vector3, ObjError> {
+    if strict && parts.len() < 4 {
+        return Err(ObjError::MissingCoords { line, found: parts.len() - 1 });
+    }
+    let mut c = [0.0; 3];
+    for (k, value) in c.iter_mut().enumerate() {
+        let token = parts.get(k + 1).copied().unwrap_or("");
+        *value = match token.parse::<f32>() {
+            Ok(x) => x,
+            Err(_) if strict => return Err(ObjError::ParseFloat { line, token: token.to_string() }),
+            Err(_) => 0.0,
+        };
+    }
+    Ok(Vector3::new(c[0], c[1], c[2]))
+}
+
+/// Índice OBJ (1-based o negativo relativo) -> índice 0-based en un arreglo de `len` elementos.
+fn resolve_index(idx: i32, len: usize) -> Option<usize> {
+    match idx {
+        i if i > 0 => Some(i as usize - 1),
+        i if i < 0 => len.checked_sub(i.unsigned_abs() as usize),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> ObjModel {
+        ObjModel::load_from_reader(text.as_bytes()).expect("OBJ válido")
+    }
+
+    #[test]
+    fn groups_become_submeshes() {
+        let model = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\ng planet\nf 1 2 3\nf 2 4 3\no moon\nf 1 2 4\n");
+        let subs = model.submeshes();
+        let summary: Vec<(&str, usize)> = subs.iter().map(|s| (s.name.as_str(), s.faces.len())).collect();
+        assert_eq!(summary, [("planet", 2), ("moon", 1)]);
+        assert_eq!(subs[1].faces[0], [0, 1, 3]);
+    }
+
+    /// Dos caras que comparten la arista 1-3 doblada en ángulo recto, con los grupos de
+    /// suavizado dados.
+    fn folded(s0: &str, s1: &str) -> ObjModel {
+        let mut model = parse(&format!("v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\ns {s0}\nf 1 2 3\ns {s1}\nf 1 3 4\n"));
+        model.compute_smooth_normals();
+        model
+    }
+
+    /// Normal de la esquina `corner` de la cara `face`.
+    fn corner_normal(model: &ObjModel, face: usize, corner: usize) -> Vector3 {
+        model.normals[model.face_normals[face][corner]]
+    }
+
+    #[test]
+    fn shared_edge_is_smooth_within_a_group_and_hard_across_groups() {
+        // Vértice 1 (esquina 0 de las dos caras) está en la arista compartida
+        let smooth = folded("1", "1");
+        assert_eq!(corner_normal(&smooth, 0, 0), corner_normal(&smooth, 1, 0));
+
+        let hard = folded("1", "2");
+        let (a, b) = (corner_normal(&hard, 0, 0), corner_normal(&hard, 1, 0));
+        assert!((a - Vector3::new(0.0, 0.0, 1.0)).length() < 1e-6, "{a:?}");
+        assert!((b - Vector3::new(1.0, 0.0, 0.0)).length() < 1e-6, "{b:?}");
+
+        // "s off" también da la normal plana de cada cara
+        let flat = folded("off", "off");
+        assert_eq!(corner_normal(&flat, 0, 0), a);
+    }
+
+    #[test]
+    fn normalized_model_fits_unit_sphere() {
+        let mut model = parse("v 10 10 10\nv 14 10 10\nv 10 18 10\nv 10 10 12\nf 1 2 3\nf 1 3 4\n");
+        model.scale_to_unit();
+        let max = model.vertices.iter().map(|v| v.length()).fold(0.0, f32::max);
+        assert!((max - 1.0).abs() < 1e-5, "{max}");
+
+        let sphere = ObjModel::load_with("sphere-1.obj", true).expect("sphere-1.obj");
+        let max = sphere.vertices.iter().map(|v| v.length()).fold(0.0, f32::max);
+        assert!((max - 1.0).abs() < 1e-5, "{max}");
+    }
+
+    #[test]
+    fn offset_cube_is_centered_and_bounded_after_normalize() {
+        // Cubo de lado 4 con una esquina en (10, 10, 10)
+        let mut text = String::new();
+        for i in 0..8 {
+            let c = |bit: u32| 10.0 + 4.0 * ((i >> bit) & 1) as f32;
+            text += &format!("v {} {} {}\n", c(0), c(1), c(2));
+        }
+        text += "f 1 2 4 3\nf 5 6 8 7\nf 1 2 6 5\nf 3 4 8 7\nf 1 3 7 5\nf 2 4 8 6\n";
+        let mut cube = parse(&text);
+        assert_eq!(cube.bounding_box(), (Vector3::new(10.0, 10.0, 10.0), Vector3::new(14.0, 14.0, 14.0)));
+
+        cube.normalize();
+        let (min, max) = cube.bounding_box();
+        assert_eq!((min + max) * 0.5, Vector3::zero());
+        assert_eq!((min, max), (Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0)));
+        assert!(cube.vertices.iter().all(|v| v.x.abs() <= 1.0 && v.y.abs() <= 1.0 && v.z.abs() <= 1.0));
+    }
+
+    #[test]
+    fn lerp_positions_goes_from_original_to_target() {
+        let mut original = crate::geometria::generate_icosphere(1.0, 2);
+        original.compute_smooth_normals();
+        let mut target = original.clone();
+        target.vertices.iter_mut().for_each(|v| *v = *v * 2.0 + Vector3::new(0.0, 0.5, 0.0));
+
+        assert_eq!(original.lerp_positions(&target, 0.0).vertices, original.vertices);
+        let end = original.lerp_positions(&target, 1.0);
+        for (a, b) in end.vertices.iter().zip(&target.vertices) {
+            assert!((*a - *b).length() < 1e-6);
+        }
+
+        let mid = original.lerp_positions(&target, 0.5);
+        assert_eq!(mid.faces, original.faces);
+        for (i, v) in mid.vertices.iter().enumerate() {
+            let (a, b) = (original.vertices[i], target.vertices[i]);
+            assert!((*v - (a + b) * 0.5).length() < 1e-6);
+            assert!((*v - a).length() > 0.1 && (*v - b).length() > 0.1);
+        }
+        // Normales recalculadas: siguen siendo unitarias y hacia afuera del centro nuevo
+        let center = Vector3::new(0.0, 0.25, 0.0);
+        for (face, corners) in mid.faces.iter().zip(&mid.face_normals) {
+            for (&v, &n) in face.iter().zip(corners) {
+                let n = mid.normals[n];
+                assert!((n.length() - 1.0).abs() < 1e-4);
+                assert!(n.dot(mid.vertices[v] - center) > 0.9 * (mid.vertices[v] - center).length());
+            }
+        }
+    }
+
+    #[test]
+    fn weighting_changes_shared_vertex_normal() {
+        // Vértice 1 compartido por una cara grande (normal +Z) y tres chicas (normal +X)
+        let text = "v 0 0 0\nv 10 0 0\nv 0 10 0\nv 0 0.1 0\nv 0 0.05 0.1\nv 0 0 0.1\n\
+                    f 1 2 3\nf 1 4 5\nf 1 5 6\nf 1 4 6\n";
+        let normal_at_origin = |weighting| {
+            let mut model = parse(text);
+            model.compute_smooth_normals_ex(weighting);
+            corner_normal(&model, 0, 0)
+        };
+        let area = normal_at_origin(NormalWeighting::Area);
+        let uniform = normal_at_origin(NormalWeighting::Uniform);
+        let angle = normal_at_origin(NormalWeighting::Angle);
+
+        // Por área domina la cara grande; contando caras, las chicas
+        assert!(area.z > area.x, "{area:?}");
+        assert!(uniform.x > uniform.z, "{uniform:?}");
+        assert!((angle - area).length() > 1e-3 && (angle - uniform).length() > 1e-3);
+    }
+
+    #[test]
+    fn face_token_forms() {
+        assert_eq!(parse_face_token("3"), (Some(3), None, None));
+        assert_eq!(parse_face_token("3/4"), (Some(3), Some(4), None));
+        assert_eq!(parse_face_token("3//5"), (Some(3), None, Some(5)));
+        assert_eq!(parse_face_token("3/4/5"), (Some(3), Some(4), Some(5)));
+        assert_eq!(parse_face_token("3/"), (Some(3), None, None));
+        // Negativos (relativos) pasan tal cual; basura da `None`
+        assert_eq!(parse_face_token("-1//-2"), (Some(-1), None, Some(-2)));
+        assert_eq!(parse_face_token("x/1"), (None, Some(1), None));
+    }
+
+    #[test]
+    fn crlf_file_with_bom_parses() {
+        let path = std::env::temp_dir().join(format!("lab4-bom-{}.obj", std::process::id()));
+        std::fs::write(&path, "\u{feff}v 0 0 0\r\nv 1 0 0\r\nv 0 1 0\r\nvn 0 0 1\r\nf 1//1 2//1 3//1\r\n").unwrap();
+        let model = ObjModel::load(path.to_str().unwrap());
+        std::fs::remove_file(&path).ok();
+        let model = model.expect("OBJ con BOM y CRLF");
+        assert_eq!(model.vertices, [Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)]);
+        assert_eq!(model.faces, [vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn decimate_reduces_faces_and_keeps_vertices_on_the_sphere() {
+        let mut sphere = crate::geometria::generate_icosphere(1.0, 4);
+        assert_eq!(sphere.faces.len(), 5120);
+        sphere.decimate(500);
+        assert!(sphere.faces.len() <= 500, "{} caras", sphere.faces.len());
+        // Hacia el objetivo, no una esfera de un puñado de caras
+        assert!(sphere.faces.len() >= 150, "{} caras", sphere.faces.len());
+        assert!(sphere.vertices.iter().all(|v| (v.length() - 1.0).abs() < 1e-4));
+        assert!(sphere.faces.iter().flatten().all(|&i| i < sphere.vertices.len()));
+    }
+
+    #[test]
+    fn cluster_simplify_leaves_about_one_vertex_per_cell() {
+        let mut sphere = crate::geometria::generate_icosphere(1.0, 4);
+        let faces_before = sphere.faces.len();
+        let grid = 4;
+        let (min, max) = sphere.bounding_box();
+        let cell = (max - min).x.max((max - min).y).max((max - min).z) * 1.0001 / grid as f32;
+        let occupied: std::collections::HashSet<_> = sphere
+            .vertices
+            .iter()
+            .map(|&v| {
+                let c = (v - min) / cell;
+                (c.x.floor() as i32, c.y.floor() as i32, c.z.floor() as i32)
+            })
+            .collect();
+
+        sphere.cluster_simplify(grid);
+        assert!(sphere.vertices.len() <= occupied.len());
+        assert!(sphere.vertices.len() * 10 >= occupied.len() * 8, "{} de {}", sphere.vertices.len(), occupied.len());
+        assert!(sphere.faces.len() * 20 < faces_before, "{} caras", sphere.faces.len());
+        assert!(sphere.faces.iter().all(|f| f[0] != f[1] && f[1] != f[2] && f[0] != f[2]));
+    }
+
+    #[test]
+    fn make_irregular_is_lumpy_and_deterministic() {
+        // Desviación relativa de los radios desde el centro de la caja
+        let spread = |model: &ObjModel| {
+            let (min, max) = model.bounding_box();
+            let center = (min + max) * 0.5;
+            let radii: Vec<f32> = model.vertices.iter().map(|&v| (v - center).length()).collect();
+            let mean = radii.iter().sum::<f32>() / radii.len() as f32;
+            let var = radii.iter().map(|r| (r - mean).powi(2)).sum::<f32>() / radii.len() as f32;
+            var.sqrt() / mean
+        };
+        let sphere = crate::geometria::generate_icosphere(1.0, 3);
+        assert!(spread(&sphere) < 1e-3);
+
+        let potato = |seed| {
+            let mut model = sphere.clone();
+            model.make_irregular(0.3, 2.0, seed);
+            model
+        };
+        let a = potato(7);
+        assert!(spread(&a) > 0.05, "desviación {}", spread(&a));
+        assert_eq!(a.vertices, potato(7).vertices);
+        assert_ne!(a.vertices, potato(8).vertices);
+        assert_eq!(a.normals.len(), a.vertices.len());
+    }
+
+    #[test]
+    fn short_v_line_is_an_error_when_strict_and_skipped_when_lenient() {
+        let text = "v 0 0 0\nv 1 0 0\nv 1 2\nv 0 1 0\nf 1 2 3\n";
+        match ObjModel::load_from_reader(text.as_bytes()) {
+            Err(ObjError::MissingCoords { line: 3, found: 2 }) => {}
+            other => panic!("se esperaba MissingCoords en la línea 3: {other:?}"),
+        }
+
+        let model = ObjModel::parse(text.as_bytes(), false).expect("permisivo");
+        assert_eq!(model.vertices.len(), 3);
+        assert_eq!(model.vertices[2], Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(model.faces, vec![[0, 1, 2]]);
+        // Con `w` (o colores) de más sigue siendo válido
+        assert_eq!(parse("v 1 2 3 1.0\nv 0 0 0\nv 0 1 0\nf 1 2 3\n").vertices[0], Vector3::new(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn lenient_drops_out_of_range_corners() {
+        let text = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nf 1 2 9\nf 1 2 4 7\nf 2 4 3\n";
+        assert!(matches!(ObjModel::load_from_reader(text.as_bytes()), Err(ObjError::BadFace { line: 5 })));
+
+        // Permisivo: la primera cara queda con dos esquinas y se salta; la segunda pierde la 7
+        let mut model = ObjModel::parse(text.as_bytes(), false).expect("permisivo");
+        assert_eq!(model.faces, vec![vec![0, 1, 3], vec![1, 3, 2]]);
+        // Las normales suaves ya no indexan fuera del pool
+        model.compute_smooth_normals();
+        assert_eq!(model.normals.len(), model.vertices.len());
+    }
+
+    #[test]
+    #[should_panic(expected = "fuera de rango")]
+    fn set_vertex_past_the_end_panics() {
+        let mut model = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n");
+        model.set_vertex(3, Vector3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn add_face_rejects_bad_indices_and_leaves_the_model_alone() {
+        let mut model = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nf 1 2 3\n");
+        let faces = model.faces.len();
+        let out_of_range = model.add_face(vec![1, 3, 4]);
+        assert!(
+            matches!(&out_of_range, Err(ObjError::InvalidFace { indices, vertex_count: 4 }) if indices == &[1, 3, 4]),
+            "{out_of_range:?}"
+        );
+        let too_few = model.add_face(vec![1, 3]);
+        assert!(
+            matches!(&too_few, Err(ObjError::InvalidFace { indices, vertex_count: 4 }) if indices == &[1, 3]),
+            "{too_few:?}"
+        );
+        assert_eq!(model.faces.len(), faces);
+        assert_eq!(model.add_face(vec![1, 3, 2]).ok(), Some(faces));
     }
 }
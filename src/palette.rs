@@ -0,0 +1,166 @@
+//! Paletas procedurales: conversión HSV, rampas de color y un generador que a partir de una
+//! semilla arma colores que combinan entre sí (armonías clásicas del círculo cromático).
+use raylib::prelude::*;
+use crate::sampling::hash01;
+
+/// Colores por paleta de `generate_palette`, de oscuro a claro.
+pub const PALETTE_STOPS: usize = 5;
+
+/// Cuánto se puede correr cada color del tono de su grupo (grados, ±).
+const HUE_JITTER: f32 = 8.0;
+
+/// HSV -> RGB opaco. `h` en grados (cualquier valor, se envuelve a 0..360), `s` y `v` en 0..1.
+pub fn hsv_to_color(h: f32, s: f32, v: f32) -> Color {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let (s, v) = (s.clamp(0.0, 1.0), v.clamp(0.0, 1.0));
+    let c = v * s;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = v - c;
+    let to_u8 = |k: f32| ((k + m) * 255.0).round() as u8;
+    Color::new(to_u8(r), to_u8(g), to_u8(b), 255)
+}
+
+/// RGB -> (h en grados 0..360, s, v). Los grises dan `h = 0`.
+pub fn color_to_hsv(c: Color) -> (f32, f32, f32) {
+    let (r, g, b) = (c.r as f32 / 255.0, c.g as f32 / 255.0, c.b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+    let h = if delta <= 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let s = if max > 0.0 { delta / max } else { 0.0 };
+    (h, s, max)
+}
+
+/// Degradado por tramos: colores en posiciones de 0..1, interpolados linealmente en RGB.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorRamp {
+    stops: Vec<(f32, Color)>,
+}
+
+impl ColorRamp {
+    /// Rampa con los `stops` dados (se ordenan por posición). Sin stops `sample` da negro.
+    pub fn new(mut stops: Vec<(f32, Color)>) -> Self {
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { stops }
+    }
+
+    /// Rampa con `colors` repartidos a la misma distancia entre 0 y 1.
+    pub fn even(colors: &[Color]) -> Self {
+        let last = colors.len().saturating_sub(1).max(1) as f32;
+        Self::new(colors.iter().enumerate().map(|(i, &c)| (i as f32 / last, c)).collect())
+    }
+
+    /// Color en `t`; fuera del rango de los stops se queda con el del extremo.
+    pub fn sample(&self, t: f32) -> Color {
+        let (Some(first), Some(last)) = (self.stops.first(), self.stops.last()) else {
+            return Color::BLACK;
+        };
+        if t <= first.0 {
+            return first.1;
+        }
+        if t >= last.0 {
+            return last.1;
+        }
+        let i = self.stops.iter().position(|s| s.0 > t).unwrap_or(self.stops.len() - 1);
+        let ((t0, a), (t1, b)) = (self.stops[i - 1], self.stops[i]);
+        let k = if t1 > t0 { (t - t0) / (t1 - t0) } else { 0.0 };
+        let mix = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * k).round() as u8;
+        Color::new(mix(a.r, b.r), mix(a.g, b.g), mix(a.b, b.b), mix(a.a, b.a))
+    }
+
+    /// Los colores de los stops, en orden.
+    pub fn colors(&self) -> Vec<Color> {
+        self.stops.iter().map(|s| s.1).collect()
+    }
+}
+
+/// Cómo se eligen los tonos a partir del tono base.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Harmony {
+    /// Tono base y su opuesto (+180°).
+    Complementary,
+    /// Tres tonos vecinos (-30°, 0, +30°).
+    Analogous,
+    /// Tres tonos repartidos (0, +120°, +240°).
+    Triadic,
+}
+
+impl Harmony {
+    /// Desplazamientos de tono (grados) de cada grupo respecto al tono base.
+    pub fn hue_offsets(self) -> &'static [f32] {
+        match self {
+            Harmony::Complementary => &[0.0, 180.0],
+            Harmony::Analogous => &[-30.0, 0.0, 30.0],
+            Harmony::Triadic => &[0.0, 120.0, 240.0],
+        }
+    }
+}
+
+/// Paleta de `PALETTE_STOPS` colores que combinan: el tono base sale de `seed` y cada grupo
+/// de `harmony` se lleva un tramo seguido de la rampa (de oscuro a claro), con la saturación
+/// elegida por la semilla y un poco de variación de tono dentro del grupo. La misma semilla
+/// da la misma paleta. `PaletteShader` la muestrea con `ColorRamp::sample`.
+pub fn generate_palette(seed: u32, harmony: Harmony) -> ColorRamp {
+    let base_hue = hash01(0, seed) * 360.0;
+    let saturation = 0.45 + 0.35 * hash01(1, seed);
+    let offsets = harmony.hue_offsets();
+
+    let colors: Vec<Color> = (0..PALETTE_STOPS)
+        .map(|i| {
+            let group = i * offsets.len() / PALETTE_STOPS;
+            let k = i as u32 + 2;
+            let hue = base_hue + offsets[group] + (hash01(k, seed) * 2.0 - 1.0) * HUE_JITTER;
+            let s = (saturation + (hash01(k + 100, seed) - 0.5) * 0.2).clamp(0.0, 1.0);
+            let v = 0.25 + 0.7 * i as f32 / (PALETTE_STOPS - 1) as f32;
+            hsv_to_color(hue, s, v)
+        })
+        .collect();
+    ColorRamp::even(&colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Distancia entre dos tonos en el círculo (0..180°).
+    fn hue_distance(a: f32, b: f32) -> f32 {
+        let d = (a - b).rem_euclid(360.0);
+        d.min(360.0 - d)
+    }
+
+    #[test]
+    fn complementary_gives_two_opposite_hue_groups_and_reproduces() {
+        for seed in [1, 42, 1234, 0xDEAD_BEEF] {
+            let palette = generate_palette(seed, Harmony::Complementary);
+            assert_eq!(palette, generate_palette(seed, Harmony::Complementary));
+            let hues: Vec<f32> = palette.stops.iter().map(|&(_, c)| color_to_hsv(c).0).collect();
+            assert_eq!(hues.len(), PALETTE_STOPS);
+            // Los primeros tramos son el tono base y el resto su opuesto
+            let split = PALETTE_STOPS.div_ceil(2);
+            let (base, opposite) = hues.split_at(split);
+            let close = 2.0 * HUE_JITTER + 4.0;
+            for group in [base, opposite] {
+                assert!(group.iter().all(|&h| hue_distance(h, group[0]) <= close), "semilla {seed}: {hues:?}");
+            }
+            for (&a, &b) in base.iter().zip(opposite) {
+                assert!((hue_distance(a, b) - 180.0).abs() <= close, "semilla {seed}: {hues:?}");
+            }
+        }
+        assert_ne!(generate_palette(1, Harmony::Complementary), generate_palette(2, Harmony::Complementary));
+    }
+}
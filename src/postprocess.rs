@@ -0,0 +1,186 @@
+use raylib::prelude::*;
+use crate::framebuffer::Framebuffer;
+
+/// Luminancia relativa (Rec. 709) de un color RGB lineal.
+pub fn luminance(c: Vector3) -> f32 {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+/// Color de 8 bits -> RGB lineal en 0..1 (aprox. gamma 2.2).
+pub fn color_to_linear(c: Color) -> Vector3 {
+    let f = |v: u8| (v as f32 / 255.0).powf(2.2);
+    Vector3::new(f(c.r), f(c.g), f(c.b))
+}
+
+/// RGB lineal (0..1, se recorta) -> color de 8 bits. Inverso de `color_to_linear`.
+pub fn linear_to_color(c: Vector3, alpha: u8) -> Color {
+    let f = |v: f32| (v.clamp(0.0, 1.0).powf(1.0 / 2.2) * 255.0).round() as u8;
+    Color::new(f(c.x), f(c.y), f(c.z), alpha)
+}
+
+/// Tone mapping de Reinhard por canal: [0, ∞) -> [0, 1).
+pub fn reinhard(c: Vector3) -> Vector3 {
+    Vector3::new(c.x / (1.0 + c.x), c.y / (1.0 + c.y), c.z / (1.0 + c.z))
+}
+
+/// Exposición (multiplicador del color lineal) de `apply_exposure` por defecto.
+pub const DEFAULT_EXPOSURE: f32 = 1.0;
+
+/// Aplica `exposure` al color lineal y vuelve a 8 bits. Con buffer HDR se parte de él y se
+/// comprime con `reinhard` (también con `DEFAULT_EXPOSURE`: el tone mapping cambia la
+/// imagen); sin él se multiplica el color de 8 bits linealizado y se recorta (así, con
+/// `DEFAULT_EXPOSURE` la imagen queda igual).
+pub fn apply_exposure(fb: &mut Framebuffer, exposure: f32) {
+    let (w, h) = (fb.width as usize, fb.height as usize);
+    let pixels = fb.pixels();
+    let hdr = fb.hdr_buffer.clone();
+
+    for y in 0..h {
+        for x in 0..w {
+            let i = y * w + x;
+            let c = pixels[i];
+            let out = match &hdr {
+                Some(hdr) => reinhard(hdr[i] * exposure),
+                None => color_to_linear(c) * exposure,
+            };
+            fb.set_pixel_with_color(x as i32, y as i32, linear_to_color(out, c.a));
+        }
+    }
+}
+
+/// Configuración del bloom.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BloomSettings {
+    /// Luminancia lineal a partir de la cual un píxel brilla. 1.0 = más que blanco de pantalla,
+    /// o sea, sólo zonas realmente emisivas cuando hay buffer HDR.
+    pub threshold: f32,
+    /// Cuánto del brillo difuminado se suma a la imagen.
+    pub intensity: f32,
+    /// Radio del blur (en píxeles).
+    pub radius: i32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self { threshold: 1.0, intensity: 0.6, radius: 6 }
+    }
+}
+
+/// Extrae la parte brillante: lo que excede `threshold` en luminancia (0 en el resto).
+/// Lee el buffer HDR si existe; si no, el color de 8 bits convertido a lineal (donde el
+/// máximo es 1.0, así que con el umbral por defecto sólo el blanco puro llega a brillar).
+pub fn bloom_extract(fb: &Framebuffer, threshold: f32) -> Vec<Vector3> {
+    let source: Vec<Vector3> = match &fb.hdr_buffer {
+        Some(hdr) => hdr.clone(),
+        None => fb.pixels().iter().map(|&c| color_to_linear(c)).collect(),
+    };
+    source
+        .into_iter()
+        .map(|c| {
+            let lum = luminance(c);
+            if lum > threshold && lum > 0.0 {
+                c * ((lum - threshold) / lum)
+            } else {
+                Vector3::new(0.0, 0.0, 0.0)
+            }
+        })
+        .collect()
+}
+
+/// Blur de caja separable (horizontal + vertical).
+fn box_blur(src: &[Vector3], width: usize, height: usize, radius: i32) -> Vec<Vector3> {
+    let zero = Vector3::new(0.0, 0.0, 0.0);
+    let norm = 1.0 / (2 * radius + 1) as f32;
+    let mut tmp = vec![zero; src.len()];
+    let mut out = vec![zero; src.len()];
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut acc = zero;
+            for k in -radius..=radius {
+                let xx = (x as i32 + k).clamp(0, width as i32 - 1) as usize;
+                acc += src[y * width + xx];
+            }
+            tmp[y * width + x] = acc * norm;
+        }
+    }
+    for y in 0..height {
+        for x in 0..width {
+            let mut acc = zero;
+            for k in -radius..=radius {
+                let yy = (y as i32 + k).clamp(0, height as i32 - 1) as usize;
+                acc += tmp[yy * width + x];
+            }
+            out[y * width + x] = acc * norm;
+        }
+    }
+    out
+}
+
+/// Bloom: extrae lo que pasa del umbral, lo difumina y lo suma al color buffer.
+pub fn apply_bloom(fb: &mut Framebuffer, settings: &BloomSettings) {
+    let (w, h) = (fb.width as usize, fb.height as usize);
+    let bright = bloom_extract(fb, settings.threshold);
+    let glow = box_blur(&bright, w, h, settings.radius.max(1));
+    let pixels = fb.pixels();
+
+    for y in 0..h {
+        for x in 0..w {
+            let g = glow[y * w + x] * settings.intensity;
+            if g.x <= 0.0 && g.y <= 0.0 && g.z <= 0.0 {
+                continue;
+            }
+            let c = pixels[y * w + x];
+            let add = |v: u8, e: f32| (v as f32 + e * 255.0).clamp(0.0, 255.0) as u8;
+            let out = Color::new(add(c.r, g.x), add(c.g, g.y), add(c.b, g.z), c.a);
+            fb.set_pixel_with_color(x as i32, y as i32, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_hdr_luminance_above_threshold_blooms() {
+        let mut fb = Framebuffer::new(4, 1, Color::BLACK);
+        fb.enable_hdr();
+        fb.set_pixel_hdr(0, 0, Vector3::new(5.0, 5.0, 5.0));
+        fb.set_pixel_hdr(2, 0, Vector3::new(0.8, 0.8, 0.8));
+
+        let bright = bloom_extract(&fb, BloomSettings::default().threshold);
+        assert!(luminance(bright[0]) > 3.9);
+        assert_eq!(bright[2], Vector3::new(0.0, 0.0, 0.0));
+
+        // Sin HDR, un gris claro de 8 bits tampoco llega a brillar
+        let mut ldr = Framebuffer::new(1, 1, Color::BLACK);
+        ldr.set_pixel_with_color(0, 0, Color::new(230, 230, 230, 255));
+        assert_eq!(bloom_extract(&ldr, 1.0)[0], Vector3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn higher_exposure_is_brighter_with_and_without_hdr() {
+        let grey = Color::new(90, 90, 90, 255);
+        let exposed = |hdr: bool, exposure: f32| {
+            let mut fb = Framebuffer::new(2, 1, grey);
+            if hdr {
+                fb.enable_hdr();
+                fb.set_pixel_hdr(0, 0, Vector3::new(0.3, 0.3, 0.3));
+                fb.set_pixel_hdr(1, 0, Vector3::new(2.5, 2.0, 1.5)); // más que blanco
+            }
+            apply_exposure(&mut fb, exposure);
+            fb.pixels().iter().map(|&c| luminance(color_to_linear(c))).collect::<Vec<f32>>()
+        };
+        for hdr in [false, true] {
+            let [dark, normal, bright] = [0.5, 1.0, 2.0].map(|e| exposed(hdr, e));
+            for i in 0..2 {
+                assert!(dark[i] < normal[i] && normal[i] < bright[i], "hdr = {hdr}, píxel {i}: {dark:?} {normal:?} {bright:?}");
+            }
+        }
+        // Sin HDR, la exposición por defecto no cambia la imagen
+        let mut fb = Framebuffer::new(2, 1, grey);
+        apply_exposure(&mut fb, DEFAULT_EXPOSURE);
+        assert!(fb.pixels().iter().all(|&c| c == grey));
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Etapas del pipeline que se miden por frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Transform,
+    Raster,
+    PostProcess,
+    Upload,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 4] = [Stage::Transform, Stage::Raster, Stage::PostProcess, Stage::Upload];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Transform => "transform",
+            Stage::Raster => "raster",
+            Stage::PostProcess => "post",
+            Stage::Upload => "upload",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Cronómetro por etapa con promedio móvil de los últimos `window` frames.
+/// Desactivado no toca `Instant`: `start` devuelve `None` y `stop` no hace nada.
+pub struct FrameProfiler {
+    enabled: bool,
+    window: usize,
+    history: [VecDeque<f32>; 4], // ms por frame, por etapa
+    current: [f32; 4],
+    frames: u64,
+    report_every: u64,
+}
+
+impl FrameProfiler {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            window: 60,
+            history: Default::default(),
+            current: [0.0; 4],
+            frames: 0,
+            report_every: 60,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Marca el inicio de una medición (sólo si el profiler está activo).
+    pub fn start(&self) -> Option<Instant> {
+        self.enabled.then(Instant::now)
+    }
+
+    /// Suma el tiempo transcurrido desde `start` a la etapa indicada.
+    pub fn stop(&mut self, stage: Stage, start: Option<Instant>) {
+        if let Some(t0) = start {
+            self.current[stage.index()] += t0.elapsed().as_secs_f32() * 1000.0;
+        }
+    }
+
+    /// Cierra el frame: guarda los tiempos en el historial e imprime el promedio cada tanto.
+    pub fn end_frame(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        for stage in Stage::ALL {
+            let i = stage.index();
+            let h = &mut self.history[i];
+            h.push_back(self.current[i]);
+            if h.len() > self.window {
+                h.pop_front();
+            }
+            self.current[i] = 0.0;
+        }
+        self.frames += 1;
+        if self.frames.is_multiple_of(self.report_every) {
+            println!("{}", self.report());
+        }
+    }
+
+    /// Promedio móvil (ms) de una etapa.
+    pub fn average_ms(&self, stage: Stage) -> f32 {
+        let h = &self.history[stage.index()];
+        if h.is_empty() {
+            return 0.0;
+        }
+        h.iter().sum::<f32>() / h.len() as f32
+    }
+
+    /// Resumen de una línea, p. ej. "transform 0.41ms | raster 12.30ms | ...".
+    pub fn report(&self) -> String {
+        Stage::ALL
+            .iter()
+            .map(|&s| format!("{} {:.2}ms", s.name(), self.average_ms(s)))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
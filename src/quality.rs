@@ -0,0 +1,117 @@
+/// Parámetros de calidad que el render puede bajar para ganar velocidad.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RenderQuality {
+    /// Factor de supersampling por eje (1 = sin SSAA).
+    pub ssaa: u32,
+    /// Tope de octavas de FBM en los shaders.
+    pub max_octaves: u32,
+}
+
+/// Niveles de menor a mayor calidad. El penúltimo es el look original (FBM completo, sin SSAA).
+pub const QUALITY_LEVELS: [RenderQuality; 5] = [
+    RenderQuality { ssaa: 1, max_octaves: 2 },
+    RenderQuality { ssaa: 1, max_octaves: 3 },
+    RenderQuality { ssaa: 1, max_octaves: 4 },
+    RenderQuality { ssaa: 1, max_octaves: 6 },
+    RenderQuality { ssaa: 2, max_octaves: 6 },
+];
+
+/// Escalador automático de calidad: promedia el tiempo de frame y sube/baja un nivel
+/// cuando se pasa del objetivo o sobra margen. Tiene histéresis y un enfriamiento entre
+/// cambios para no oscilar cada frame.
+pub struct QualityController {
+    target_ms: f32,
+    level: usize,
+    min_level: usize,
+    max_level: usize,
+    avg_ms: f32,
+    cooldown: u32,
+}
+
+impl QualityController {
+    /// Frames que espera tras un cambio antes de volver a decidir.
+    const COOLDOWN_FRAMES: u32 = 30;
+
+    pub fn new(target_ms: f32) -> Self {
+        let max_level = QUALITY_LEVELS.len() - 1;
+        Self {
+            target_ms,
+            level: max_level - 1,
+            min_level: 0,
+            max_level,
+            avg_ms: target_ms,
+            cooldown: 0,
+        }
+    }
+
+    /// Restringe los niveles permitidos (índices en `QUALITY_LEVELS`).
+    pub fn with_bounds(mut self, min_level: usize, max_level: usize) -> Self {
+        self.max_level = max_level.min(QUALITY_LEVELS.len() - 1);
+        self.min_level = min_level.min(self.max_level);
+        self.level = self.level.clamp(self.min_level, self.max_level);
+        self
+    }
+
+    pub fn quality(&self) -> RenderQuality {
+        QUALITY_LEVELS[self.level]
+    }
+
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Alimenta el tiempo del último frame (ms) y devuelve la calidad a usar en el siguiente.
+    pub fn update(&mut self, frame_ms: f32) -> RenderQuality {
+        // promedio exponencial para ignorar picos aislados
+        self.avg_ms = self.avg_ms * 0.9 + frame_ms * 0.1;
+
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+            return self.quality();
+        }
+
+        if self.avg_ms > self.target_ms * 1.1 && self.level > self.min_level {
+            self.level -= 1;
+            self.cooldown = Self::COOLDOWN_FRAMES;
+        } else if self.avg_ms < self.target_ms * 0.6 && self.level < self.max_level {
+            self.level += 1;
+            self.cooldown = Self::COOLDOWN_FRAMES;
+        }
+        self.quality()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_frames_lower_quality_and_fast_frames_raise_it() {
+        let mut qc = QualityController::new(16.6);
+        let start = qc.level();
+
+        for _ in 0..500 {
+            qc.update(50.0);
+        }
+        assert_eq!(qc.level(), 0);
+        assert!(qc.level() < start);
+
+        for _ in 0..500 {
+            qc.update(2.0);
+        }
+        assert_eq!(qc.level(), QUALITY_LEVELS.len() - 1);
+    }
+
+    #[test]
+    fn bounds_are_respected() {
+        let mut qc = QualityController::new(16.6).with_bounds(1, 2);
+        for _ in 0..500 {
+            qc.update(50.0);
+        }
+        assert_eq!(qc.level(), 1);
+        for _ in 0..500 {
+            qc.update(2.0);
+        }
+        assert_eq!(qc.level(), 2);
+    }
+}
@@ -0,0 +1,127 @@
+use raylib::prelude::*;
+
+/// Cómo repartir puntos en el cuadrado unitario (estrellas, escombros del cinturón, etc).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Distribution {
+    /// Hash pseudoaleatorio: rápido pero forma grumos.
+    Uniform,
+    /// Secuencia de Halton (bases 2 y 3): baja discrepancia, cobertura más pareja.
+    Halton,
+}
+
+/// Elemento `index` de la secuencia de van der Corput en la base dada (radical inverse).
+/// `halton(1, 2) = 0.5`, `halton(2, 2) = 0.25`, `halton(3, 2) = 0.75`, ...
+pub fn halton(mut index: u32, base: u32) -> f32 {
+    let mut f = 1.0;
+    let mut r = 0.0;
+    while index > 0 {
+        f /= base as f32;
+        r += f * (index % base) as f32;
+        index /= base;
+    }
+    r
+}
+
+/// Hash entero -> [0, 1). Misma idea que el hash del ruido de los shaders.
+pub fn hash01(index: u32, seed: u32) -> f32 {
+    let mut h = index.wrapping_mul(0x9E37_79B9) ^ seed.wrapping_mul(0x85EB_CA6B);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7FEB_352D);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846C_A68B);
+    h ^= h >> 16;
+    (h >> 8) as f32 / (1u32 << 24) as f32
+}
+
+/// Semilla estable a partir de un nombre (FNV-1a de 32 bits sobre los bytes UTF-8): el mismo
+/// nombre da la misma semilla en cualquier corrida y máquina, a diferencia de `std::hash`.
+pub fn seed_from_name(name: &str) -> u32 {
+    name.bytes().fold(0x811C_9DC5, |h, b| (h ^ b as u32).wrapping_mul(0x0100_0193))
+}
+
+/// `count` puntos en [0, 1)² según la distribución. `seed` desplaza la secuencia
+/// (en Halton se usa como offset de índice para que dos campos no coincidan).
+pub fn sample_unit_square(distribution: Distribution, count: usize, seed: u32) -> Vec<Vector2> {
+    (0..count as u32)
+        .map(|i| match distribution {
+            Distribution::Uniform => Vector2::new(hash01(i, seed), hash01(i, seed ^ 0x5bd1_e995)),
+            Distribution::Halton => {
+                // Halton empieza en 1: el índice 0 siempre cae en la esquina (0, 0)
+                let k = i.wrapping_add(seed).wrapping_add(1);
+                Vector2::new(halton(k, 2), halton(k, 3))
+            }
+        })
+        .collect()
+}
+
+/// Dirección uniforme en la esfera unitaria a partir de dos números en [0, 1).
+pub fn unit_sphere_point(u: f32, v: f32) -> Vector3 {
+    let y = 1.0 - 2.0 * u;
+    let r = (1.0 - y * y).max(0.0).sqrt();
+    let phi = std::f32::consts::TAU * v;
+    Vector3::new(r * phi.cos(), y, r * phi.sin())
+}
+
+/// Ruido de valor 3D en [-1, 1] con interpolación suave entre los puntos de la rejilla
+/// entera, cada uno con `hash01`. A diferencia del ruido de los shaders no depende de
+/// ningún estado global: el mismo `seed` da siempre el mismo campo.
+pub fn value_noise3(p: Vector3, seed: u32) -> f32 {
+    let (x0, y0, z0) = (p.x.floor(), p.y.floor(), p.z.floor());
+    let fade = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (fx, fy, fz) = (fade(p.x - x0), fade(p.y - y0), fade(p.z - z0));
+    let lattice = |dx: i32, dy: i32, dz: i32| {
+        let (x, y, z) = (x0 as i32 + dx, y0 as i32 + dy, z0 as i32 + dz);
+        let index = (x as u32).wrapping_mul(73_856_093) ^ (y as u32).wrapping_mul(19_349_663) ^ (z as u32).wrapping_mul(83_492_791);
+        hash01(index, seed)
+    };
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let layer = |dz: i32| {
+        lerp(lerp(lattice(0, 0, dz), lattice(1, 0, dz), fx), lerp(lattice(0, 1, dz), lattice(1, 1, dz), fx), fy)
+    };
+    lerp(layer(0), layer(1), fz) * 2.0 - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn halton_first_values() {
+        let base2: Vec<f32> = (1..=4).map(|i| halton(i, 2)).collect();
+        assert_eq!(base2, [0.5, 0.25, 0.75, 0.125]);
+        let base3: Vec<f32> = (1..=3).map(|i| halton(i, 3)).collect();
+        for (got, want) in base3.iter().zip([1.0 / 3.0, 2.0 / 3.0, 1.0 / 9.0]) {
+            assert!((got - want).abs() < 1e-6, "{got} != {want}");
+        }
+    }
+
+    /// Celdas de una rejilla de `n x n` sin ningún punto.
+    fn empty_cells(points: &[Vector2], n: usize) -> usize {
+        let mut hit = vec![false; n * n];
+        for p in points {
+            let (x, y) = ((p.x * n as f32) as usize, (p.y * n as f32) as usize);
+            hit[y.min(n - 1) * n + x.min(n - 1)] = true;
+        }
+        hit.iter().filter(|&&h| !h).count()
+    }
+
+    #[test]
+    fn halton_covers_square_more_evenly_than_hash() {
+        let halton = sample_unit_square(Distribution::Halton, 100, 0);
+        let uniform = sample_unit_square(Distribution::Uniform, 100, 0);
+        assert!(empty_cells(&halton, 10) < empty_cells(&uniform, 10));
+    }
+
+    #[test]
+    fn same_name_same_seed_and_different_names_differ() {
+        assert_eq!(seed_from_name("Kepler-22b"), seed_from_name("Kepler-22b"));
+        // FNV-1a de 32 bits: valor fijo, no depende de la corrida ni de la máquina
+        assert_eq!(seed_from_name(""), 0x811C_9DC5);
+        let names = ["Kepler-22b", "Kepler-22c", "Trappist", "Luna", "luna"];
+        for (i, a) in names.iter().enumerate() {
+            for b in &names[i + 1..] {
+                assert_ne!(seed_from_name(a), seed_from_name(b), "{a} vs {b}");
+            }
+        }
+    }
+}
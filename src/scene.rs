@@ -0,0 +1,921 @@
+//! Escena: cámara, luces y cuerpos (malla + transformación + shader + órbita).
+//! `update` avanza la animación y `render` dibuja todos los cuerpos visibles; las apps sólo
+//! deciden qué cuerpos hay y cómo se controlan.
+use raylib::prelude::*;
+use std::borrow::Cow;
+use std::sync::Arc;
+use crate::camera::{Camera, BASE_EYE, DEFAULT_FOV};
+use crate::framebuffer::Framebuffer;
+use crate::geometria::{model_matrix, transform_normals_matrix, transform_vertex, TransformCache};
+use crate::obj_loader::ObjModel;
+use crate::profiler::{FrameProfiler, Stage};
+pub use crate::shader::Light;
+use crate::shader::{self, Lights, PlanetShader, Uniforms};
+use crate::tiled::{TiledRasterizer, DEFAULT_TILE_SIZE};
+use crate::triangle::{self, DebugView, FragmentTarget, RasterOptions};
+
+/// Cómo se dibujan los modelos. Se recorre con una sola tecla (`next`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+    Shaded,
+    WireOnShaded,
+    Wireframe,
+    Normals,
+    Depth,
+    Clay,
+    Points,
+}
+
+impl RenderMode {
+    pub const ALL: [RenderMode; 7] = [
+        RenderMode::Shaded,
+        RenderMode::WireOnShaded,
+        RenderMode::Wireframe,
+        RenderMode::Normals,
+        RenderMode::Depth,
+        RenderMode::Clay,
+        RenderMode::Points,
+    ];
+
+    /// Siguiente modo; después del último vuelve al primero.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&m| m == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderMode::Shaded => "sombreado",
+            RenderMode::WireOnShaded => "sombreado + aristas",
+            RenderMode::Wireframe => "alambre",
+            RenderMode::Normals => "normales",
+            RenderMode::Depth => "profundidad",
+            RenderMode::Clay => "arcilla",
+            RenderMode::Points => "puntos",
+        }
+    }
+
+    /// Vista de depuración del rasterizador para los modos rellenos que la usan.
+    pub fn debug_view(self) -> Option<DebugView> {
+        match self {
+            RenderMode::Normals => Some(DebugView::Normals),
+            RenderMode::Depth => Some(DebugView::Depth),
+            RenderMode::Clay => Some(DebugView::Clay),
+            _ => None,
+        }
+    }
+}
+
+/// Órbita circular en el plano XZ alrededor del centro del padre (o del origen de la escena).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Orbit {
+    pub radius: f32,
+    pub height: f32, // desplazamiento en Y del plano de la órbita
+    pub speed: f32,  // rad/s
+    pub angle: f32,
+}
+
+impl Orbit {
+    pub fn new(radius: f32, height: f32, speed: f32) -> Self {
+        Self { radius, height, speed, angle: 0.0 }
+    }
+
+    /// Posición actual (sin zoom).
+    pub fn position(&self) -> Vector3 {
+        Vector3::new(self.angle.cos() * self.radius, self.height, self.angle.sin() * self.radius)
+    }
+}
+
+/// Velocidad (rad/s) de la "respiración" de los cuerpos con `pulse > 0`.
+pub const PULSE_SPEED: f32 = 3.0;
+
+/// Un cuerpo de la escena. Las posiciones y escalas están en unidades de escena: el zoom de
+/// `Scene` se aplica encima al transformar. Con `parent` el cuerpo cuelga de otro (luna ->
+/// planeta -> estrella): su transformación local (escala, inclinación, giro y centro) se
+/// compone con la de cada ancestro (ver `Scene::world_transform`), así que la órbita gira,
+/// se inclina y se escala con el padre.
+pub struct Body {
+    pub name: String,
+    pub mesh: ObjModel,
+    pub shader: Arc<dyn PlanetShader>, // compartido: varios cuerpos (o la app) pueden usar el mismo
+    pub position: Vector3, // centro si no orbita
+    pub orbit: Option<Orbit>,
+    pub parent: Option<usize>, // índice en `Scene::bodies` del cuerpo del que cuelga
+    pub scale: f32,
+    pub rotation_y: f32,
+    pub tilt: f32, // rotación en X (inclinación del eje)
+    pub spin: f32, // rad/s alrededor de Y
+    pub pulse: f32, // amplitud de la respiración (fracción del radio; 0 = nada)
+    pub sphere: bool, // la malla aproxima una esfera: admite normales analíticas
+    pub visible: bool,
+    cache: TransformCache,
+    impostor: Option<Impostor>,
+}
+
+/// Lado en píxeles de la imagen de un impostor.
+pub const IMPOSTOR_SIZE: u32 = 64;
+
+/// Cada cuánto (segundos) se vuelve a sombrear un impostor de un shader animado.
+pub const IMPOSTOR_TIME_STEP: f32 = 0.25;
+
+/// Cada cuánto (radianes) de giro o inclinación del cuerpo se vuelve a dibujar su impostor.
+pub const IMPOSTOR_ANGLE_STEP: f32 = 0.035;
+
+/// Imagen ya rasterizada de un cuerpo lejano (ver `render_impostor`). Se rehace sólo si
+/// cambia algo de `ImpostorKey`.
+struct Impostor {
+    pixels: Vec<Color>,
+    radius: f32, // radio de la malla sin escalar
+    key: ImpostorKey,
+}
+
+/// Todo lo que cambia la imagen de un impostor. El tiempo y los ángulos del cuerpo van por
+/// escalones (`IMPOSTOR_TIME_STEP`, `IMPOSTOR_ANGLE_STEP`) para no redibujarlo en cada frame.
+#[derive(Clone, PartialEq)]
+struct ImpostorKey {
+    shader: ShaderId,
+    forward: Vector3, // orientación de la cámara
+    up: Vector3,
+    orientation: [i64; 9], // la rotación del cuerpo en el mundo, por escalones
+    time: i64,
+    seed: u32,
+    uniforms: Uniforms,
+    light_color: Color, // `shader::light_color`, que tiñe la luz propia de los shaders
+}
+
+/// El shader de un cuerpo comparado por identidad (el mismo objeto), no por nombre: dos
+/// shaders distintos pueden llamarse igual.
+#[derive(Clone)]
+struct ShaderId(Arc<dyn PlanetShader>);
+
+impl PartialEq for ShaderId {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(Arc::as_ptr(&self.0), Arc::as_ptr(&other.0))
+    }
+}
+
+impl ImpostorKey {
+    /// `orientation` es la rotación del cuerpo en el mundo (ver `rotation_of`).
+    fn new(body: &Body, orientation: Matrix, camera: &Camera, time: f32, uniforms: Uniforms) -> Self {
+        let step = |x: f32, s: f32| (x / s).floor() as i64;
+        let m = orientation;
+        // Un giro chico mueve cada coeficiente a lo más el ángulo girado
+        let orientation = [m.m0, m.m1, m.m2, m.m4, m.m5, m.m6, m.m8, m.m9, m.m10].map(|c| step(c, IMPOSTOR_ANGLE_STEP));
+        Self {
+            shader: ShaderId(body.shader.clone()),
+            forward: camera.forward(),
+            up: camera.view_up(),
+            orientation,
+            time: step(time, IMPOSTOR_TIME_STEP),
+            seed: shader::noise_seed(),
+            uniforms,
+            light_color: shader::light_color(),
+        }
+    }
+}
+
+impl Body {
+    /// Cuerpo quieto en el origen, escala 1, sin giro ni órbita. Si la malla no trae
+    /// normales (o le faltan en alguna esquina) se calculan (para el sombreado suave).
+    /// `shader` es un `ShaderType` o cualquier `Arc<dyn PlanetShader>`.
+    pub fn new(name: &str, mut mesh: ObjModel, shader: impl Into<Arc<dyn PlanetShader>>) -> Self {
+        if !mesh.has_complete_normals() {
+            mesh.compute_smooth_normals();
+        }
+        Self {
+            name: name.to_string(),
+            mesh,
+            shader: shader.into(),
+            position: Vector3::new(0.0, 0.0, 0.0),
+            orbit: None,
+            parent: None,
+            scale: 1.0,
+            rotation_y: 0.0,
+            tilt: 0.0,
+            spin: 0.0,
+            pulse: 0.0,
+            sphere: false,
+            visible: true,
+            cache: TransformCache::new(),
+            impostor: None,
+        }
+    }
+
+    /// Centro del cuerpo relativo a su padre (sin zoom). Ver `Scene::world_center`.
+    pub fn center(&self) -> Vector3 {
+        self.orbit.map_or(self.position, |o| o.position())
+    }
+}
+
+/// Cámara, luces y cuerpos, más el estado global de la animación.
+pub struct Scene {
+    pub camera: Camera,
+    pub lights: Vec<Light>, // vacío = la luz propia de cada shader (ver `frame_uniforms`)
+    pub bodies: Vec<Body>,
+    pub time: f32,
+    pub zoom: f32,          // escala global de posiciones y tamaños
+    pub auto_rotate: bool,  // `false` congela los giros (las órbitas siguen)
+    pub mode: RenderMode,
+    pub smooth: bool,       // sombreado suave (normales por vértice) en vez de plano
+    pub analytic_normals: bool, // normal exacta de esfera en los cuerpos con `sphere`
+    pub camera_relative: bool, // transformar relativo al ojo (cuerpos lejos del origen)
+    pub dither_strength: f32,  // ruido por fragmento contra el banding (`RasterOptions`)
+    pub impostor_distance: Option<f32>, // más lejos que esto un cuerpo se dibuja como impostor
+    pub system_tilt: f32, // giro en X (rad) del plano de todas las órbitas (0 = el plano XZ)
+    pub uniforms: Option<Uniforms>, // anillos/planetshine/rejilla propios (`None` = los globales)
+}
+
+impl Scene {
+    pub fn new(camera: Camera) -> Self {
+        Self {
+            camera,
+            lights: Vec::new(),
+            bodies: Vec::new(),
+            time: 0.0,
+            zoom: 1.0,
+            auto_rotate: true,
+            mode: RenderMode::Shaded,
+            smooth: false,
+            analytic_normals: false,
+            camera_relative: false,
+            dither_strength: 0.0,
+            impostor_distance: None,
+            system_tilt: 0.0,
+            uniforms: None,
+        }
+    }
+
+    /// Agrega un cuerpo y devuelve su índice.
+    pub fn add_body(&mut self, body: Body) -> usize {
+        self.bodies.push(body);
+        self.bodies.len() - 1
+    }
+
+    /// Avanza `dt` segundos: tiempo de los shaders, órbitas y (si `auto_rotate`) giros.
+    pub fn update(&mut self, dt: f32) {
+        // El tiempo de los shaders avanzaba 0.016 por frame a 60 FPS
+        self.time += 0.96 * dt;
+        for body in &mut self.bodies {
+            if let Some(orbit) = &mut body.orbit {
+                orbit.angle += orbit.speed * dt;
+            }
+            if self.auto_rotate {
+                body.rotation_y += body.spin * dt;
+            }
+        }
+    }
+
+    /// Transformación de un cuerpo en la escena (sin zoom): la local seguida de la de cada
+    /// ancestro. Una cadena de padres con ciclo se corta después de recorrer todos los cuerpos.
+    pub fn world_transform(&self, index: usize) -> Matrix {
+        let mut transform = self.local_transform(index);
+        let mut parent = self.bodies[index].parent;
+        for _ in 0..self.bodies.len() {
+            let Some(p) = parent else { break };
+            transform = transform * self.local_transform(p);
+            parent = self.bodies[p].parent;
+        }
+        transform
+    }
+
+    /// Escala, inclinación, giro y centro de un cuerpo relativos a su padre.
+    fn local_transform(&self, index: usize) -> Matrix {
+        let body = &self.bodies[index];
+        model_matrix(self.local_center(index), body.rotation_y, body.tilt, body.scale)
+    }
+
+    /// Centro de un cuerpo en la escena (sin zoom).
+    pub fn world_center(&self, index: usize) -> Vector3 {
+        translation_of(self.world_transform(index))
+    }
+
+    /// Escala de un cuerpo en la escena (sin zoom): la suya por la de cada ancestro.
+    pub fn world_scale(&self, index: usize) -> f32 {
+        scale_of(self.world_transform(index))
+    }
+
+    /// `Body::center` con las órbitas inclinadas `system_tilt` (las posiciones fijas no se tocan).
+    fn local_center(&self, index: usize) -> Vector3 {
+        let body = &self.bodies[index];
+        match body.orbit {
+            Some(_) if self.system_tilt != 0.0 => {
+                transform_vertex(body.center(), Vector3::zero(), 0.0, self.system_tilt, 1.0)
+            }
+            _ => body.center(),
+        }
+    }
+
+    /// Centro de un cuerpo en el mundo (con zoom).
+    pub fn body_position(&self, index: usize) -> Vector3 {
+        self.world_center(index) * self.zoom
+    }
+
+    /// Esfera (centro, radio) en el mundo que contiene los cuerpos visibles en su posición
+    /// actual, para encuadrarlos con `Camera::frame_sphere`. No cuenta el pulso. `None` si
+    /// no hay cuerpos visibles.
+    pub fn bounding_sphere(&self) -> Option<(Vector3, f32)> {
+        let spheres: Vec<(Vector3, f32)> = (0..self.bodies.len())
+            .filter(|&i| self.bodies[i].visible && !self.bodies[i].mesh.vertices.is_empty())
+            .map(|i| {
+                let body = &self.bodies[i];
+                let (min, max) = body.mesh.bounding_box();
+                let mid = (min + max) * 0.5;
+                let radius = body.mesh.vertices.iter().map(|&v| (v - mid).length()).fold(0.0, f32::max);
+                let world = self.world_transform(i);
+                (mid.transform_with(world) * self.zoom, radius * scale_of(world) * self.zoom)
+            })
+            .collect();
+        let &(first, _) = spheres.first()?;
+
+        // Centro: el de la caja de las esferas; radio: lo que haga falta para cubrirlas todas
+        let (min, max) = spheres.iter().fold((first, first), |(min, max), &(c, r)| {
+            let r = Vector3::new(r, r, r);
+            let (lo, hi) = (c - r, c + r);
+            (
+                Vector3::new(min.x.min(lo.x), min.y.min(lo.y), min.z.min(lo.z)),
+                Vector3::new(max.x.max(hi.x), max.y.max(hi.y), max.z.max(hi.z)),
+            )
+        });
+        let center = (min + max) * 0.5;
+        let radius = spheres.iter().map(|&(c, r)| (c - center).length() + r).fold(0.0, f32::max);
+        Some((center, radius))
+    }
+
+    /// Dibuja los cuerpos visibles desde la cámara de la escena.
+    pub fn render(&mut self, fb: &mut Framebuffer) {
+        let camera = self.camera;
+        self.render_from(fb, &camera, &mut FrameProfiler::new(false));
+    }
+
+    /// `true` si el cuerpo `index` se dibuja como impostor desde `camera`: en modo sombreado
+    /// y con el centro más lejos del ojo que `impostor_distance`.
+    pub fn uses_impostor(&self, index: usize, camera: &Camera) -> bool {
+        let Some(limit) = self.impostor_distance else { return false };
+        matches!(self.mode, RenderMode::Shaded)
+            && (self.world_center(index) * self.zoom - camera.eye()).length() > limit
+    }
+
+    /// Estado de los shaders para este frame: `uniforms` (o los globales) con las luces de
+    /// `lights`, si hay alguna.
+    pub fn frame_uniforms(&self) -> Uniforms {
+        let mut uniforms = self.uniforms.unwrap_or_else(Uniforms::current);
+        if !self.lights.is_empty() {
+            uniforms.lights = Some(Lights::new(&self.lights));
+        }
+        uniforms
+    }
+
+    /// Dibuja los cuerpos visibles desde `camera` (p. ej. un ojo en estéreo), midiendo
+    /// transformación y rasterizado en `profiler`. Con `camera_relative` la posición de cada
+    /// cuerpo se resta del ojo (en f64) antes de transformar sus vértices, así nunca se
+    /// suman/restan coordenadas grandes en f32. Los cuerpos translúcidos
+    /// (`PlanetShader::opacity` < 1) van al final, en el orden en que se agregaron.
+    pub fn render_from(&mut self, fb: &mut Framebuffer, camera: &Camera, profiler: &mut FrameProfiler) {
+        let (zoom, time, mode, smooth) = (self.zoom, self.time, self.mode, self.smooth);
+        let (analytic, relative, dither) = (self.analytic_normals, self.camera_relative, self.dither_strength);
+        let uniforms = self.frame_uniforms();
+        let transforms: Vec<Matrix> = (0..self.bodies.len()).map(|i| self.world_transform(i)).collect();
+        // Opacos primero: los translúcidos se mezclan con lo que ya está dibujado detrás
+        let mut order: Vec<usize> = (0..self.bodies.len()).filter(|&i| self.bodies[i].visible).collect();
+        order.sort_by_key(|&i| self.bodies[i].shader.opacity() < 1.0);
+        let impostors: Vec<bool> = (0..self.bodies.len()).map(|i| self.uses_impostor(i, camera)).collect();
+        for i in order {
+            let (body, to_world) = (&mut self.bodies[i], transforms[i]);
+            let center = translation_of(to_world);
+            // Relativo: la traslación ya es respecto al ojo y a la vista sólo le falta rotar
+            let translation = if relative { camera.relative_to_eye(center, zoom) } else { center * zoom };
+            let scale = scale_of(to_world) * zoom;
+            // Giro y escala en el mundo (con zoom) y después la traslación de arriba
+            let linear = Matrix { m12: 0.0, m13: 0.0, m14: 0.0, ..to_world } * Matrix::scale(zoom, zoom, zoom);
+            let transform = linear * Matrix::translate(translation.x, translation.y, translation.z);
+
+            if impostors[i] {
+                let t = profiler.start();
+                let orientation = rotation_of(to_world);
+                let key = ImpostorKey::new(body, orientation, camera, time, uniforms);
+                if body.impostor.as_ref().is_none_or(|imp| imp.key != key) {
+                    let pixels =
+                        render_impostor(body, orientation, camera, time, Some(key.uniforms)).get_image_data().to_vec();
+                    body.impostor = Some(Impostor { pixels, radius: body.mesh.bounding_radius(), key });
+                }
+                if let Some(imp) = &body.impostor {
+                    let view_center =
+                        if relative { camera.relative_to_view(translation) } else { camera.to_view(translation) };
+                    draw_billboard(fb, &imp.pixels, IMPOSTOR_SIZE, view_center, imp.radius * scale, camera.focal());
+                }
+                profiler.stop(Stage::Raster, t);
+                continue;
+            }
+
+            let t = profiler.start();
+            let pulsed;
+            let world = if body.pulse != 0.0 {
+                // Respiración: el radio oscila alrededor del centro (no pasa por la caché, cambia cada frame)
+                let k = body.pulse * (time * PULSE_SPEED).sin();
+                let vertices = body.mesh.vertices.iter().map(|v| v.transform_with(transform));
+                pulsed = vertices.map(|v| v + (v - translation) * k).collect::<Vec<_>>();
+                &pulsed[..]
+            } else {
+                body.cache.get(&body.mesh, transform)
+            };
+            let view = if relative {
+                Cow::Owned(world.iter().map(|&v| camera.relative_to_view(v)).collect())
+            } else {
+                camera.view_vertices(world)
+            };
+            let normals = smooth.then(|| {
+                let mut n = transform_normals_matrix(&body.mesh.normals, linear);
+                if !camera.is_identity() {
+                    n.iter_mut().for_each(|d| *d = camera.to_view_dir(*d));
+                }
+                n
+            });
+            // Centro en espacio de vista; con pulso el radio cambia pero el centro no
+            let sphere_center = (analytic && body.sphere).then(|| {
+                if relative { camera.relative_to_view(translation) } else { camera.to_view(translation) }
+            });
+            profiler.stop(Stage::Transform, t);
+
+            let t = profiler.start();
+            draw_model(fb, camera, &body.mesh, &view, normals.as_deref(), sphere_center, &*body.shader, time, mode, dither, Some(uniforms));
+            profiler.stop(Stage::Raster, t);
+        }
+    }
+}
+
+/// Rasteriza `body`, girado con `orientation` (su rotación en el mundo, ver
+/// `Scene::world_transform`), en una imagen de `IMPOSTOR_SIZE` x `IMPOSTOR_SIZE` con fondo
+/// transparente, visto con la orientación de `camera` y con la silueta llenando la imagen.
+/// Los shaders dependen de la posición en vista, así que el patrón es aproximado: sirve para
+/// cuerpos que ocupan pocos píxeles (ver `Scene::impostor_distance`). `uniforms` como en
+/// `RasterOptions::uniforms`.
+pub fn render_impostor(body: &Body, orientation: Matrix, camera: &Camera, time: f32, uniforms: Option<Uniforms>) -> Image {
+    // Malla de radio 1 frente al ojo, a la distancia que la encuadra justo con focal 1
+    let view_camera = Camera { position: BASE_EYE, target: BASE_EYE + camera.forward(), fov: DEFAULT_FOV, ..*camera };
+    let unit = 1.0 / body.mesh.bounding_radius().max(1e-6);
+    let offset = Vector3::new(0.0, 0.0, view_camera.framing_distance(1.0, 1.0));
+    let view: Vec<Vector3> = body
+        .mesh
+        .vertices
+        .iter()
+        .map(|&v| view_camera.relative_to_view(v.transform_with(orientation) * unit) + offset)
+        .collect();
+    let mut normals = transform_normals_matrix(&body.mesh.normals, orientation);
+    normals.iter_mut().for_each(|d| *d = view_camera.to_view_dir(*d));
+
+    let mut fb = Framebuffer::new(IMPOSTOR_SIZE, IMPOSTOR_SIZE, Color::new(0, 0, 0, 0));
+    let center = body.sphere.then_some(BASE_EYE + offset);
+    draw_model(&mut fb, &view_camera, &body.mesh, &view, Some(&normals), center, &*body.shader, time, RenderMode::Shaded, 0.0, uniforms);
+    fb.color_buffer
+}
+
+/// Traslación de una transformación (dónde queda el origen del modelo).
+fn translation_of(m: Matrix) -> Vector3 {
+    Vector3::new(m.m12, m.m13, m.m14)
+}
+
+/// Escala (uniforme) de una transformación: el largo de la imagen del eje X.
+fn scale_of(m: Matrix) -> f32 {
+    Vector3::new(m.m0, m.m1, m.m2).length()
+}
+
+/// Sólo la rotación de una transformación, sin escala ni traslación.
+fn rotation_of(m: Matrix) -> Matrix {
+    let s = 1.0 / scale_of(m).max(1e-12);
+    Matrix { m12: 0.0, m13: 0.0, m14: 0.0, ..m } * Matrix::scale(s, s, s)
+}
+
+/// Dibuja la imagen de un impostor (`pixels`, `size` x `size`) como un rectángulo de frente a
+/// la cámara centrado en `center` (espacio de vista) que cubre la silueta de una esfera de
+/// `radius`. Todo el rectángulo usa la profundidad del frente de la esfera; los píxeles
+/// transparentes no se dibujan.
+pub fn draw_billboard(fb: &mut Framebuffer, pixels: &[Color], size: u32, center: Vector3, radius: f32, focal: f32) {
+    let distance = (center - BASE_EYE).length();
+    if distance <= radius || center.z <= BASE_EYE.z || pixels.len() < (size * size) as usize {
+        return;
+    }
+    // Tangente del semiángulo de la silueta, igual que al encuadrar en `render_impostor`
+    let extent = focal * radius / (distance * distance - radius * radius).sqrt();
+    let fov = 1.0 / (center.z - BASE_EYE.z);
+    let (w, h) = (fb.width as f32, fb.height as f32);
+    let (cx, cy) = (w / 2.0 + center.x * focal * fov * w / 2.0, h / 2.0 - center.y * focal * fov * h / 2.0);
+    let (half_w, half_h) = (extent * w / 2.0, extent * h / 2.0);
+
+    let depth = center.z - radius;
+    let min_x = ((cx - half_w).floor() as i32).max(0);
+    let max_x = ((cx + half_w).ceil() as i32).min(fb.width as i32 - 1);
+    let min_y = ((cy - half_h).floor() as i32).max(0);
+    let max_y = ((cy + half_h).ceil() as i32).min(fb.height as i32 - 1);
+    for y in min_y..=max_y {
+        let v = ((y as f32 + 0.5 - (cy - half_h)) / (2.0 * half_h) * size as f32).floor();
+        if v < 0.0 || v >= size as f32 {
+            continue;
+        }
+        for x in min_x..=max_x {
+            let u = ((x as f32 + 0.5 - (cx - half_w)) / (2.0 * half_w) * size as f32).floor();
+            if u < 0.0 || u >= size as f32 {
+                continue;
+            }
+            let color = pixels[(v as u32 * size + u as u32) as usize];
+            if color.a > 0 && depth < fb.depth(x, y) {
+                fb.write(x, y, depth, color);
+            }
+        }
+    }
+}
+
+/// Color de las aristas en modo relleno + aristas (en alambre/puntos se usa `WIRE_COLOR`).
+pub const EDGE_COLOR: Color = Color::new(20, 20, 30, 255);
+pub const WIRE_COLOR: Color = Color::new(120, 220, 255, 255);
+
+/// Dibuja todas las caras (en abanico) de un modelo ya transformado según el modo de render.
+/// Con `normals = Some(pool)` (el pool `model.normals` ya rotado) los modos rellenos usan
+/// sombreado suave. Alambre y puntos sólo usan las caras frontales (en puntos, los vértices
+/// de alguna cara frontal) y escriben profundidad, así que la cara de atrás no se cuela por
+/// delante. De `camera` sólo se usan `near`/`far`/`fov` (los vértices ya vienen en espacio de
+/// vista); las aristas se recortan igual que los rellenos y en puntos se descarta lo que
+/// quede fuera de ese rango. Con `sphere_center` los modos rellenos usan la normal analítica
+/// de la esfera (ver `RasterOptions::sphere_center`). `dither` es
+/// `RasterOptions::dither_strength` y `uniforms`, `RasterOptions::uniforms`.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_model(
+    fb: &mut Framebuffer,
+    camera: &Camera,
+    model: &ObjModel,
+    transformed: &[Vector3],
+    normals: Option<&[Vector3]>,
+    sphere_center: Option<Vector3>,
+    shader: &dyn PlanetShader,
+    time: f32,
+    mode: RenderMode,
+    dither: f32,
+    uniforms: Option<Uniforms>,
+) {
+    // Triángulos de menos de medio píxel: un solo fragmento (cuerpos lejanos/alejados con zoom)
+    let options = RasterOptions {
+        area_cull_threshold: 0.5,
+        splat_culled: true,
+        debug_view: mode.debug_view(),
+        smooth: normals.is_some(),
+        near: camera.near,
+        far: camera.far,
+        focal: camera.focal(),
+        sphere_center,
+        dither_strength: dither,
+        uniforms,
+        ..RasterOptions::default()
+    };
+
+    if mode == RenderMode::Points {
+        // Sólo los vértices de alguna cara frontal (mismo abanico que los rellenos)
+        let mut visible = vec![false; transformed.len()];
+        for face in model.faces.iter().filter(|f| f.len() >= 3) {
+            for i in 1..(face.len() - 1) {
+                let corners = [face[0], face[i], face[i + 1]];
+                if triangle::is_front_facing(transformed[corners[0]], transformed[corners[1]], transformed[corners[2]]) {
+                    corners.iter().for_each(|&c| visible[c] = true);
+                }
+            }
+        }
+        fb.set_current_color(WIRE_COLOR);
+        let front = transformed.iter().zip(&visible).filter(|&(_, &seen)| seen).map(|(v, _)| v);
+        for &v in front.filter(|&&v| camera.in_depth_range(v)) {
+            let p = camera.project(v, fb.width, fb.height);
+            fb.set_pixel_depth(p.x as i32, p.y as i32, v.z);
+        }
+        return;
+    }
+
+    // Con `parallel` los modos rellenos (sin aristas) se encolan y se rasterizan por franjas
+    // en paralelo al final; si no, cada triángulo se dibuja en el momento
+    let mut tiled = (cfg!(feature = "parallel") && !matches!(mode, RenderMode::WireOnShaded | RenderMode::Wireframe))
+        .then(|| TiledRasterizer::new(fb.width, fb.height, DEFAULT_TILE_SIZE));
+
+    for (fi, face) in model.faces.iter().enumerate() {
+        if face.len() < 3 { continue; }
+        // Normales de las esquinas de esta cara, si hay sombreado suave
+        let corners = normals.zip(model.face_normals.get(fi));
+        for i in 1..(face.len() - 1) {
+            let v0 = transformed[face[0]];
+            let v1 = transformed[face[i]];
+            let v2 = transformed[face[i + 1]];
+            match (mode, corners) {
+                (RenderMode::Shaded | RenderMode::Normals | RenderMode::Depth | RenderMode::Clay, Some((pool, idx))) => {
+                    let n = [pool[idx[0]], pool[idx[i]], pool[idx[i + 1]]];
+                    match &mut tiled {
+                        Some(t) => t.submit_smooth(fb, [v0, v1, v2], n, shader, time, &options),
+                        None => triangle::draw_smooth_triangle(fb, v0, v1, v2, n[0], n[1], n[2], shader, time, &options),
+                    }
+                }
+                (RenderMode::WireOnShaded, _) => triangle::draw_wire_on_shaded_triangle(fb, v0, v1, v2, shader, time, &options, EDGE_COLOR),
+                (RenderMode::Wireframe, _) => triangle::draw_wire_triangle(fb, v0, v1, v2, &options, WIRE_COLOR),
+                _ => match &mut tiled {
+                    Some(t) => t.submit(fb, v0, v1, v2, shader, time, &options),
+                    None => triangle::draw_filled_view_triangle(fb, v0, v1, v2, shader, time, &options),
+                },
+            }
+        }
+    }
+
+    if let Some(t) = &mut tiled {
+        t.flush(fb);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::look_direction;
+    use crate::geometria::generate_icosphere;
+    use crate::shader::lock_uniforms;
+    use crate::triangle::ShaderType;
+
+    /// Escena con una esfera de radio 1 en el origen, vista con la cámara fija.
+    fn sphere_scene(shader: ShaderType) -> Scene {
+        let mut scene = Scene::new(Camera::default());
+        let mut body = Body::new("esfera", generate_icosphere(1.0, 2), shader);
+        body.sphere = true;
+        scene.add_body(body);
+        scene
+    }
+
+    fn render(scene: &mut Scene) -> Vec<Color> {
+        let mut fb = Framebuffer::new(64, 64, Color::BLACK);
+        scene.render(&mut fb);
+        fb.pixels()
+    }
+
+    #[test]
+    fn minimal_scene_renders_one_body() {
+        let _uniforms = lock_uniforms();
+        let mut scene = sphere_scene(ShaderType::Rocky);
+        scene.bodies[0].scale = 0.5;
+        scene.bodies[0].spin = 1.0;
+        let image = render(&mut scene);
+        // El cuerpo ocupa el centro y no llega a las esquinas
+        assert_ne!(image[32 * 64 + 32], Color::BLACK);
+        for i in [0, 63, 63 * 64, 64 * 64 - 1] {
+            assert_eq!(image[i], Color::BLACK);
+        }
+
+        scene.update(0.5);
+        assert!(scene.time > 0.0);
+        assert_eq!(scene.bodies[0].rotation_y, 0.5);
+        scene.bodies[0].visible = false;
+        assert!(render(&mut scene).iter().all(|&c| c == Color::BLACK));
+    }
+
+    #[test]
+    fn child_position_composes_two_levels() {
+        use std::f32::consts::FRAC_PI_2;
+        let mut scene = Scene::new(Camera::default());
+        let body = |name| Body::new(name, generate_icosphere(0.1, 0), ShaderType::Rocky);
+        let mut star = body("estrella");
+        star.position = Vector3::new(10.0, 0.0, 0.0);
+        star.scale = 2.0;
+        let star = scene.add_body(star);
+        let mut planet = body("planeta");
+        planet.orbit = Some(Orbit::new(3.0, 0.0, 1.0));
+        planet.rotation_y = FRAC_PI_2;
+        planet.parent = Some(star);
+        let planet = scene.add_body(planet);
+        let mut moon = body("luna");
+        moon.orbit = Some(Orbit::new(1.0, 0.5, 2.0));
+        moon.parent = Some(planet);
+        let moon = scene.add_body(moon);
+
+        // La escala de la estrella agranda las dos órbitas y el giro del planeta lleva la
+        // luna de +X a -Z
+        let close = |a: Vector3, b: Vector3| (a - b).length() < 1e-5;
+        assert!(close(scene.world_center(planet), Vector3::new(16.0, 0.0, 0.0)));
+        assert!(close(scene.world_center(moon), Vector3::new(16.0, 1.0, -2.0)));
+        assert!((scene.world_scale(moon) - 2.0).abs() < 1e-6);
+
+        // Un cuarto de vuelta del planeta (π/2 a 1 rad/s) y media de la luna (2 rad/s)
+        scene.update(FRAC_PI_2);
+        assert!(close(scene.world_center(planet), Vector3::new(10.0, 0.0, 6.0)));
+        assert!(close(scene.world_center(moon), Vector3::new(10.0, 1.0, 8.0)));
+        scene.zoom = 2.0;
+        assert!(close(scene.body_position(moon), Vector3::new(20.0, 2.0, 16.0)));
+    }
+
+    #[test]
+    fn mode_cycle_wraps_and_each_mode_draws_differently() {
+        let _uniforms = lock_uniforms();
+        assert_eq!(RenderMode::Points.next(), RenderMode::Shaded);
+        let mut mode = RenderMode::Shaded;
+        let mut seen = Vec::new();
+        for _ in 0..RenderMode::ALL.len() {
+            assert!(!seen.contains(&mode));
+            seen.push(mode);
+            mode = mode.next();
+        }
+        assert_eq!(mode, RenderMode::Shaded);
+
+        let mut scene = sphere_scene(ShaderType::Rocky);
+        let images: Vec<Vec<Color>> = RenderMode::ALL
+            .iter()
+            .map(|&mode| {
+                scene.mode = mode;
+                render(&mut scene)
+            })
+            .collect();
+        for (i, a) in images.iter().enumerate() {
+            assert!(a.iter().any(|&c| c != Color::BLACK), "{:?} no dibujó nada", RenderMode::ALL[i]);
+            for (j, b) in images.iter().enumerate().skip(i + 1) {
+                assert_ne!(a, b, "{:?} y {:?} dibujan lo mismo", RenderMode::ALL[i], RenderMode::ALL[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn points_mode_skips_the_back_hemisphere() {
+        let _uniforms = lock_uniforms();
+        let mut scene = sphere_scene(ShaderType::Rocky);
+        scene.mode = RenderMode::Points;
+        let mut fb = Framebuffer::new(128, 128, Color::BLACK);
+        scene.render(&mut fb);
+        // Esfera unitaria vista desde z = -3: la silueta está en z = -1/3; los vértices de las
+        // caras frontales no pasan mucho de ahí, los de atrás llegarían hasta z = 1
+        let depths: Vec<f32> = fb.z_buffer.iter().copied().filter(|z| z.is_finite()).collect();
+        assert!(depths.len() > 20, "{} puntos", depths.len());
+        assert!(depths.iter().all(|&z| z < 0.5), "{:?}", depths.iter().copied().fold(f32::MIN, f32::max));
+    }
+
+    #[test]
+    fn body_beyond_the_threshold_is_drawn_as_a_billboard() {
+        let _uniforms = lock_uniforms();
+        let mut scene = sphere_scene(ShaderType::Rocky);
+        scene.impostor_distance = Some(10.0);
+        let camera = Camera::default();
+        // Ojo en z = -3: a 8 del ojo geometría, a 13 impostor
+        scene.bodies[0].position = Vector3::new(0.0, 0.0, 5.0);
+        assert!(!scene.uses_impostor(0, &camera));
+        render(&mut scene);
+        assert!(scene.bodies[0].impostor.is_none());
+
+        scene.bodies[0].position = Vector3::new(0.0, 0.0, 10.0);
+        assert!(scene.uses_impostor(0, &camera));
+        let image = render(&mut scene);
+        assert!(scene.bodies[0].impostor.is_some());
+        assert_ne!(image[32 * 64 + 32], Color::BLACK);
+        // El alambre siempre dibuja la malla
+        scene.mode = RenderMode::Wireframe;
+        assert!(!scene.uses_impostor(0, &camera));
+
+        // Girar el cuerpo, cambiar la luz o avanzar el tiempo lo vuelve a sombrear
+        let key = |scene: &Scene| scene.bodies[0].impostor.as_ref().map(|imp| imp.key.clone());
+        scene.mode = RenderMode::Shaded;
+        let first = key(&scene);
+        render(&mut scene);
+        assert!(key(&scene) == first);
+        scene.bodies[0].rotation_y += 0.5;
+        render(&mut scene);
+        let rotated = key(&scene);
+        assert!(rotated != first);
+        scene.lights = vec![Light::directional(Vector3::new(-1.0, 0.0, 0.0))];
+        render(&mut scene);
+        assert!(key(&scene) != rotated);
+        let lit = key(&scene);
+        scene.time += IMPOSTOR_TIME_STEP;
+        render(&mut scene);
+        assert!(key(&scene) != lit);
+    }
+
+    /// Shader propio de un solo color (no `'static`: se crea en la prueba).
+    struct Solid(Color);
+
+    impl PlanetShader for Solid {
+        fn shade(&self, _pos: &Vector3, _normal: &Vector3, _time: f32) -> Color {
+            self.0
+        }
+        fn name(&self) -> &str {
+            "Rocky"
+        }
+    }
+
+    #[test]
+    fn impostor_is_redrawn_for_another_shader_with_the_same_name() {
+        let _uniforms = lock_uniforms();
+        let mut scene = Scene::new(Camera::default());
+        let mut body = Body::new("esfera", generate_icosphere(1.0, 2), Arc::new(Solid(Color::RED)) as Arc<dyn PlanetShader>);
+        body.position = Vector3::new(0.0, 0.0, 10.0);
+        scene.add_body(body);
+        scene.impostor_distance = Some(10.0);
+        assert_eq!(render(&mut scene)[32 * 64 + 32], Color::RED);
+
+        // Mismo nombre que el de antes (y que `ShaderType::Rocky`), pero es otro shader
+        scene.bodies[0].shader = Arc::new(Solid(Color::BLUE));
+        assert_eq!(render(&mut scene)[32 * 64 + 32], Color::BLUE);
+    }
+
+    #[test]
+    fn scene_lights_reach_the_shaders() {
+        let _uniforms = lock_uniforms();
+        let mut scene = sphere_scene(ShaderType::Rocky);
+        let unlit = render(&mut scene);
+        let luma = |image: &[Color], x0: usize| -> u32 {
+            (28..36).flat_map(|y| (x0..x0 + 6).map(move |x| y * 64 + x)).map(|i| {
+                let c = image[i];
+                c.r as u32 + c.g as u32 + c.b as u32
+            }).sum()
+        };
+        // La esfera ocupa x ≈ 21..43. Luz desde la derecha (+X en vista) y después desde la izquierda
+        scene.lights = vec![Light::directional(Vector3::new(1.0, 0.0, -0.3))];
+        let right = render(&mut scene);
+        scene.lights = vec![Light::directional(Vector3::new(-1.0, 0.0, -0.3))];
+        let left = render(&mut scene);
+        assert_ne!(right, unlit);
+        assert!(luma(&right, 35) > luma(&right, 23), "luz a la derecha");
+        assert!(luma(&left, 23) > luma(&left, 35), "luz a la izquierda");
+        // Sin luces vuelve la luz propia del shader
+        scene.lights.clear();
+        assert_eq!(render(&mut scene), unlit);
+    }
+
+    #[test]
+    fn framed_offset_scaled_sphere_fills_the_requested_fraction() {
+        let _uniforms = lock_uniforms();
+        let mut scene = sphere_scene(ShaderType::Rocky);
+        scene.mode = RenderMode::Clay;
+        scene.bodies[0].position = Vector3::new(4.0, -2.5, 7.0);
+        scene.bodies[0].scale = 2.5;
+        scene.camera = Camera { target: BASE_EYE + look_direction(0.4, 0.2), ..Camera::default() };
+        let (center, radius) = scene.bounding_sphere().expect("hay un cuerpo visible");
+        assert!((radius - 2.5).abs() < 0.01, "{radius}");
+
+        let size = 128;
+        for fill in [0.3, 0.6] {
+            scene.camera.frame_sphere(center, radius, fill);
+            let mut fb = Framebuffer::new(size, size, Color::BLACK);
+            scene.render(&mut fb);
+            let pixels = fb.pixels();
+            let (mut min, mut max) = ((size, size), (0, 0));
+            for y in 0..size {
+                for x in 0..size {
+                    if pixels[(y * size + x) as usize] != Color::BLACK {
+                        min = (min.0.min(x), min.1.min(y));
+                        max = (max.0.max(x), max.1.max(y));
+                    }
+                }
+            }
+            // Silueta centrada y de `fill` de ancho y de alto (a un par de píxeles)
+            let extent = |lo: u32, hi: u32| (hi - lo + 1) as f32 / size as f32;
+            for (lo, hi) in [(min.0, max.0), (min.1, max.1)] {
+                assert!((extent(lo, hi) - fill).abs() < 0.03, "fill {fill}: {}", extent(lo, hi));
+                assert!(((lo + hi) as f32 / 2.0 - size as f32 / 2.0).abs() < 2.0, "fill {fill}: {lo}..{hi}");
+            }
+        }
+    }
+
+    #[test]
+    fn system_tilt_turns_the_orbit_plane_toward_the_camera() {
+        let mut scene = sphere_scene(ShaderType::Rocky);
+        scene.camera.position = Vector3::new(0.0, 0.0, -7.0);
+        scene.bodies[0].orbit = Some(Orbit::new(1.5, 0.0, 0.0));
+        // Alto y ancho (en píxeles) de la órbita proyectada, muestreada en una vuelta completa
+        let spread = |scene: &mut Scene| {
+            let (mut lo, mut hi) = (Vector2::new(f32::MAX, f32::MAX), Vector2::new(f32::MIN, f32::MIN));
+            for k in 0..64 {
+                scene.bodies[0].orbit.as_mut().unwrap().angle = k as f32 * std::f32::consts::TAU / 64.0;
+                let p = scene.camera.project(scene.camera.to_view(scene.world_center(0)), 256, 256);
+                lo = Vector2::new(lo.x.min(p.x), lo.y.min(p.y));
+                hi = Vector2::new(hi.x.max(p.x), hi.y.max(p.y));
+            }
+            hi - lo
+        };
+
+        // De canto: una línea horizontal
+        let flat = spread(&mut scene);
+        assert!(flat.x > 50.0 && flat.y < 0.5, "{flat:?}");
+        // Girada un cuarto de vuelta la órbita mira a la cámara: casi un círculo
+        scene.system_tilt = std::f32::consts::FRAC_PI_2;
+        let facing = spread(&mut scene);
+        assert!((facing.y / facing.x - 1.0).abs() < 0.2, "{facing:?}");
+    }
+
+    #[test]
+    fn smooth_normals_change_the_normals_view() {
+        let _uniforms = lock_uniforms();
+        let mut scene = Scene::new(Camera::default());
+        scene.add_body(Body::new("esfera", generate_icosphere(1.0, 1), ShaderType::Rocky));
+        scene.mode = RenderMode::Normals;
+        let flat = render(&mut scene);
+        scene.smooth = true;
+        let smooth = render(&mut scene);
+
+        // Misma silueta; adentro, las normales interpoladas no son las de cada cara
+        let covered = |image: &[Color]| image.iter().map(|&c| c != Color::BLACK).collect::<Vec<bool>>();
+        assert_eq!(covered(&flat), covered(&smooth));
+        let differing = flat.iter().zip(&smooth).filter(|(f, s)| f != s).count();
+        assert!(differing > 100, "{differing} píxeles distintos");
+        // Plano: pocos colores (uno por cara visible); suave: un degradado
+        let distinct = |image: &[Color]| {
+            let mut colors: Vec<(u8, u8, u8)> = image.iter().map(|c| (c.r, c.g, c.b)).collect();
+            colors.sort_unstable();
+            colors.dedup();
+            colors.len()
+        };
+        assert!(distinct(&smooth) > 2 * distinct(&flat), "{} vs {}", distinct(&flat), distinct(&smooth));
+    }
+}
@@ -1,4 +1,9 @@
 use raylib::prelude::*;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::RwLock;
+use crate::camera::BASE_EYE;
+use crate::palette::{generate_palette, ColorRamp, Harmony};
+use crate::skybox::Starfield;
 
 /// Shaders "CPU-style" para planetas con mayor complejidad:
 /// - Soporta hasta 4 capas de color por planeta (gradientes, bandas, nubes, brillo emissive)
@@ -40,32 +45,90 @@ fn ring_mask(pos: &Vector3, inner: f32, outer: f32, tilt: f32) -> f32 {
 }
 
 // ---------- EFECTO ATMOSFÉRICO GENERAL ----------
+/// Espesor relativo de atmósfera que atraviesa el rayo de vista en `pos`: 0 donde la
+/// superficie mira de frente al ojo (centro del disco), 1 donde el rayo la roza (limbo).
+/// Sólo depende del ángulo entre la normal y la dirección al ojo, no del tamaño de la malla.
+pub fn atmosphere_thickness(pos: &Vector3, normal: &Vector3) -> f32 {
+    let to_eye = BASE_EYE - *pos;
+    if to_eye.length() < 1e-6 || normal.length() < 1e-6 {
+        return 0.0;
+    }
+    let cos = to_eye.normalized().dot(normal.normalized()).clamp(0.0, 1.0);
+    1.0 - cos
+}
+
 fn apply_atmosphere(color: Color, pos: &Vector3, normal: &Vector3, time: f32) -> Color {
     let view_dir = Vector3::new(0.0, 0.0, 1.0);
     let rim = fresnel(normal, view_dir, 2.5);
-    let altitude = (1.0 - pos.length()).clamp(0.0, 1.0);
-    let haze = (rim * 0.6 + altitude * 0.4).powf(1.5);
+    let thickness = atmosphere_thickness(pos, normal);
+    let haze = (rim * 0.6 + thickness * 0.4).powf(1.5);
     let haze_color = Color::new(180, 210, 255, 255);
     blend_colors(color, haze_color, haze * 0.15 + (time * 0.1).sin().abs() * 0.05)
 }
 
 // ---------- PLANETA ROCOSO DETALLADO (AHORA 4 CAPAS + LAVA) ----------
+/// Parámetros del planeta rocoso. `Default` reproduce el look original.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RocaParams {
+    /// Deriva continental: cuánto se corre por segundo el dominio del relieve (0 = continentes
+    /// quietos). A velocidad normal no se nota; en una captura acelerada las masas migran.
+    pub drift_speed: f32,
+}
+
+impl Default for RocaParams {
+    fn default() -> Self {
+        Self { drift_speed: 0.0 }
+    }
+}
+
+/// Dirección (en x, z) en la que derivan los continentes.
+const CONTINENT_DRIFT_DIR: Vector2 = Vector2::new(0.8, 0.6);
+
+// Velocidad de deriva para `roca()` (se fija desde la demo).
+static CONTINENT_DRIFT: RwLock<f32> = RwLock::new(0.0);
+
+/// Fija la velocidad de deriva continental que usa `roca()` (0 = sin deriva).
+pub fn set_continent_drift(drift_speed: f32) {
+    if let Ok(mut current) = CONTINENT_DRIFT.write() {
+        *current = drift_speed;
+    }
+}
+
+/// Desplazamiento del dominio del relieve en `time`: sólo depende del tiempo, así que una
+/// captura con los mismos tiempos da siempre los mismos continentes.
+pub fn continent_drift(time: f32, drift_speed: f32) -> Vector2 {
+    let t = time * drift_speed;
+    Vector2::new(CONTINENT_DRIFT_DIR.x * t, CONTINENT_DRIFT_DIR.y * t)
+}
+
+/// Relieve (0..1) del planeta rocoso en (x, y, z): las zonas altas son los "continentes".
+/// El tiempo sólo entra por la deriva: sin ella los continentes quedan quietos.
+pub fn roca_relief(x: f32, y: f32, z: f32, time: f32, params: &RocaParams) -> f32 {
+    let drift = continent_drift(time, params.drift_speed);
+    fbm_noise3((x + drift.x) * 8.0, y * 8.0, (z + drift.y) * 8.0, 5)
+}
+
 pub fn roca(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
+    let drift_speed = CONTINENT_DRIFT.read().map_or(0.0, |d| *d);
+    roca_with(pos, normal, time, &RocaParams { drift_speed }, &Uniforms::current())
+}
+
+pub fn roca_with(pos: &Vector3, normal: &Vector3, time: f32, params: &RocaParams, uniforms: &Uniforms) -> Color {
     // Capa base: latitud + gradiente
     let latitude = (pos.y).clamp(-1.0, 1.0) * 0.5 + 0.5;
     let base_col = lerp_color(Color::new(40, 30, 25, 255), Color::new(210, 170, 120, 255), latitude);
 
     // Generamos 4 capas con pesos dinámicos:
-    let relief = fbm_noise(pos.x * 8.0, pos.z * 8.0 + time * 0.02, 5);
-    let veins = fbm_noise(pos.x * 24.0, pos.z * 24.0, 4).powf(1.2);
-    let rust = fbm_noise(pos.x * 10.0, pos.z * 10.0, 3).powf(2.8);
+    let relief = roca_relief(pos.x, pos.y, pos.z, time, params);
+    let veins = fbm_noise3(pos.x * 24.0, pos.y * 24.0, pos.z * 24.0, 4).powf(1.2);
+    let rust = fbm_noise3(pos.x * 10.0, pos.y * 10.0, pos.z * 10.0, 3).powf(2.8);
     let moss = smoothstep(0.3, 0.8, relief) * (1.0 - latitude);
 
     let layer0 = (lerp_color(Color::new(90, 60, 50, 255), Color::new(240, 210, 180, 255), relief.powf(1.6)), 0.5); // rocas claras/obscuras
     let layer1 = (blend_colors(Color::new(255, 230, 200, 255), Color::new(190, 80, 40, 255), veins), 0.25); // vetas / óxidos
     let layer2 = (Color::new(60, 100, 70, 255), moss * 0.8); // musgo húmedo
     // capa 3: salpicaduras de material fundido (lava superficial)
-    let lava_noise = fbm_noise(pos.x * 6.0, pos.z * 6.0 + time * 0.12, 4);
+    let lava_noise = fbm_noise3(pos.x * 6.0, pos.y * 6.0, pos.z * 6.0 + time * 0.12, 4);
     let lava_mask = ridge(lava_noise).powf(2.0) * (1.0 - latitude).max(0.0);
     let lava_color = Color::new(255, 120, 40, 255);
     let layer3 = (lava_color, lava_mask * 0.8);
@@ -73,7 +136,7 @@ pub fn roca(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
     let mut col = blend_layered(base_col, &[layer0, layer1, layer2, layer3]);
 
     // aplicar pequeñas grietas y brillo ecuatorial
-    let cracks = fbm_noise(pos.x * 30.0, pos.z * 30.0, 4).powf(1.8);
+    let cracks = fbm_noise3(pos.x * 30.0, pos.y * 30.0, pos.z * 30.0, 4).powf(1.8);
     col = blend_colors(col, Color::new(30, 20, 18, 255), cracks * 0.25);
 
     // Emissive por lava: usar lava_mask para sumarlo
@@ -82,14 +145,257 @@ pub fn roca(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
 
     // Normal perturb y shading
     let pert = perturb_normal(normal, pos, 1.0);
-    let light_dir = Vector3::new(0.6, 0.8, 0.5).normalized();
-    let shaded = shading(col, &pert, light_dir, 64.0, 0.5);
+    let shaded = with_lights(DEFAULT_LIGHT_DIR, uniforms, |lights| shading(col, &pert, lights, 64.0, 0.5));
 
     apply_atmosphere(shaded, pos, normal, time)
 }
 
 // ---------- PLANETA GASEOSO DETALLADO (BANDAS + ANILLO) ----------
+/// Parámetros de las bandas del gigante gaseoso. `Default` reproduce el look original.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GasParams {
+    /// Frecuencia de las bandas primarias a lo largo de Y (las secundarias usan 0.6x).
+    pub band_count: f32,
+    /// Multiplica los exponentes de las bandas: >1 bordes más marcados, <1 más difusos.
+    pub band_sharpness: f32,
+    /// 0 = bandas parejas; hacia 1 las bandas se ensanchan en el ecuador y se afinan en los polos.
+    pub equator_widening: f32,
+    /// Cuánto desplaza el campo de curl noise a las nubes (0 = nubes sin remolinos).
+    pub flow_strength: f32,
+    /// Anillos que proyectan sombra sobre el planeta (`None` = sin sombra).
+    pub ring_shadow: Option<RingShadow>,
+    /// Lados de la tormenta polar poligonal (6 = hexágono de Saturno; menos de 3 = sin tormenta).
+    pub polar_vortex_sides: u32,
+}
+
+impl Default for GasParams {
+    fn default() -> Self {
+        Self { band_count: 10.0, band_sharpness: 1.0, equator_widening: 0.0, flow_strength: 0.35, ring_shadow: None, polar_vortex_sides: 6 }
+    }
+}
+
+/// Anillo plano (corona entre `inner` y `outer` alrededor de `center`) en el mismo espacio
+/// que las posiciones que recibe el shader.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RingShadow {
+    pub center: Vector3,
+    pub normal: Vector3,
+    pub inner: f32,
+    pub outer: f32,
+}
+
+/// Distancia al eje (en la esfera unitaria) del borde de la tormenta polar, y dónde termina
+/// de desvanecerse.
+const POLAR_VORTEX_RADIUS: f32 = 0.28;
+const POLAR_VORTEX_FADE: f32 = 0.45;
+
+/// Intensidad (0..1) de la tormenta polar de `sides` lados en la dirección `dir` (normalizada).
+/// En coordenadas polares alrededor del eje Y: el borde del polígono regular es
+/// ρ(θ) = R·cos(π/N) / cos((θ mod 2π/N) - π/N), así que el patrón tiene simetría de orden N.
+pub fn polar_vortex(dir: &Vector3, sides: u32) -> f32 {
+    if sides < 3 {
+        return 0.0;
+    }
+    let rho = (dir.x * dir.x + dir.z * dir.z).sqrt(); // 0 en el polo
+    if rho >= POLAR_VORTEX_FADE {
+        return 0.0;
+    }
+    let sector = std::f32::consts::TAU / sides as f32;
+    let theta = dir.z.atan2(dir.x).rem_euclid(sector) - sector * 0.5;
+    let edge = POLAR_VORTEX_RADIUS * (sector * 0.5).cos() / theta.cos();
+
+    // Pared del vórtice (banda fina en el borde) + interior más tenue
+    let wall = 1.0 - smoothstep(0.0, 0.03, (rho - edge).abs());
+    let inside = 1.0 - smoothstep(edge - 0.02, edge, rho);
+    let fade = 1.0 - smoothstep(POLAR_VORTEX_RADIUS, POLAR_VORTEX_FADE, rho);
+    (wall * 0.8 + inside * 0.35).min(1.0) * fade
+}
+
+/// Luz del gigante gaseoso (la misma que usa `shading` en `gas_with`).
+const GAS_LIGHT_DIR: Vector3 = Vector3::new(0.4, 0.8, 0.9);
+
+/// Factor de intensidad de cada luz cuyo rayo atraviesa los anillos (solo la luz directa).
+const RING_SHADOW_DARKEN: f32 = 0.45;
+
+/// `true` si el rayo desde `pos` hacia la luz (`light_dir`, no hace falta normalizar) cruza el
+/// plano del anillo dentro de la corona, o sea, si `pos` queda en la sombra de los anillos.
+pub fn ring_shadow_hit(pos: &Vector3, light_dir: Vector3, ring: &RingShadow) -> bool {
+    let denom = light_dir.dot(ring.normal);
+    if denom.abs() < 1e-6 {
+        return false; // luz paralela al plano
+    }
+    let t = (ring.center - *pos).dot(ring.normal) / denom;
+    if t <= 0.0 {
+        return false; // el plano queda detrás (del lado opuesto a la luz)
+    }
+    let r = (*pos + light_dir * t - ring.center).length();
+    r >= ring.inner && r <= ring.outer
+}
+
+// Anillos activos para `gas()` (se fija cada frame desde la escena).
+static RING_SHADOW: RwLock<Option<RingShadow>> = RwLock::new(None);
+
+/// Fija (o quita, con `None`) los anillos que sombrean al planeta gaseoso en `gas()`.
+pub fn set_ring_shadow(ring: Option<RingShadow>) {
+    if let Ok(mut current) = RING_SHADOW.write() {
+        *current = ring;
+    }
+}
+
+/// Luz que el lado iluminado de un planeta refleja sobre los cuerpos cercanos ("planetshine",
+/// como la luz cenicienta de la Luna), en el mismo espacio que las posiciones del shader.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PlanetShine {
+    pub center: Vector3,
+    pub radius: f32,
+    pub albedo: Color, // color promedio del planeta iluminado
+    pub light_dir: Vector3, // hacia el sol, normalizada
+}
+
+/// Intensidad del planetshine sobre una cara que mira de frente a un planeta iluminado
+/// del todo y tangente a él.
+pub const PLANET_SHINE_STRENGTH: f32 = 0.35;
+
+// Planeta que ilumina a sus vecinos (se fija cada frame desde la escena).
+static PLANET_SHINE: RwLock<Option<PlanetShine>> = RwLock::new(None);
+
+/// Fija (o quita, con `None`) el planeta cuyo reflejo ilumina a los demás cuerpos.
+pub fn set_planet_shine(shine: Option<PlanetShine>) {
+    if let Ok(mut current) = PLANET_SHINE.write() {
+        *current = shine;
+    }
+}
+
+/// Cuánto planetshine (0..`PLANET_SHINE_STRENGTH`) recibe el punto `pos` con normal `normal`:
+/// lambert hacia el centro del planeta, por la fracción iluminada del hemisferio que lo mira
+/// y por el ángulo sólido aproximado (radio / distancia)². La superficie del propio planeta
+/// mira hacia afuera, así que no recibe nada.
+pub fn planet_shine_amount(pos: &Vector3, normal: &Vector3, shine: &PlanetShine) -> f32 {
+    let to_planet = shine.center - *pos;
+    let distance = to_planet.length();
+    if distance <= shine.radius {
+        return 0.0;
+    }
+    let dir = to_planet / distance;
+    let facing = normal.dot(dir).max(0.0);
+    let lit_fraction = 0.5 * (1.0 - dir.dot(shine.light_dir));
+    let solid_angle = (shine.radius / distance).powi(2);
+    PLANET_SHINE_STRENGTH * facing * lit_fraction * solid_angle
+}
+
+/// Suma a `color` el planetshine `shine` (el de `Uniforms`), teñido con su albedo.
+pub fn apply_planet_shine(color: Color, pos: &Vector3, normal: &Vector3, shine: Option<&PlanetShine>) -> Color {
+    let Some(shine) = shine else { return color };
+    let amount = planet_shine_amount(pos, normal, shine);
+    if amount <= 0.0 {
+        return color;
+    }
+    let add = |c: u8, a: u8| (c as f32 + a as f32 * amount).clamp(0.0, 255.0) as u8;
+    Color::new(add(color.r, shine.albedo.r), add(color.g, shine.albedo.g), add(color.b, shine.albedo.b), color.a)
+}
+
+/// Rejilla de latitud/longitud sobre la superficie de un planeta (look de "globo"), en el
+/// mismo espacio que las posiciones del shader. Son bandas finas calculadas por fragmento,
+/// sin geometría extra; `axes` gira con el planeta, así que la rejilla muestra su rotación.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GlobeGrid {
+    pub center: Vector3,
+    pub radius: f32,
+    pub axes: [Vector3; 3], // ejes x, y (hacia el polo norte) y z del planeta, normalizados
+    pub spacing: f32,       // grados entre líneas
+    pub width: f32,         // grosor de las líneas, en grados de arco
+    pub color: Color,
+}
+
+/// Sólo los fragmentos a menos de este múltiplo del radio son del planeta (no la luna ni
+/// los anillos).
+const GLOBE_GRID_REACH: f32 = 1.05;
+
+// Rejilla del planeta (se fija cada frame desde la escena).
+static GLOBE_GRID: RwLock<Option<GlobeGrid>> = RwLock::new(None);
+
+/// Fija (o quita, con `None`) la rejilla de latitud/longitud del planeta.
+pub fn set_globe_grid(grid: Option<GlobeGrid>) {
+    if let Ok(mut current) = GLOBE_GRID.write() {
+        *current = grid;
+    }
+}
+
+/// `true` si `pos` cae sobre una línea de `grid`. La distancia a un meridiano se mide en
+/// arco (Δlongitud · cos(latitud)), así las líneas no engordan hacia los polos.
+pub fn on_globe_grid(pos: &Vector3, grid: &GlobeGrid) -> bool {
+    let d = *pos - grid.center;
+    let distance = d.length();
+    if distance < 1e-6 || distance > grid.radius * GLOBE_GRID_REACH || grid.spacing <= 0.0 {
+        return false;
+    }
+    let d = d / distance;
+    let (x, y, z) = (d.dot(grid.axes[0]), d.dot(grid.axes[1]), d.dot(grid.axes[2]));
+    let lat = y.clamp(-1.0, 1.0).asin().to_degrees();
+    let lon = z.atan2(x).to_degrees();
+    let off_line = |a: f32| {
+        let r = a.rem_euclid(grid.spacing);
+        r.min(grid.spacing - r)
+    };
+    off_line(lat) <= grid.width * 0.5 || off_line(lon) * lat.to_radians().cos() <= grid.width * 0.5
+}
+
+/// Pinta con el color de `grid` (la de `Uniforms`) los fragmentos sobre sus líneas.
+pub fn apply_globe_grid(color: Color, pos: &Vector3, grid: Option<&GlobeGrid>) -> Color {
+    match grid {
+        Some(grid) if on_globe_grid(pos, grid) => Color { a: color.a, ..grid.color },
+        _ => color,
+    }
+}
+
+/// Estado de los shaders que cambia con la escena: luces, anillos que sombrean, planetshine
+/// y rejilla. Los globales (`set_ring_shadow` y compañía) sirven para una escena a la vez;
+/// pasado explícito en `RasterOptions::uniforms` se pueden dibujar varias escenas en
+/// paralelo (p. ej. las celdas de la hoja de contacto).
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Uniforms {
+    pub lights: Option<Lights>, // `None` = la luz propia de cada shader
+    pub ring_shadow: Option<RingShadow>,
+    pub planet_shine: Option<PlanetShine>,
+    pub globe_grid: Option<GlobeGrid>,
+}
+
+impl Uniforms {
+    /// Lo fijado ahora con `set_ring_shadow`, `set_planet_shine` y `set_globe_grid`; las
+    /// luces no tienen global (las pone la escena).
+    pub fn current() -> Self {
+        Self {
+            lights: None,
+            ring_shadow: RING_SHADOW.read().ok().and_then(|r| *r),
+            planet_shine: PLANET_SHINE.read().ok().and_then(|s| *s),
+            globe_grid: GLOBE_GRID.read().ok().and_then(|g| *g),
+        }
+    }
+
+    /// Dirección (normalizada) de la primera luz, o `default` si no trae luces.
+    pub fn key_light_dir(&self, default: Vector3) -> Vector3 {
+        self.lights.and_then(|l| l.as_slice().first().map(|l| l.direction)).unwrap_or_else(|| default.normalized())
+    }
+}
+
+/// Coordenada de latitud deformada para las bandas: u = (1-w)·y + w·y³.
+/// du/dy vale 1-w en el ecuador (bandas anchas) y 1+2w en los polos (bandas finas).
+pub fn gas_band_coord(y: f32, equator_widening: f32) -> f32 {
+    let w = equator_widening.clamp(0.0, 0.95);
+    (1.0 - w) * y + w * y * y * y
+}
+
+/// Función de bandas primarias (-1..1 antes de remapear), útil para inspeccionar los parámetros.
+pub fn gas_band_wave(y: f32, band_noise: f32, params: &GasParams) -> f32 {
+    (gas_band_coord(y, params.equator_widening) * params.band_count + band_noise * 4.0).sin()
+}
+
 pub fn gas(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
+    let ring_shadow = RING_SHADOW.read().ok().and_then(|r| *r);
+    gas_with(pos, normal, time, &GasParams { ring_shadow, ..GasParams::default() }, &Uniforms::current())
+}
+
+pub fn gas_with(pos: &Vector3, normal: &Vector3, time: f32, params: &GasParams, uniforms: &Uniforms) -> Color {
     let r = pos.length().clamp(0.0, 1.0);
     let gradient = (1.0 - r).powf(0.5);
 
@@ -97,16 +403,20 @@ pub fn gas(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
     let base_col = lerp_color(Color::new(10, 20, 60, 255), Color::new(220, 200, 160, 255), gradient);
 
     // Bandas primarias (hasta 3 capas de bandas)
-    let band_noise = fbm_noise(pos.y * 3.0 + time * 0.08, pos.x * 3.0, 6);
-    let bands_a = ((pos.y * 10.0 + band_noise * 4.0).sin() * 0.5 + 0.5).powf(1.6);
+    let band_y = gas_band_coord(pos.y, params.equator_widening);
+    let band_noise = fbm_noise3(pos.x * 3.0, pos.y * 3.0 + time * 0.08, pos.z * 3.0, 6);
+    let bands_a = (gas_band_wave(pos.y, band_noise, params) * 0.5 + 0.5).powf(1.6 * params.band_sharpness);
     let band_col_a = lerp_color(Color::new(255, 180, 90, 255), Color::new(180, 230, 255, 255), band_noise);
 
-    let band_noise2 = fbm_noise(pos.y * 6.0 - time * 0.12, pos.z * 2.0, 5);
-    let bands_b = ((pos.y * 6.0 + band_noise2 * 2.0).cos() * 0.5 + 0.5).powf(1.3);
+    let band_noise2 = fbm_noise3(pos.x * 2.0, pos.y * 6.0 - time * 0.12, pos.z * 2.0, 5);
+    let bands_b = ((band_y * (params.band_count * 0.6) + band_noise2 * 2.0).cos() * 0.5 + 0.5).powf(1.3 * params.band_sharpness);
     let band_col_b = lerp_color(Color::new(120, 80, 200, 255), Color::new(240, 220, 200, 255), band_noise2);
 
     // Nubes / remolinos locales
-    let swirl = fbm_noise(pos.x * 12.0 + time * 0.4, pos.z * 12.0, 5).powf(1.3);
+    // Las coordenadas de las nubes se advectan con un campo de curl (sin divergencia) que a su
+    // vez se desliza con el tiempo: remolinos que giran en vez de sólo desplazarse
+    let flow = curl_noise2(pos.x * 3.0 + time * 0.05, pos.z * 3.0 - time * 0.03) * params.flow_strength;
+    let swirl = fbm_noise3(pos.x * 12.0 + flow.x + time * 0.4, pos.y * 12.0, pos.z * 12.0 + flow.y, 5).powf(1.3);
     let swirl_col = Color::new(255, 245, 210, 255);
 
     // Capa de neblina
@@ -122,6 +432,12 @@ pub fn gas(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
 
     let mut col = blend_layered(base_col, &layers);
 
+    // Tormenta polar poligonal (en ambos polos, alrededor del eje Y)
+    if pos.length() > 1e-6 {
+        let vortex = polar_vortex(&pos.normalized(), params.polar_vortex_sides);
+        col = blend_colors(col, Color::new(90, 120, 170, 255), vortex * 0.6);
+    }
+
     // Anillo: calculamos máscara y pintamos un anillo con gradiente y polvo
     let ring_alpha = ring_mask(pos, 1.05, 1.35, (time * 0.03 + 0.3).sin().abs() * 0.3 + 0.7);
     if ring_alpha > 0.0001 {
@@ -136,28 +452,46 @@ pub fn gas(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
 
     // Perturbación menor (gaseoso suave)
     let pert = perturb_normal(normal, pos, 0.18);
-    let shaded = shading(col, &pert, Vector3::new(0.4, 0.8, 0.9).normalized(), 20.0, 0.18);
+    let shaded = with_lights(GAS_LIGHT_DIR, uniforms, |lights| match params.ring_shadow {
+        // Sombra de los anillos, luz por luz: atenuamos la que atraviesa la corona para llegar
+        Some(ring) => {
+            let lights: Vec<Light> = lights
+                .iter()
+                .map(|l| match ring_shadow_hit(pos, l.direction, &ring) {
+                    true => Light { intensity: l.intensity * RING_SHADOW_DARKEN, ..*l },
+                    false => *l,
+                })
+                .collect();
+            shading(col, &pert, &lights, 20.0, 0.18)
+        }
+        None => shading(col, &pert, lights, 20.0, 0.18),
+    });
 
     apply_atmosphere(shaded, pos, normal, time)
 }
 
 // ---------- MARCIANO MEJORADO (NOVEDAD: cristales/biolumin + campos magnéticos) ----------
 pub fn marciano(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
-    let base_noise = fbm_noise(pos.x * 6.0, pos.z * 6.0 + time * 0.02, 4);
+    marciano_with(pos, normal, time, &Uniforms::current())
+}
+
+/// `marciano` con las luces de `uniforms`.
+pub fn marciano_with(pos: &Vector3, normal: &Vector3, time: f32, uniforms: &Uniforms) -> Color {
+    let base_noise = fbm_noise3(pos.x * 6.0, pos.y * 6.0, pos.z * 6.0 + time * 0.02, 4);
     let mut col = lerp_color(Color::new(140, 30, 25, 255), Color::new(250, 100, 70, 255), base_noise);
 
     // Vetas emisivas y pulsantes (bioluminiscencia sub-superficial)
-    let veins = ridge(fbm_noise(pos.x * 22.0, pos.z * 22.0 + time * 0.15, 3));
+    let veins = ridge(fbm_noise3(pos.x * 22.0, pos.y * 22.0, pos.z * 22.0 + time * 0.15, 3));
     let pulsation = ((time * 2.2 + pos.y * 4.0).sin() * 0.5 + 0.5).powf(2.0);
     let emissive_col = Color::new(0, 255, 160, 255);
     col = blend_colors(col, emissive_col, veins * (0.45 + pulsation * 0.55));
 
     // Magma superficial
-    let magma = fbm_noise(pos.x * 4.0, pos.z * 4.0, 3);
+    let magma = fbm_noise3(pos.x * 4.0, pos.y * 4.0, pos.z * 4.0, 3);
     col = blend_colors(col, Color::new(255, 80, 50, 255), magma.powf(3.0) * 0.2);
 
     // NUEVO: cristales reflectivos (puntos brillantes con normal perturb fuerte)
-    let crystal_noise = fbm_noise(pos.x * 40.0 + time * 0.9, pos.z * 40.0, 3);
+    let crystal_noise = fbm_noise3(pos.x * 40.0 + time * 0.9, pos.y * 40.0, pos.z * 40.0, 3);
     let crystals = smoothstep(0.85, 0.98, crystal_noise);
     let crystal_col = Color::new(200, 230, 255, 255);
     col = blend_colors(col, crystal_col, crystals * 0.9);
@@ -173,13 +507,18 @@ pub fn marciano(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
     let pert = perturb_normal(normal, pos, 0.55);
     // Para cristales dejamos specular más alto localmente: aumentamos specular si crystals > 0
     let specular_strength = 0.2 + crystals * 0.6;
-    let shaded = shading(col, &pert, Vector3::new(0.5, 0.9, 0.2).normalized(), 36.0, specular_strength);
+    let shaded = with_lights(Vector3::new(0.5, 0.9, 0.2), uniforms, |lights| shading(col, &pert, lights, 36.0, specular_strength));
 
     apply_atmosphere(shaded, pos, normal, time)
 }
 
 // ---------- PANQUEQUES MÁS TEXTURADO Y CAPAS (mantequilla, syrup, grano, crema) ----------
 pub fn panqueques(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
+    panqueques_with(pos, normal, time, &Uniforms::current())
+}
+
+/// `panqueques` con las luces de `uniforms`.
+pub fn panqueques_with(pos: &Vector3, normal: &Vector3, time: f32, uniforms: &Uniforms) -> Color {
     let radio = (pos.x * pos.x + pos.z * pos.z).sqrt();
     let base1 = Color::new(200, 150, 90, 255);
     let base2 = Color::new(255, 210, 130, 255);
@@ -205,13 +544,137 @@ pub fn panqueques(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
     col = blend_colors(col, Color::new(80, 50, 30, 255), cracks * 0.18);
 
     let pert = perturb_normal(normal, pos, 0.32);
-    let shaded = shading(col, &pert, Vector3::new(0.5, 0.8, 0.3).normalized(), 36.0, 0.25);
+    let shaded = with_lights(Vector3::new(0.5, 0.8, 0.3), uniforms, |lights| shading(col, &pert, lights, 36.0, 0.25));
+
+    apply_atmosphere(shaded, pos, normal, time)
+}
+
+// ---------- CRISTAL / LAVA / HIELO ----------
+// Shaders alternativos con la misma firma; `ShaderType` sigue usando marciano/arcoiris/
+// panqueques para Crystal/Lava/Ice, estos quedan para planetas nuevos (con
+// `CristalShader`/`LavaShader`/`HieloShader` como `PlanetShader`).
+
+/// Facetas azules translúcidas: celdas de color casi plano (ruido cuantizado) con brillos
+/// especulares fuertes y un núcleo más claro que se transparenta hacia los bordes.
+pub fn cristal(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
+    cristal_with(pos, normal, time, &Uniforms::current())
+}
+
+/// `cristal` con las luces de `uniforms`.
+pub fn cristal_with(pos: &Vector3, normal: &Vector3, time: f32, uniforms: &Uniforms) -> Color {
+    // Facetas: el ruido se cuantiza en escalones para que cada zona tenga un tono plano
+    let facets = (fbm_noise(pos.x * 5.0, pos.z * 5.0 + pos.y * 3.0, 3) * 6.0).floor() / 6.0;
+    let mut col = lerp_color(Color::new(20, 60, 140, 255), Color::new(120, 200, 255, 255), facets);
 
+    // Translucidez: el centro del disco deja ver un núcleo claro, los bordes quedan oscuros
+    let core = (1.0 - fresnel(normal, Vector3::new(0.0, 0.0, -1.0), 1.0)).powf(2.0);
+    col = blend_colors(col, Color::new(200, 240, 255, 255), core * 0.35);
+
+    // Destellos que recorren las aristas de las facetas
+    let edges = ridge(fbm_noise(pos.x * 14.0 + time * 0.3, pos.z * 14.0, 3)).powf(4.0);
+    col = apply_emissive(col, Color::new(180, 230, 255, 255), edges * 0.4);
+
+    let pert = perturb_normal(normal, pos, 0.15);
+    let shaded = with_lights(Vector3::new(0.5, 0.8, 0.3), uniforms, |lights| shading(col, &pert, lights, 96.0, 0.7));
+    apply_atmosphere(shaded, pos, normal, time)
+}
+
+/// Lava: corteza oscura partida por grietas rojas emisivas (`ridge` del FBM) que laten.
+pub fn lava(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
+    lava_with(pos, normal, time, &Uniforms::current())
+}
+
+/// `lava` con las luces de `uniforms`.
+pub fn lava_with(pos: &Vector3, normal: &Vector3, time: f32, uniforms: &Uniforms) -> Color {
+    let crust_noise = fbm_noise(pos.x * 6.0, pos.z * 6.0, 4);
+    let crust = lerp_color(Color::new(25, 15, 12, 255), Color::new(70, 35, 25, 255), crust_noise);
+
+    let pert = perturb_normal(normal, pos, 0.45);
+    let shaded = with_lights(Vector3::new(0.5, 0.8, 0.3), uniforms, |lights| shading(crust, &pert, lights, 24.0, 0.1));
+
+    // Grietas: crestas del FBM que se desplazan despacio; emisivas, brillan también de noche
+    let cracks = ridge(fbm_noise(pos.x * 9.0 + time * 0.05, pos.z * 9.0, 4)).powf(3.0);
+    let pulse = 0.75 + 0.25 * (time * 1.7 + crust_noise * 6.0).sin();
+    let hot = lerp_color(Color::new(200, 30, 10, 255), Color::new(255, 170, 40, 255), cracks);
+    let molten = apply_emissive(shaded, hot, cracks * pulse);
+    apply_atmosphere(molten, pos, normal, time)
+}
+
+/// Hielo: azul pálido casi blanco, pulido (especular alto y concentrado) con vetas de
+/// grietas azul más profundo.
+pub fn hielo(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
+    hielo_with(pos, normal, time, &Uniforms::current())
+}
+
+/// `hielo` con las luces de `uniforms`.
+pub fn hielo_with(pos: &Vector3, normal: &Vector3, time: f32, uniforms: &Uniforms) -> Color {
+    let frost = fbm_noise(pos.x * 7.0, pos.z * 7.0, 4);
+    let mut col = lerp_color(Color::new(170, 210, 235, 255), Color::new(240, 250, 255, 255), frost);
+
+    let fissures = ridge(fbm_noise(pos.x * 20.0, pos.z * 20.0, 3)).powf(6.0);
+    col = blend_colors(col, Color::new(60, 120, 190, 255), fissures * 0.5);
+
+    // Casquetes polares más blancos
+    let polar = smoothstep(0.6, 0.9, pos.normalized().y.abs());
+    col = blend_colors(col, Color::new(250, 252, 255, 255), polar * 0.6);
+
+    let pert = perturb_normal(normal, pos, 0.1);
+    let shaded = with_lights(Vector3::new(0.5, 0.8, 0.3), uniforms, |lights| shading(col, &pert, lights, 128.0, 0.9));
     apply_atmosphere(shaded, pos, normal, time)
 }
 
 // ---------- ARCOÍRIS (se mantiene, ligero ajuste para capas) ----------
+/// Parámetros de los ríos de lava del planeta "arcoíris".
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LavaParams {
+    /// Distancia (en coordenadas del relieve) que avanza la lava por segundo, cuesta abajo.
+    pub flow_speed: f32,
+    /// Cuánto brillan los canales de lava sobre el color base (0 = sin lava).
+    pub glow: f32,
+}
+
+impl Default for LavaParams {
+    fn default() -> Self {
+        Self { flow_speed: 0.08, glow: 0.45 }
+    }
+}
+
+/// Ciclo (s) del desplazamiento de la lava: dos muestras desfasadas medio ciclo se mezclan
+/// para que el reinicio del desplazamiento no se note.
+const LAVA_FLOW_PERIOD: f32 = 4.0;
+
+/// Dirección cuesta abajo (normalizada) del relieve en (x, z), o cero en zonas planas.
+pub fn lava_flow_dir(x: f32, z: f32) -> Vector2 {
+    let g = relief_gradient(x, z);
+    let len = g.length();
+    if len < 1e-6 { Vector2::new(0.0, 0.0) } else { -g / len }
+}
+
+/// Desplazamiento de las coordenadas de muestreo tras avanzar `phase` segundos a `flow_speed`.
+pub fn lava_flow_offset(x: f32, z: f32, phase: f32, flow_speed: f32) -> Vector2 {
+    lava_flow_dir(x, z) * (phase * flow_speed)
+}
+
+/// Intensidad (0..1) de los canales de lava en (x, z). Los canales son crestas de un fbm
+/// que se "arrastran" cuesta abajo, así que el patrón fluye de lo alto a lo bajo.
+fn lava_channels(x: f32, z: f32, time: f32, params: &LavaParams) -> f32 {
+    let sample = |phase: f32| {
+        let o = lava_flow_offset(x, z, phase, params.flow_speed);
+        // mismo origen que el relieve: el punto toma el valor de la lava que venía de arriba
+        ridge(fbm_noise((x - o.x) * 5.0, (z - o.y) * 5.0, 3)).powf(6.0)
+    };
+    let p0 = time.rem_euclid(LAVA_FLOW_PERIOD);
+    let p1 = (time + LAVA_FLOW_PERIOD * 0.5).rem_euclid(LAVA_FLOW_PERIOD);
+    // peso triangular: cada muestra pesa 0 justo cuando su fase se reinicia
+    let w0 = 1.0 - (2.0 * p0 / LAVA_FLOW_PERIOD - 1.0).abs();
+    sample(p0) * w0 + sample(p1) * (1.0 - w0)
+}
+
 pub fn arcoiris(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
+    arcoiris_with(pos, normal, time, &LavaParams::default(), &Uniforms::current())
+}
+
+pub fn arcoiris_with(pos: &Vector3, normal: &Vector3, time: f32, params: &LavaParams, uniforms: &Uniforms) -> Color {
     let angle = pos.y.atan2(pos.x) + time * 0.7;
     let mut t = (angle / std::f32::consts::PI) % 2.0;
     if t < 0.0 { t += 2.0; }
@@ -227,30 +690,353 @@ pub fn arcoiris(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
     let layered = blend_colors(rimmed, Color::new(255, 255, 255, 255), pulse * 0.1);
 
     let pert = perturb_normal(normal, pos, 0.25);
-    let shaded = shading(layered, &pert, Vector3::new(0.5, 0.7, 0.3).normalized(), 64.0, 0.08);
+    let shaded = with_lights(Vector3::new(0.5, 0.7, 0.3), uniforms, |lights| shading(layered, &pert, lights, 64.0, 0.08));
+
+    // Lava emisiva: se suma después de la iluminación (brilla también en el lado oscuro)
+    let lava = lava_channels(pos.x, pos.z, time, params) * params.glow;
+    let molten = blend_colors(shaded, Color::new(255, 120, 30, 255), lava);
+    apply_atmosphere(molten, pos, normal, time)
+}
+
+// ---------- TOON (CEL SHADING) ----------
+/// Parámetros del shader toon. `Default` da 3 tonos y un contorno fino.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ToonParams {
+    /// Tonos en que se cuantiza el difuso (1 = color plano).
+    pub bands: u32,
+    /// Grosor del contorno: se pinta donde cos(normal, ojo) < `outline` (0 = sin contorno).
+    pub outline: f32,
+}
+
+impl Default for ToonParams {
+    fn default() -> Self {
+        Self { bands: 3, outline: 0.3 }
+    }
+}
+
+const TOON_AMBIENT: f32 = 0.15;
+const TOON_OUTLINE_COLOR: Color = Color::new(15, 12, 25, 255);
+
+/// Lambert (`ndotl`, 0..1) cuantizado en `bands` escalones: 0 en la sombra, 1 en el tono
+/// más claro. Valores dentro del mismo escalón dan exactamente el mismo brillo.
+pub fn toon_band(ndotl: f32, bands: u32) -> f32 {
+    let bands = bands.max(1);
+    if bands == 1 {
+        return 1.0;
+    }
+    let step = (ndotl.clamp(0.0, 1.0) * bands as f32).floor().min(bands as f32 - 1.0);
+    step / (bands as f32 - 1.0)
+}
+
+pub fn toon(pos: &Vector3, normal: &Vector3, time: f32) -> Color {
+    toon_with(pos, normal, time, &ToonParams::default(), &Uniforms::current())
+}
+
+/// Cel shading: dos colores planos (mares/tierras de ruido grueso), difuso en `bands` tonos
+/// sin especular y un contorno oscuro cerca de la silueta. Cada luz de `uniforms` se
+/// cuantiza por separado y se suma teñida con su color.
+pub fn toon_with(pos: &Vector3, normal: &Vector3, time: f32, params: &ToonParams, uniforms: &Uniforms) -> Color {
+    let n = normal.normalized();
+    let to_eye = (BASE_EYE - *pos).normalized();
+    if to_eye.dot(n) < params.outline {
+        return TOON_OUTLINE_COLOR;
+    }
+
+    let land = fbm_noise(pos.x * 3.0, pos.z * 3.0 + pos.y * 2.0 + time * 0.01, 3) > 0.5;
+    let base = if land { Color::new(235, 170, 90, 255) } else { Color::new(60, 150, 200, 255) };
+
+    let mut light = [TOON_AMBIENT; 3];
+    with_lights(DEFAULT_LIGHT_DIR, uniforms, |lights| {
+        for l in lights {
+            let band = toon_band(n.dot(l.direction).max(0.0), params.bands) * l.intensity * (1.0 - TOON_AMBIENT);
+            for (b, c) in light.iter_mut().zip([l.color.r, l.color.g, l.color.b]) {
+                *b += band * c as f32 / 255.0;
+            }
+        }
+    });
+    let tint = |c: u8, b: f32| (c as f32 * b).clamp(0.0, 255.0) as u8;
+    Color::new(tint(base.r, light[0]), tint(base.g, light[1]), tint(base.b, light[2]), 255)
+}
+
+// ---------- PLANETA CON PALETA GENERADA ----------
+/// Color de superficie (sin luz) de `PaletteShader`: el relieve de `roca_relief` recorre la
+/// rampa, de los valles (el primer color, el más oscuro) a las cumbres (el último).
+pub fn palette_surface(pos: &Vector3, ramp: &ColorRamp) -> Color {
+    let relief = roca_relief(pos.x, pos.y, pos.z, 0.0, &RocaParams::default());
+    ramp.sample(smoothstep(0.3, 0.7, relief))
+}
+
+/// Planeta con los colores de una paleta generada: `palette_surface` iluminado por las luces
+/// de `uniforms`, con la misma atmósfera que el rocoso.
+pub fn palette_with(pos: &Vector3, normal: &Vector3, time: f32, ramp: &ColorRamp, uniforms: &Uniforms) -> Color {
+    let col = palette_surface(pos, ramp);
+    let pert = perturb_normal(normal, pos, 0.6);
+    let shaded = with_lights(DEFAULT_LIGHT_DIR, uniforms, |lights| shading(col, &pert, lights, 32.0, 0.3));
     apply_atmosphere(shaded, pos, normal, time)
 }
 
+// ---------- SHADERS COMO TRAIT ----------
+/// Shader de superficie de un planeta: color de un punto (en espacio de vista) con su normal.
+/// El rasterizador recibe `&dyn PlanetShader` y los cuerpos un `Arc<dyn PlanetShader>`: un
+/// shader propio se implementa acá y se usa directo, sin tocar el rasterizador.
+pub trait PlanetShader: Send + Sync {
+    fn shade(&self, pos: &Vector3, normal: &Vector3, time: f32) -> Color;
+
+    /// Como `shade` pero con el estado de la escena explícito en vez de los globales; sólo lo
+    /// redefinen los shaders que leen alguno (ver `Uniforms`).
+    fn shade_with(&self, pos: &Vector3, normal: &Vector3, time: f32, _uniforms: &Uniforms) -> Color {
+        self.shade(pos, normal, time)
+    }
+
+    /// Opacidad (0..1) de lo que dibuja este shader. Menos de 1 = se mezcla con lo que ya
+    /// hay en pantalla y no escribe profundidad (ver `Framebuffer::blend_pixel`), así que
+    /// los cuerpos translúcidos tienen que dibujarse después de los opacos.
+    fn opacity(&self) -> f32 {
+        1.0
+    }
+
+    /// Nombre para mensajes (puede repetirse: los shaders se comparan por identidad).
+    fn name(&self) -> &str;
+}
+
+/// Structs vacíos sobre las funciones de este módulo, uno por shader.
+pub struct RocaShader;
+pub struct GasShader;
+pub struct MarcianoShader;
+pub struct ArcoirisShader;
+pub struct PanquequesShader;
+pub struct ToonShader;
+pub struct CristalShader;
+pub struct LavaShader;
+pub struct HieloShader;
+
+/// Planeta que toma sus colores de `generate_palette`: con una semilla por planeta (p. ej.
+/// `seed_from_name`) cada uno sale con un esquema propio que combina.
+pub struct PaletteShader {
+    ramp: ColorRamp,
+}
+
+impl PaletteShader {
+    pub fn new(seed: u32, harmony: Harmony) -> Self {
+        Self { ramp: generate_palette(seed, harmony) }
+    }
+
+    /// La rampa que muestrea (ver `palette_surface`).
+    pub fn ramp(&self) -> &ColorRamp {
+        &self.ramp
+    }
+}
+
+impl PlanetShader for RocaShader {
+    fn shade(&self, pos: &Vector3, normal: &Vector3, time: f32) -> Color { roca(pos, normal, time) }
+    fn shade_with(&self, pos: &Vector3, normal: &Vector3, time: f32, uniforms: &Uniforms) -> Color {
+        let drift_speed = CONTINENT_DRIFT.read().map_or(0.0, |d| *d);
+        roca_with(pos, normal, time, &RocaParams { drift_speed }, uniforms)
+    }
+    fn name(&self) -> &str { "Rocky" }
+}
+
+impl PlanetShader for GasShader {
+    fn shade(&self, pos: &Vector3, normal: &Vector3, time: f32) -> Color { gas(pos, normal, time) }
+    fn shade_with(&self, pos: &Vector3, normal: &Vector3, time: f32, uniforms: &Uniforms) -> Color {
+        gas_with(pos, normal, time, &GasParams { ring_shadow: uniforms.ring_shadow, ..GasParams::default() }, uniforms)
+    }
+    fn name(&self) -> &str { "Gas" }
+}
+
+impl PlanetShader for MarcianoShader {
+    fn shade(&self, pos: &Vector3, normal: &Vector3, time: f32) -> Color { marciano(pos, normal, time) }
+    fn shade_with(&self, pos: &Vector3, normal: &Vector3, time: f32, uniforms: &Uniforms) -> Color {
+        marciano_with(pos, normal, time, uniforms)
+    }
+    fn opacity(&self) -> f32 { crate::triangle::CRYSTAL_OPACITY }
+    fn name(&self) -> &str { "Crystal" }
+}
+
+impl PlanetShader for ArcoirisShader {
+    fn shade(&self, pos: &Vector3, normal: &Vector3, time: f32) -> Color { arcoiris(pos, normal, time) }
+    fn shade_with(&self, pos: &Vector3, normal: &Vector3, time: f32, uniforms: &Uniforms) -> Color {
+        arcoiris_with(pos, normal, time, &LavaParams::default(), uniforms)
+    }
+    fn name(&self) -> &str { "Lava" }
+}
+
+impl PlanetShader for PanquequesShader {
+    fn shade(&self, pos: &Vector3, normal: &Vector3, time: f32) -> Color { panqueques(pos, normal, time) }
+    fn shade_with(&self, pos: &Vector3, normal: &Vector3, time: f32, uniforms: &Uniforms) -> Color {
+        panqueques_with(pos, normal, time, uniforms)
+    }
+    fn name(&self) -> &str { "Ice" }
+}
+
+impl PlanetShader for ToonShader {
+    fn shade(&self, pos: &Vector3, normal: &Vector3, time: f32) -> Color { toon(pos, normal, time) }
+    fn shade_with(&self, pos: &Vector3, normal: &Vector3, time: f32, uniforms: &Uniforms) -> Color {
+        toon_with(pos, normal, time, &ToonParams::default(), uniforms)
+    }
+    fn name(&self) -> &str { "Toon" }
+}
+
+impl PlanetShader for CristalShader {
+    fn shade(&self, pos: &Vector3, normal: &Vector3, time: f32) -> Color { cristal(pos, normal, time) }
+    fn shade_with(&self, pos: &Vector3, normal: &Vector3, time: f32, uniforms: &Uniforms) -> Color {
+        cristal_with(pos, normal, time, uniforms)
+    }
+    fn name(&self) -> &str { "Cristal" }
+}
+
+impl PlanetShader for LavaShader {
+    fn shade(&self, pos: &Vector3, normal: &Vector3, time: f32) -> Color { lava(pos, normal, time) }
+    fn shade_with(&self, pos: &Vector3, normal: &Vector3, time: f32, uniforms: &Uniforms) -> Color {
+        lava_with(pos, normal, time, uniforms)
+    }
+    fn name(&self) -> &str { "Lava" }
+}
+
+impl PlanetShader for HieloShader {
+    fn shade(&self, pos: &Vector3, normal: &Vector3, time: f32) -> Color { hielo(pos, normal, time) }
+    fn shade_with(&self, pos: &Vector3, normal: &Vector3, time: f32, uniforms: &Uniforms) -> Color {
+        hielo_with(pos, normal, time, uniforms)
+    }
+    fn name(&self) -> &str { "Hielo" }
+}
+
+impl PlanetShader for PaletteShader {
+    fn shade(&self, pos: &Vector3, normal: &Vector3, time: f32) -> Color {
+        palette_with(pos, normal, time, &self.ramp, &Uniforms::current())
+    }
+    fn shade_with(&self, pos: &Vector3, normal: &Vector3, time: f32, uniforms: &Uniforms) -> Color {
+        palette_with(pos, normal, time, &self.ramp, uniforms)
+    }
+    fn name(&self) -> &str { "Paleta" }
+}
+
+// ---------- ENTORNO (SKYBOX) ----------
+/// Cielo procedural por dirección: gradiente oscuro + banda tipo vía láctea + las estrellas
+/// de `stars` (ver `Starfield`). Barato (sin FBM) porque se evalúa en todos los píxeles.
+pub fn starfield_env(dir: Vector3, stars: &Starfield) -> Color {
+    let lon = dir.z.atan2(dir.x); // -π..π
+    let lat = dir.y.clamp(-1.0, 1.0).asin(); // -π/2..π/2
+
+    let base = lerp_color(Color::new(2, 2, 8, 255), Color::new(8, 8, 24, 255), dir.y * 0.5 + 0.5);
+
+    // banda galáctica inclinada
+    let band = (1.0 - ((lat - 0.4 * lon.sin()) * 3.0).abs()).max(0.0).powf(3.0);
+    let mut col = blend_colors(base, Color::new(60, 50, 90, 255), band * 0.35);
+
+    if let Some(b) = stars.brightness(dir.normalized()) {
+        col = blend_colors(col, Color::new(255, 250, 235, 255), 0.4 + b * 0.6);
+    }
+    col
+}
+
 /* ---------------- UTILIDADES AVANZADAS ---------------- */
 fn perturb_normal(n: &Vector3, pos: &Vector3, scale: f32) -> Vector3 {
     // Perturba la normal usando derivadas aproximadas de FBM para dar relieve
-    let eps = 0.001;
-    let nx = fbm_noise((pos.x + eps) * 6.0, pos.z * 6.0, 3) - fbm_noise((pos.x - eps) * 6.0, pos.z * 6.0, 3);
-    let nz = fbm_noise(pos.x * 6.0, (pos.z + eps) * 6.0, 3) - fbm_noise(pos.x * 6.0, (pos.z - eps) * 6.0, 3);
-    let tangent = Vector3::new(nx, 0.0, nz) * scale;
+    let g = relief_gradient(pos.x, pos.z);
+    let tangent = Vector3::new(g.x, 0.0, g.y) * scale;
     (*n + tangent).normalized()
 }
 
-fn shading(base: Color, normal: &Vector3, light_dir: Vector3, shininess: f32, specular_strength: f32) -> Color {
-    let ndotl = normal.dot(light_dir).max(0.0);
+/// Diferencias centrales (sin dividir entre 2·eps) del relieve fbm(6x, 6z) en x y z.
+/// Es el campo que usan `perturb_normal` y el flujo de lava.
+pub fn relief_gradient(x: f32, z: f32) -> Vector2 {
+    let eps = 0.001;
+    let gx = fbm_noise((x + eps) * 6.0, z * 6.0, 3) - fbm_noise((x - eps) * 6.0, z * 6.0, 3);
+    let gz = fbm_noise(x * 6.0, (z + eps) * 6.0, 3) - fbm_noise(x * 6.0, (z - eps) * 6.0, 3);
+    Vector2::new(gx, gz)
+}
+
+/// Dirección hacia la luz de la escena por defecto (la del planeta rocoso); cada shader
+/// tiene además la suya propia mientras `Uniforms::lights` no traiga otras.
+pub const DEFAULT_LIGHT_DIR: Vector3 = Vector3::new(0.6, 0.8, 0.5);
+
+/// Luz direccional con color e intensidad (1 = la luz original de los shaders).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Light {
+    pub direction: Vector3, // hacia la luz, normalizada
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl Light {
+    /// Luz blanca de intensidad 1 que viene de `direction` (no hace falta normalizarla).
+    pub fn directional(direction: Vector3) -> Self {
+        Self { direction: direction.normalized(), color: Color::new(255, 255, 255, 255), intensity: 1.0 }
+    }
+}
+
+/// Cuántas luces entran en `Lights`.
+pub const MAX_LIGHTS: usize = 4;
+
+/// Luces de una escena (p. ej. una principal cálida y un relleno frío) en un arreglo fijo,
+/// así `Uniforms` sigue siendo `Copy`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Lights {
+    lights: [Light; MAX_LIGHTS],
+    len: usize,
+}
+
+impl Lights {
+    /// Las primeras `MAX_LIGHTS` de `lights`, con la dirección normalizada.
+    pub fn new(lights: &[Light]) -> Self {
+        let unused = Light { direction: Vector3::new(0.0, 0.0, 0.0), color: Color::BLACK, intensity: 0.0 };
+        let mut out = Self { lights: [unused; MAX_LIGHTS], len: 0 };
+        for l in lights.iter().take(MAX_LIGHTS) {
+            out.lights[out.len] = Light { direction: l.direction.normalized(), ..*l };
+            out.len += 1;
+        }
+        out
+    }
+
+    pub fn as_slice(&self) -> &[Light] {
+        &self.lights[..self.len]
+    }
+}
+
+/// Llama a `f` con las luces de `uniforms`, o con la luz única de siempre (dirección
+/// `default_dir` y color `light_color()`) si no trae ninguna.
+fn with_lights<R>(default_dir: Vector3, uniforms: &Uniforms, f: impl FnOnce(&[Light]) -> R) -> R {
+    match &uniforms.lights {
+        Some(lights) => f(lights.as_slice()),
+        None => f(&[Light { direction: default_dir.normalized(), color: light_color(), intensity: 1.0 }]),
+    }
+}
+
+// Color de la luz (se fija desde la demo); multiplica el difuso y tiñe el especular.
+static LIGHT_COLOR: RwLock<Color> = RwLock::new(Color::new(255, 255, 255, 255));
+
+/// Fija el color de la luz que usan todos los shaders (blanco = la iluminación original).
+pub fn set_light_color(color: Color) {
+    if let Ok(mut current) = LIGHT_COLOR.write() {
+        *current = color;
+    }
+}
+
+pub fn light_color() -> Color {
+    LIGHT_COLOR.read().map_or(Color::new(255, 255, 255, 255), |c| *c)
+}
+
+fn shading(base: Color, normal: &Vector3, lights: &[Light], shininess: f32, specular_strength: f32) -> Color {
     let ambient = 0.08;
-    let mut lit = apply_brightness(base, ambient + ndotl * (1.0 - ambient));
+    // El ambiente no depende de las luces: el difuso de cada una se tiñe con su color y se suma
+    let mut diffuse = [ambient; 3];
+    for light in lights {
+        let ndotl = normal.dot(light.direction).max(0.0) * light.intensity * (1.0 - ambient);
+        for (d, l) in diffuse.iter_mut().zip([light.color.r, light.color.g, light.color.b]) {
+            *d += ndotl * (l as f32 / 255.0);
+        }
+    }
+    let channel = |c: u8, b: f32| (c as f32 * b).clamp(0.0, 255.0) as u8;
+    let mut lit = Color::new(channel(base.r, diffuse[0]), channel(base.g, diffuse[1]), channel(base.b, diffuse[2]), base.a);
 
-    // Specular Blinn-Phong (suave)
+    // Specular Blinn-Phong (suave), uno por luz
     let view = Vector3::new(0.0, 0.0, 1.0); // cámara fija
-    let half = (light_dir + view).normalized();
-    let spec = normal.dot(half).max(0.0).powf(shininess) * specular_strength;
-    lit = blend_colors(lit, Color::new(255, 255, 255, 255), spec as f32);
+    for light in lights {
+        let half = (light.direction + view).normalized();
+        let spec = normal.dot(half).max(0.0).powf(shininess) * specular_strength * light.intensity;
+        lit = blend_colors(lit, Color { a: 255, ..light.color }, spec);
+    }
 
     // Rim lighting para accentuar bordes
     let rim = 1.0 - view.dot(*normal).clamp(0.0, 1.0);
@@ -268,7 +1054,60 @@ fn fresnel(normal: &Vector3, view_dir: Vector3, power: f32) -> f32 {
 }
 
 /* ---------------- RUIDO (FBM y Perlin-like) ---------------- */
+/// Curl de un potencial FBM: (∂ψ/∂y, -∂ψ/∂x) con diferencias centrales.
+/// Al ser el rotacional de un escalar, el campo es (aprox.) libre de divergencia: no crea
+/// fuentes ni sumideros, sólo remolinos.
+pub fn curl_noise2(x: f32, y: f32) -> Vector2 {
+    let eps = 0.01;
+    let dpdx = (fbm_noise(x + eps, y, 3) - fbm_noise(x - eps, y, 3)) / (2.0 * eps);
+    let dpdy = (fbm_noise(x, y + eps, 3) - fbm_noise(x, y - eps, 3)) / (2.0 * eps);
+    Vector2::new(dpdy, -dpdx)
+}
+
+// Tope global de octavas de FBM (control de calidad); por defecto no limita nada.
+static MAX_OCTAVES: AtomicU32 = AtomicU32::new(u32::MAX);
+
+/// Limita las octavas de todos los FBM (menos octavas = shaders más baratos y menos detalle).
+pub fn set_max_octaves(octaves: u32) {
+    MAX_OCTAVES.store(octaves.max(1), Ordering::Relaxed);
+}
+
+pub fn max_octaves() -> u32 {
+    MAX_OCTAVES.load(Ordering::Relaxed)
+}
+
+// Semilla del ruido de todos los shaders (0 = los planetas originales).
+static NOISE_SEED: AtomicU32 = AtomicU32::new(0);
+
+/// Cambia la semilla del ruido: se mezcla (XOR) en las coordenadas de la rejilla antes del
+/// hash, así cada semilla da otro campo de ruido. Con la misma semilla y el mismo `time` la
+/// salida es idéntica byte a byte.
+pub fn set_noise_seed(seed: u32) {
+    NOISE_SEED.store(seed, Ordering::Relaxed);
+}
+
+pub fn noise_seed() -> u32 {
+    NOISE_SEED.load(Ordering::Relaxed)
+}
+
+/// Para tests que tocan (o leen) los parámetros globales de los shaders: los serializa y los
+/// deja en sus valores por defecto mientras dure el guard.
+#[cfg(test)]
+pub(crate) fn lock_uniforms() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    set_continent_drift(0.0);
+    set_ring_shadow(None);
+    set_planet_shine(None);
+    set_globe_grid(None);
+    set_light_color(Color::WHITE);
+    set_max_octaves(u32::MAX);
+    set_noise_seed(0);
+    guard
+}
+
 fn fbm_noise(x: f32, y: f32, oct: u32) -> f32 {
+    let oct = oct.min(max_octaves());
     let mut sum = 0.0;
     let mut amp = 1.0;
     let mut freq = 1.0;
@@ -282,6 +1121,23 @@ fn fbm_noise(x: f32, y: f32, oct: u32) -> f32 {
     ((sum / maxv) + 1.0) * 0.5
 }
 
+/// Como `fbm_noise` pero en 3D: muestreando la posición completa en la esfera no hay
+/// franjas verticales ni pellizco en los polos (donde x y z tienden a cero a la vez).
+fn fbm_noise3(x: f32, y: f32, z: f32, oct: u32) -> f32 {
+    let oct = oct.min(max_octaves());
+    let mut sum = 0.0;
+    let mut amp = 1.0;
+    let mut freq = 1.0;
+    let mut maxv = 0.0;
+    for _ in 0..oct {
+        sum += noise3d(x * freq, y * freq, z * freq) * amp;
+        maxv += amp;
+        amp *= 0.5;
+        freq *= 2.0;
+    }
+    ((sum / maxv) + 1.0) * 0.5
+}
+
 fn noise2d(x: f32, y: f32) -> f32 {
     let xi = x.floor() as i32;
     let yi = y.floor() as i32;
@@ -294,40 +1150,75 @@ fn noise2d(x: f32, y: f32) -> f32 {
     lerp_f32(lerp_f32(v00, v10, fade(xf)), lerp_f32(v01, v11, fade(xf)), fade(yf)) * 2.0 - 1.0
 }
 
+/// Ruido de valor trilineal: `noise2d` con un tercer eje (8 esquinas en vez de 4).
+fn noise3d(x: f32, y: f32, z: f32) -> f32 {
+    let (xi, yi, zi) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+    let (xf, yf, zf) = (fade(x - x.floor()), fade(y - y.floor()), fade(z - z.floor()));
+    let layer = |zi: i32| {
+        let v00 = hash3_to_float(xi, yi, zi);
+        let v10 = hash3_to_float(xi + 1, yi, zi);
+        let v01 = hash3_to_float(xi, yi + 1, zi);
+        let v11 = hash3_to_float(xi + 1, yi + 1, zi);
+        lerp_f32(lerp_f32(v00, v10, xf), lerp_f32(v01, v11, xf), yf)
+    };
+    lerp_f32(layer(zi), layer(zi + 1), zf) * 2.0 - 1.0
+}
+
 fn fade(t: f32) -> f32 { t * t * t * (t * (t * 6.0 - 15.0) + 10.0) }
 fn lerp_f32(a: f32, b: f32, t: f32) -> f32 { a + (b - a) * t }
 fn hash_to_float(x: i32, y: i32) -> f32 {
+    let s = noise_seed() as i32;
+    let (x, y) = (x ^ s, y ^ s.rotate_left(16));
     ((x.wrapping_mul(374761393) ^ y.wrapping_mul(668265263)).wrapping_add(1274126177) & 0xFFFF) as f32 / 65535.0
 }
 
-/* ---------------- COLOR UTILITIES ---------------- */
-fn apply_brightness(c: Color, b: f32) -> Color {
-    Color::new(
-        (c.r as f32 * b).clamp(0.0, 255.0) as u8,
-        (c.g as f32 * b).clamp(0.0, 255.0) as u8,
-        (c.b as f32 * b).clamp(0.0, 255.0) as u8,
-        c.a,
-    )
+fn hash3_to_float(x: i32, y: i32, z: i32) -> f32 {
+    let s = noise_seed() as i32;
+    let (x, y, z) = (x ^ s, y ^ s.rotate_left(16), z ^ s.rotate_left(8));
+    ((x.wrapping_mul(374761393) ^ y.wrapping_mul(668265263) ^ z.wrapping_mul(1440662683)).wrapping_add(1274126177) & 0xFFFF) as f32 / 65535.0
 }
 
+/* ---------------- COLOR UTILITIES ---------------- */
+
+// `lerp_color` y `blend_colors` hacen la misma mezcla; con la feature `simd` se procesan
+// los cuatro canales (alfa incluido) juntos en un f32x4. Ambas rutas hacen mul/add por separado
+// (sin FMA) y truncan con `as u8`, así que el resultado es bit a bit idéntico al escalar.
 fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    mix_colors(a, b, t)
+}
+
+fn blend_colors(base: Color, top: Color, alpha: f32) -> Color {
+    mix_colors(base, top, alpha)
+}
+
+#[cfg(not(feature = "simd"))]
+fn mix_colors(a: Color, b: Color, t: f32) -> Color {
+    mix_colors_scalar(a, b, t)
+}
+
+#[cfg(feature = "simd")]
+fn mix_colors(a: Color, b: Color, t: f32) -> Color {
+    mix_colors_simd(a, b, t)
+}
+
+pub fn mix_colors_scalar(a: Color, b: Color, t: f32) -> Color {
     let t = t.clamp(0.0, 1.0);
     Color::new(
         (a.r as f32 * (1.0 - t) + b.r as f32 * t) as u8,
         (a.g as f32 * (1.0 - t) + b.g as f32 * t) as u8,
         (a.b as f32 * (1.0 - t) + b.b as f32 * t) as u8,
-        255,
+        (a.a as f32 * (1.0 - t) + b.a as f32 * t) as u8,
     )
 }
 
-fn blend_colors(base: Color, top: Color, alpha: f32) -> Color {
-    let a = alpha.clamp(0.0, 1.0);
-    Color::new(
-        (base.r as f32 * (1.0 - a) + top.r as f32 * a) as u8,
-        (base.g as f32 * (1.0 - a) + top.g as f32 * a) as u8,
-        (base.b as f32 * (1.0 - a) + top.b as f32 * a) as u8,
-        255,
-    )
+#[cfg(feature = "simd")]
+pub fn mix_colors_simd(a: Color, b: Color, t: f32) -> Color {
+    use wide::f32x4;
+    let t = t.clamp(0.0, 1.0);
+    let va = f32x4::from([a.r as f32, a.g as f32, a.b as f32, a.a as f32]);
+    let vb = f32x4::from([b.r as f32, b.g as f32, b.b as f32, b.a as f32]);
+    let out = (va * f32x4::splat(1.0 - t) + vb * f32x4::splat(t)).to_array();
+    Color::new(out[0] as u8, out[1] as u8, out[2] as u8, out[3] as u8)
 }
 
 fn rainbow_gradient(t: f32) -> Color {
@@ -345,3 +1236,343 @@ fn rainbow_gradient(t: f32) -> Color {
         _ => Color::MAGENTA,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Color "aleatorio" (determinista) número `i`, alfa incluido.
+    #[cfg(feature = "simd")]
+    fn sample_color(i: u32) -> Color {
+        use crate::sampling::hash01;
+        let c = |k: u32| (hash01(i * 4 + k, 7) * 255.0) as u8;
+        Color::new(c(0), c(1), c(2), c(3))
+    }
+
+    #[test]
+    fn scalar_mix_interpolates_alpha() {
+        let (a, b) = (Color::new(0, 0, 0, 0), Color::new(200, 100, 50, 200));
+        assert_eq!(mix_colors_scalar(a, b, 0.5), Color::new(100, 50, 25, 100));
+        assert_eq!(mix_colors_scalar(a, b, 0.0), a);
+        assert_eq!(mix_colors_scalar(a, b, 1.0), b);
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn simd_mix_matches_scalar() {
+        for i in 0..2000 {
+            let (a, b) = (sample_color(2 * i), sample_color(2 * i + 1));
+            let t = crate::sampling::hash01(i, 11) * 1.2 - 0.1; // también fuera de 0..1 (se recorta)
+            assert_eq!(mix_colors_simd(a, b, t), mix_colors_scalar(a, b, t), "{a:?} {b:?} t={t}");
+        }
+    }
+
+    /// Cambios de signo de la onda de bandas (sin ruido) de polo a polo.
+    fn band_sign_changes(params: &GasParams) -> usize {
+        let wave: Vec<f32> = (0..=400).map(|i| gas_band_wave(-1.0 + i as f32 / 200.0, 0.0, params)).collect();
+        wave.windows(2).filter(|w| (w[0] < 0.0) != (w[1] < 0.0)).count()
+    }
+
+    #[test]
+    fn more_bands_give_more_sign_changes() {
+        for widening in [0.0, 0.6] {
+            let few = GasParams { band_count: 6.0, equator_widening: widening, ..GasParams::default() };
+            let many = GasParams { band_count: 18.0, ..few };
+            assert!(band_sign_changes(&many) > band_sign_changes(&few));
+        }
+    }
+
+    #[test]
+    fn curl_field_is_divergence_free() {
+        let _uniforms = lock_uniforms();
+        let h = 0.01; // el mismo paso que usa `curl_noise2`
+        let (mut div_sum, mut grad_sum) = (0.0f32, 0.0f32);
+        for i in 0..50 {
+            let (x, y) = (0.37 * i as f32, 1.3 + 0.21 * i as f32);
+            let dvx_dx = (curl_noise2(x + h, y).x - curl_noise2(x - h, y).x) / (2.0 * h);
+            let dvy_dy = (curl_noise2(x, y + h).y - curl_noise2(x, y - h).y) / (2.0 * h);
+            div_sum += (dvx_dx + dvy_dy).abs();
+            grad_sum += dvx_dx.abs() + dvy_dy.abs();
+        }
+        // Cada derivada por separado no es cero, pero se cancelan
+        assert!(grad_sum > 1.0, "{grad_sum}");
+        assert!(div_sum < grad_sum * 1e-3, "div {div_sum} vs {grad_sum}");
+    }
+
+    #[test]
+    fn lava_flows_down_the_relief_gradient() {
+        let _uniforms = lock_uniforms();
+        let height = |x: f32, z: f32| fbm_noise(x * 6.0, z * 6.0, 3);
+        let mut checked = 0;
+        for i in 0..40 {
+            let (x, z) = (0.13 * i as f32 - 2.0, 0.07 * i as f32 + 0.5);
+            let g = relief_gradient(x, z);
+            if g.length() < 1e-4 {
+                continue;
+            }
+            let o = lava_flow_offset(x, z, 1.5, 0.08);
+            // Misma dirección que -gradiente, con el largo que da la velocidad
+            assert!((o.length() - 1.5 * 0.08).abs() < 1e-5);
+            assert!(o.normalized().dot(-g.normalized()) > 0.999);
+            // Y un paso chico en esa dirección baja el relieve
+            let step = o.normalized() * 1e-3;
+            assert!(height(x + step.x, z + step.y) < height(x, z));
+            checked += 1;
+        }
+        assert!(checked > 30);
+    }
+
+    #[test]
+    fn ring_shadow_hit_at_known_geometries() {
+        // Anillo en el plano y = 0 entre 1.5 y 2.5 alrededor del origen
+        let ring = RingShadow { center: Vector3::zero(), normal: Vector3::new(0.0, 1.0, 0.0), inner: 1.5, outer: 2.5 };
+        let down = Vector3::new(0.0, -1.0, 0.0);
+        // Sobre la corona, con la luz del otro lado del plano: sombra
+        assert!(ring_shadow_hit(&Vector3::new(2.0, 1.0, 0.0), down, &ring));
+        // El rayo cae en el hueco interior o por fuera de la corona
+        assert!(!ring_shadow_hit(&Vector3::new(1.0, 1.0, 0.0), down, &ring));
+        assert!(!ring_shadow_hit(&Vector3::new(3.0, 1.0, 0.0), down, &ring));
+        // La luz está del mismo lado que el punto: el plano queda detrás
+        assert!(!ring_shadow_hit(&Vector3::new(2.0, 1.0, 0.0), -down, &ring));
+        // Luz paralela al plano
+        assert!(!ring_shadow_hit(&Vector3::new(2.0, 1.0, 0.0), Vector3::new(1.0, 0.0, 0.0), &ring));
+        // Rayo oblicuo sin normalizar: desde (0, 1, 0) hacia (+2, -1, 0) corta en r = 2
+        assert!(ring_shadow_hit(&Vector3::new(0.0, 1.0, 0.0), Vector3::new(4.0, -2.0, 0.0), &ring));
+    }
+
+    #[test]
+    fn ring_shadow_only_dims_the_light_that_crosses_the_ring() {
+        let _uniforms = lock_uniforms();
+        let ring = RingShadow { center: Vector3::zero(), normal: Vector3::new(0.0, 1.0, 0.0), inner: 1.5, outer: 2.5 };
+        let pos = Vector3::new(0.0, 0.6, 0.8);
+        // `blocked` cruza el plano en (0, 0, 2), dentro de la corona; `clear` se aleja del plano
+        let blocked = Light::directional(Vector3::new(0.0, -0.5, 1.0));
+        let clear = Light::directional(Vector3::new(0.0, 1.0, 0.2));
+        let shade = |lights: Vec<Light>, ring_shadow| {
+            let uniforms = Uniforms { lights: Some(Lights::new(&lights)), ..Uniforms::default() };
+            gas_with(&pos, &pos, 0.0, &GasParams { ring_shadow, ..GasParams::default() }, &uniforms)
+        };
+        let luma = |c: Color| c.r as u32 + c.g as u32 + c.b as u32;
+
+        assert_eq!(shade(vec![clear], Some(ring)), shade(vec![clear], None));
+        let (shadowed, lit) = (shade(vec![blocked, clear], Some(ring)), shade(vec![blocked, clear], None));
+        assert!(luma(shadowed) < luma(lit), "{shadowed:?} !< {lit:?}");
+        // La luz sin obstáculo sigue sumando: no es un oscurecido global
+        assert!(luma(shadowed) > luma(shade(vec![blocked], Some(ring))));
+    }
+
+    #[test]
+    fn polar_vortex_has_n_fold_symmetry() {
+        let at = |rho: f32, theta: f32, y_sign: f32| {
+            Vector3::new(rho * theta.cos(), y_sign * (1.0 - rho * rho).sqrt(), rho * theta.sin())
+        };
+        for sides in [5, 6, 8] {
+            let sector = std::f32::consts::TAU / sides as f32;
+            let mut asymmetric = false;
+            for i in 0..40 {
+                let (rho, theta) = (0.05 + 0.01 * i as f32, 0.37 * i as f32);
+                let v = polar_vortex(&at(rho, theta, 1.0), sides);
+                for k in 1..sides {
+                    let rotated = polar_vortex(&at(rho, theta + k as f32 * sector, 1.0), sides);
+                    assert!((v - rotated).abs() < 1e-3, "N={sides} rho={rho} theta={theta} k={k}");
+                }
+                // Mismo polígono en el polo sur
+                assert!((v - polar_vortex(&at(rho, theta, -1.0), sides)).abs() < 1e-6);
+                asymmetric |= (v - polar_vortex(&at(rho, theta + sector * 0.5, 1.0), sides)).abs() > 0.05;
+            }
+            // Medio sector no es una simetría: el patrón es un polígono, no un círculo
+            assert!(asymmetric, "N={sides}");
+        }
+        assert_eq!(polar_vortex(&Vector3::new(0.0, 1.0, 0.0), 2), 0.0);
+    }
+
+    #[test]
+    fn limb_gets_thicker_atmosphere_than_center_at_any_scale() {
+        use crate::camera::BASE_EYE;
+        let limb_normal = Vector3::new(80f32.to_radians().sin(), 0.0, -80f32.to_radians().cos());
+        let center_normal = Vector3::new(0.0, 0.0, -1.0);
+        let mut reference = None;
+        for radius in [0.05, 0.3, 1.0, 5.0, 40.0] {
+            // Esfera de `radius` cuya distancia al ojo crece con ella: se ve del mismo tamaño
+            let c = BASE_EYE + Vector3::new(0.0, 0.0, 4.0 * radius);
+            let center = atmosphere_thickness(&(c + center_normal * radius), &center_normal);
+            let limb = atmosphere_thickness(&(c + limb_normal * radius), &limb_normal);
+            assert!(center < 1e-6, "r={radius}: {center}");
+            assert!(limb > center + 0.5, "r={radius}: {limb} <= {center}");
+            let reference = *reference.get_or_insert(limb);
+            assert!((limb - reference).abs() < 1e-4, "r={radius}: {limb} != {reference}");
+        }
+    }
+
+    #[test]
+    fn reddish_light_tints_diffuse_but_not_ambient() {
+        let red = Color::new(255, 90, 70, 255);
+        let light = |color| [Light { direction: Vector3::new(0.0, 0.0, 1.0), color, intensity: 1.0 }];
+        let facing = Vector3::new(0.0, 0.0, 1.0);
+        // Superficie blanca de frente a la luz: con la luz rojiza queda rojiza
+        let lit = shading(Color::WHITE, &facing, &light(red), 32.0, 0.0);
+        assert!(lit.r > 200 && lit.r as i32 - lit.g as i32 > 80 && lit.r as i32 - lit.b as i32 > 80, "{lit:?}");
+        let white = shading(Color::WHITE, &facing, &light(Color::WHITE), 32.0, 0.0);
+        assert!(white.r.abs_diff(white.g) < 3 && white.g.abs_diff(white.b) < 3, "{white:?}");
+        // De espaldas sólo queda el ambiente (y el rim), igual con cualquier color de luz
+        let away = Vector3::new(0.0, 0.0, -1.0);
+        let dark_red = shading(Color::WHITE, &away, &light(red), 32.0, 0.3);
+        let dark_white = shading(Color::WHITE, &away, &light(Color::WHITE), 32.0, 0.3);
+        assert_eq!(dark_red, dark_white);
+    }
+
+    #[test]
+    fn planetshine_brightens_and_tints_the_planet_facing_dark_side() {
+        // Luna nueva: la luna entre el sol (+X) y un planeta azulado en el origen
+        let shine = PlanetShine {
+            center: Vector3::new(0.0, 0.0, 0.0),
+            radius: 1.0,
+            albedo: Color::new(80, 130, 255, 255),
+            light_dir: Vector3::new(1.0, 0.0, 0.0),
+        };
+        let (moon, moon_radius) = (Vector3::new(2.5, 0.0, 0.0), 0.3);
+        let dark = Color::new(20, 20, 20, 255);
+        let toward = Vector3::new(-1.0, 0.0, 0.0);
+        let near = apply_planet_shine(dark, &(moon + toward * moon_radius), &toward, Some(&shine));
+        let far = apply_planet_shine(dark, &(moon - toward * moon_radius), &-toward, Some(&shine));
+        assert_eq!(far, dark);
+        assert!(near.r > dark.r && near.g > dark.g && near.b > dark.b, "{near:?}");
+        // Teñido con el albedo: el azul sube más que el rojo, y es un relleno tenue
+        assert!(near.b - dark.b > near.r - dark.r, "{near:?}");
+        assert!(near.b < 80, "{near:?}");
+        // Sin planeta no cambia nada
+        assert_eq!(apply_planet_shine(dark, &(moon + toward * moon_radius), &toward, None), dark);
+    }
+
+    #[test]
+    fn drift_moves_the_land_mask_only_when_nonzero() {
+        let _uniforms = lock_uniforms();
+        // Máscara de tierra: relieve sobre el umbral, en una rejilla del hemisferio de frente
+        let land_mask = |time: f32, drift_speed: f32| -> Vec<bool> {
+            let params = RocaParams { drift_speed };
+            (0..24 * 24)
+                .map(|i| {
+                    let (x, y) = ((i % 24) as f32 / 12.0 - 1.0, (i / 24) as f32 / 12.0 - 1.0);
+                    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+                    roca_relief(x, y, z, time, &params) > 0.5
+                })
+                .collect()
+        };
+        assert_eq!(land_mask(0.0, 0.0), land_mask(5000.0, 0.0));
+        let (before, after) = (land_mask(0.0, 0.01), land_mask(5000.0, 0.01));
+        let changed = before.iter().zip(&after).filter(|(a, b)| a != b).count();
+        assert!(changed > before.len() / 10, "sólo cambiaron {changed} celdas");
+        // Reproducible: el mismo tiempo da los mismos continentes
+        assert_eq!(after, land_mask(5000.0, 0.01));
+    }
+
+    #[test]
+    fn toon_fragments_in_the_same_band_share_brightness() {
+        let _uniforms = lock_uniforms();
+        // Luz desde el ojo: lambert = cos(normal, ojo), y el mismo punto da el mismo color base
+        let eye_light = Light::directional(Vector3::new(0.0, 0.0, -1.0));
+        let uniforms = Uniforms { lights: Some(Lights::new(&[eye_light])), ..Uniforms::default() };
+        let pos = Vector3::new(0.0, 0.0, -1.0);
+        let at = |angle: f32| {
+            toon_with(&pos, &Vector3::new(angle.sin(), 0.0, -angle.cos()), 0.0, &ToonParams::default(), &uniforms)
+        };
+        // cos 0 = 1 y cos 0.15 ≈ 0.989: los dos en el tono más claro
+        assert_eq!(toon_band(1.0, 3), toon_band(0.15f32.cos(), 3));
+        assert_eq!(at(0.0), at(0.15));
+        // cos 1.0 ≈ 0.54: otro tono, más oscuro
+        let (bright, mid) = (at(0.0), at(1.0));
+        assert_ne!(bright, mid);
+        assert!(mid.r <= bright.r && mid.g <= bright.g && mid.b <= bright.b && mid != TOON_OUTLINE_COLOR);
+    }
+
+    #[test]
+    fn grid_latitudes_get_the_line_color_and_others_do_not() {
+        let grid = GlobeGrid {
+            center: Vector3::new(0.0, 0.0, 0.0),
+            radius: 1.0,
+            axes: [Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0)],
+            spacing: 30.0,
+            width: 2.0,
+            color: Color::new(255, 255, 0, 255),
+        };
+        let surface = Color::new(40, 90, 160, 200);
+        // Longitud 15° (entre dos meridianos), latitud en grados
+        let at = |lat: f32| {
+            let (lat, lon) = (lat.to_radians(), 15f32.to_radians());
+            Vector3::new(lat.cos() * lon.cos(), lat.sin(), lat.cos() * lon.sin())
+        };
+        for lat in [0.0, 30.0, -60.0, 30.8] {
+            assert_eq!(apply_globe_grid(surface, &at(lat), Some(&grid)), Color { a: 200, ..grid.color }, "lat {lat}");
+        }
+        for lat in [15.0, 45.0, -20.0, 31.5] {
+            assert_eq!(apply_globe_grid(surface, &at(lat), Some(&grid)), surface, "lat {lat}");
+        }
+        // Sin rejilla, o fuera del planeta, el color queda igual
+        assert_eq!(apply_globe_grid(surface, &at(30.0), None), surface);
+        assert_eq!(apply_globe_grid(surface, &(at(30.0) * 2.0), Some(&grid)), surface);
+    }
+
+    #[test]
+    fn different_seeds_give_different_fbm_samples() {
+        let _uniforms = lock_uniforms();
+        let coords = [(0.3, 1.7), (2.25, -4.5), (10.1, 3.3), (-7.8, 0.05)];
+        let samples = |seed: u32| {
+            set_noise_seed(seed);
+            coords.map(|(x, y)| fbm_noise(x, y, 5))
+        };
+        let (a, b) = (samples(1), samples(2));
+        assert!(a.iter().zip(&b).all(|(a, b)| a != b), "{a:?} vs {b:?}");
+        // La misma semilla reproduce los mismos valores
+        assert_eq!(samples(1), a);
+    }
+
+    #[test]
+    fn palette_shader_takes_its_colors_from_the_seeded_palette() {
+        use crate::sampling::seed_from_name;
+        let _uniforms = lock_uniforms();
+        let points: Vec<Vector3> = (0..64)
+            .map(|i| {
+                let (x, y) = ((i % 8) as f32 / 4.0 - 0.875, (i / 8) as f32 / 4.0 - 0.875);
+                Vector3::new(x, y, -(1.0 - x * x - y * y).max(0.0).sqrt())
+            })
+            .collect();
+        let surface = |shader: &PaletteShader| points.iter().map(|p| palette_surface(p, shader.ramp())).collect::<Vec<_>>();
+
+        let planet = PaletteShader::new(seed_from_name("Kepler-22b"), Harmony::Triadic);
+        assert_eq!(planet.ramp(), &generate_palette(seed_from_name("Kepler-22b"), Harmony::Triadic));
+        // El relieve recorre la rampa: salen varios de sus colores, no uno solo
+        let colors = surface(&planet);
+        let mut distinct = colors.clone();
+        distinct.sort_by_key(|c| (c.r, c.g, c.b));
+        distinct.dedup();
+        assert!(distinct.len() > 5, "{} colores", distinct.len());
+        // Otra semilla, otros colores; la misma, los mismos
+        let other = PaletteShader::new(seed_from_name("Trappist"), Harmony::Triadic);
+        let same = colors.iter().zip(surface(&other)).filter(|(a, b)| **a == *b).count();
+        assert!(same < points.len() / 4, "{same} iguales");
+        // Y el shader (con luz y atmósfera) es el de su paleta
+        let normal = Vector3::new(0.0, 0.0, -1.0);
+        assert_ne!(planet.shade(&points[27], &normal, 0.0), other.shade(&points[27], &normal, 0.0));
+    }
+
+    #[test]
+    fn toon_bands_every_scene_light() {
+        let _uniforms = lock_uniforms();
+        let pos = Vector3::new(0.0, 0.0, -1.0);
+        let normal = Vector3::new(0.0, 0.0, -1.0);
+        let lit = |lights: &[Light]| {
+            let uniforms = Uniforms { lights: Some(Lights::new(lights)), ..Uniforms::default() };
+            toon_with(&pos, &normal, 0.0, &ToonParams::default(), &uniforms)
+        };
+        let key = Light { color: Color::new(255, 200, 150, 255), ..Light::directional(Vector3::new(0.0, 0.0, -1.0)) };
+        // Relleno azul que también da de frente: suma su tono (tinte) al de la principal
+        let fill = Light { color: Color::new(80, 120, 255, 255), intensity: 0.5, ..key };
+        let (alone, both) = (lit(&[key]), lit(&[key, fill]));
+        assert!(both.b > alone.b, "{alone:?} -> {both:?}");
+        // Una luz que no le da a la cara no cambia nada (su lambert cae en el tono 0)
+        let behind = Light::directional(Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(lit(&[key, behind]), alone);
+        // Las luces de la escena reemplazan a la luz propia del shader
+        assert_ne!(lit(&[Light::directional(Vector3::new(0.0, 0.0, -1.0))]), lit(&[behind]));
+    }
+}
@@ -0,0 +1,134 @@
+use crate::camera::Camera;
+use crate::framebuffer::Framebuffer;
+use crate::sampling::{hash01, sample_unit_square, unit_sphere_point, Distribution};
+use raylib::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+/// Estrellas del fondo por defecto y radio angular (radianes) de cada una.
+pub const STAR_COUNT: usize = 1500;
+const STAR_RADIUS: f32 = 0.004;
+
+/// Celdas de longitud x latitud en que se guardan las estrellas para buscarlas.
+const STAR_GRID: (usize, usize) = (64, 32);
+
+/// Estrellas repartidas en la esfera con `sample_unit_square` (`Halton` = parejas, `Uniform`
+/// = azar con grumos), cada una con un brillo en 0..1. Se guardan por celda de una rejilla
+/// de longitud/latitud, así cada rayo sólo mira las de su celda y las vecinas.
+#[derive(Clone, Debug)]
+pub struct Starfield {
+    cells: Vec<Vec<(Vector3, f32)>>, // por celda: dirección y brillo
+}
+
+impl Starfield {
+    /// `count` estrellas; la misma `distribution` y el mismo `seed` dan el mismo cielo.
+    pub fn new(count: usize, distribution: Distribution, seed: u32) -> Self {
+        let mut cells = vec![Vec::new(); STAR_GRID.0 * STAR_GRID.1];
+        for (i, p) in sample_unit_square(distribution, count, seed).into_iter().enumerate() {
+            let dir = unit_sphere_point(p.x, p.y);
+            let (cx, cy) = Self::cell(dir);
+            cells[cy * STAR_GRID.0 + cx].push((dir, hash01(i as u32, seed ^ 0x2545_F491)));
+        }
+        Self { cells }
+    }
+
+    /// Celda (longitud, latitud) de una dirección normalizada.
+    fn cell(dir: Vector3) -> (usize, usize) {
+        let lon = (dir.z.atan2(dir.x) + PI) / TAU;
+        let lat = dir.y.clamp(-1.0, 1.0).asin() / PI + 0.5;
+        let cx = ((lon * STAR_GRID.0 as f32) as usize).min(STAR_GRID.0 - 1);
+        let cy = ((lat * STAR_GRID.1 as f32) as usize).min(STAR_GRID.1 - 1);
+        (cx, cy)
+    }
+
+    /// Brillo de la estrella que cae en `dir` (normalizada), si hay una a menos de
+    /// `STAR_RADIUS`.
+    pub fn brightness(&self, dir: Vector3) -> Option<f32> {
+        let (cx, cy) = Self::cell(dir);
+        let min_dot = STAR_RADIUS.cos();
+        let (w, h) = (STAR_GRID.0 as i32, STAR_GRID.1 as i32);
+        (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+            .filter_map(|(dx, dy)| {
+                let y = cy as i32 + dy;
+                (0..h).contains(&y).then(|| ((cx as i32 + dx).rem_euclid(w) + y * w) as usize)
+            })
+            .flat_map(|cell| &self.cells[cell])
+            .filter(|(star, _)| star.dot(dir) >= min_dot)
+            .map(|&(_, b)| b)
+            .reduce(f32::max)
+    }
+
+    /// Todas las estrellas (dirección y brillo), en ningún orden en particular.
+    pub fn stars(&self) -> impl Iterator<Item = &(Vector3, f32)> {
+        self.cells.iter().flatten()
+    }
+}
+
+/// Pinta el fondo evaluando `env_fn` con la dirección del rayo de cada píxel.
+/// No toca el z-buffer, así que hay que llamarlo después de `clear` y antes de la geometría.
+pub fn render_skybox<F>(fb: &mut Framebuffer, camera: &Camera, env_fn: F)
+where
+    F: Fn(Vector3) -> Color,
+{
+    for y in 0..fb.height as i32 {
+        for x in 0..fb.width as i32 {
+            let dir = camera.ray_dir(x, y, fb.width, fb.height);
+            fb.set_pixel_with_color(x, y, env_fn(dir));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{look_direction, BASE_EYE};
+
+    /// Entorno de prueba: la dirección como color (igual que `DebugView::Normals`).
+    fn direction_color(d: Vector3) -> Color {
+        let to_u8 = |v: f32| ((v * 0.5 + 0.5) * 255.0) as u8;
+        Color::new(to_u8(d.x), to_u8(d.y), to_u8(d.z), 255)
+    }
+
+    #[test]
+    fn opposite_corners_sample_different_directions() {
+        let (w, h) = (40, 30);
+        let mut fb = Framebuffer::new(w, h, Color::BLACK);
+        render_skybox(&mut fb, &Camera::default(), direction_color);
+        let px = fb.pixels();
+        let (top_left, bottom_right) = (px[0], px[(w * h - 1) as usize]);
+        assert_ne!(top_left, bottom_right);
+        // Arriba a la izquierda mira a -x/+y; abajo a la derecha a +x/-y
+        assert!(top_left.r < 128 && top_left.g > 128);
+        assert!(bottom_right.r > 128 && bottom_right.g < 128);
+
+        // Girar la cámara cambia el fondo
+        let turned = Camera { target: BASE_EYE + look_direction(1.0, 0.0), ..Camera::default() };
+        render_skybox(&mut fb, &turned, direction_color);
+        assert_ne!(fb.pixels()[0], top_left);
+    }
+
+    #[test]
+    fn starfield_lights_its_stars_and_follows_the_distribution() {
+        let stars = Starfield::new(400, Distribution::Halton, 3);
+        assert_eq!(stars.stars().count(), 400);
+        // En la dirección de cada estrella hay brillo (el suyo, o uno mayor si otra se le encima)
+        for &(dir, b) in stars.stars() {
+            assert!(stars.brightness(dir).is_some_and(|found| found >= b), "{dir:?}");
+        }
+        // Lejos de todas las estrellas no hay brillo
+        let dark = (0..2000)
+            .map(|i| unit_sphere_point(hash01(i, 99), hash01(i, 100)))
+            .find(|&d| stars.stars().all(|&(s, _)| s.dot(d) < (3.0 * STAR_RADIUS).cos()))
+            .expect("algún rayo sin estrellas cerca");
+        assert_eq!(stars.brightness(dark), None);
+
+        let directions = |distribution| {
+            let mut dirs: Vec<(f32, f32, f32)> =
+                Starfield::new(50, distribution, 3).stars().map(|&(d, _)| (d.x, d.y, d.z)).collect();
+            dirs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            dirs
+        };
+        assert_eq!(directions(Distribution::Halton), directions(Distribution::Halton));
+        assert_ne!(directions(Distribution::Halton), directions(Distribution::Uniform));
+    }
+}
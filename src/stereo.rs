@@ -0,0 +1,110 @@
+use crate::camera::Camera;
+use crate::framebuffer::Framebuffer;
+use raylib::prelude::*;
+
+/// Separación entre ojos por defecto (unidades de mundo).
+pub const DEFAULT_EYE_SEPARATION: f32 = 0.12;
+
+/// Par de cámaras izquierda/derecha desplazadas ±separación/2 a lo largo de la derecha de
+/// `base` (`Camera::right`), así la paralaje sigue siendo horizontal en pantalla al orbitar.
+/// El objetivo se desplaza con el ojo: las dos miran en paralelo.
+pub fn stereo_cameras(base: &Camera, eye_separation: f32) -> (Camera, Camera) {
+    let half = base.right() * (eye_separation * 0.5);
+    let left = Camera { position: base.position - half, target: base.target - half, ..*base };
+    let right = Camera { position: base.position + half, target: base.target + half, ..*base };
+    (left, right)
+}
+
+/// Renderiza la escena dos veces, una por ojo, en las mitades izquierda y derecha de `fb`
+/// (side-by-side). Cada mitad es un viewport propio: la escena se dibuja en un framebuffer
+/// de medio ancho y se copia a su lugar.
+pub fn render_stereo<F>(fb: &mut Framebuffer, mut scene: F, left_cam: &Camera, right_cam: &Camera)
+where
+    F: FnMut(&mut Framebuffer, &Camera),
+{
+    let half_w = fb.width / 2;
+    let mut eye = Framebuffer::new(half_w, fb.height, fb.background_color);
+
+    scene(&mut eye, left_cam);
+    fb.blit_from(&eye, 0, 0);
+
+    eye.clear();
+    scene(&mut eye, right_cam);
+    fb.blit_from(&eye, half_w as i32, 0);
+}
+
+/// Anaglifo rojo/cian: renderiza cada ojo a pantalla completa y los combina con
+/// `Framebuffer::composite_anaglyph` (izquierdo -> rojo, derecho -> verde + azul).
+pub fn render_anaglyph<F>(fb: &mut Framebuffer, mut scene: F, left_cam: &Camera, right_cam: &Camera)
+where
+    F: FnMut(&mut Framebuffer, &Camera),
+{
+    let mut right = Framebuffer::new(fb.width, fb.height, fb.background_color);
+    scene(fb, left_cam);
+    scene(&mut right, right_cam);
+    fb.color_buffer = Framebuffer::composite_anaglyph(&fb.color_buffer, &right.color_buffer);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{look_direction, BASE_EYE};
+    use crate::triangle::{draw_filled_triangle, DebugView, RasterOptions, ShaderType};
+
+    /// Triángulo en el origen, de frente a la cámara fija.
+    const TRIANGLE: [Vector3; 3] = [Vector3::new(-0.5, -0.5, 0.0), Vector3::new(0.0, 0.5, 0.0), Vector3::new(0.5, -0.5, 0.0)];
+
+    /// `world` (en coordenadas de mundo) visto desde `camera`.
+    fn draw_world(fb: &mut Framebuffer, camera: &Camera, world: [Vector3; 3]) {
+        let options = RasterOptions { debug_view: Some(DebugView::Normals), ..RasterOptions::default() };
+        let [a, b, c] = world;
+        draw_filled_triangle(fb, a, b, c, camera, &ShaderType::Rocky, 0.0, &options);
+    }
+
+    /// Un triángulo en el origen visto desde `camera`.
+    fn scene(fb: &mut Framebuffer, camera: &Camera) {
+        draw_world(fb, camera, TRIANGLE);
+    }
+
+    /// X promedio (relativa al borde izquierdo de la mitad) de lo dibujado en `[x0, x0 + w)`.
+    fn mean_x(fb: &Framebuffer, x0: u32, w: u32) -> f32 {
+        let px = fb.pixels();
+        let xs: Vec<u32> = (0..fb.height)
+            .flat_map(|y| (0..w).map(move |x| (x, y)))
+            .filter(|&(x, y)| px[(y * fb.width + x0 + x) as usize] != Color::BLACK)
+            .map(|(x, _)| x)
+            .collect();
+        assert!(!xs.is_empty());
+        xs.iter().sum::<u32>() as f32 / xs.len() as f32
+    }
+
+    #[test]
+    fn stereo_halves_are_horizontally_offset() {
+        let (left, right) = stereo_cameras(&Camera::default(), 0.5);
+        let mut fb = Framebuffer::new(128, 64, Color::BLACK);
+        render_stereo(&mut fb, scene, &left, &right);
+
+        // El ojo izquierdo ve el objeto más a la derecha que el derecho
+        let (in_left, in_right) = (mean_x(&fb, 0, 64), mean_x(&fb, 64, 64));
+        assert!(in_left > in_right + 2.0, "{in_left} vs {in_right}");
+    }
+
+    #[test]
+    fn eyes_stay_side_by_side_when_the_camera_is_yawed() {
+        for yaw in [std::f32::consts::FRAC_PI_2, std::f32::consts::PI, -2.0] {
+            let base = Camera { target: BASE_EYE + look_direction(yaw, 0.0), ..Camera::default() };
+            let (left, right) = stereo_cameras(&base, 0.5);
+            // Los ojos se separan sólo a lo largo de la derecha de la pantalla
+            let offset = right.eye() - left.eye();
+            assert!((offset - base.right() * 0.5).length() < 1e-5, "yaw {yaw}: {offset:?}");
+
+            // El mismo triángulo, a 3 unidades delante de la cámara girada y de frente a ella
+            let up = base.view_up();
+            let world = TRIANGLE.map(|v| base.eye() + base.right() * v.x + up * v.y + base.forward() * 3.0);
+            let mut fb = Framebuffer::new(128, 64, Color::BLACK);
+            render_stereo(&mut fb, |fb, cam| draw_world(fb, cam, world), &left, &right);
+            let (in_left, in_right) = (mean_x(&fb, 0, 64), mean_x(&fb, 64, 64));
+            assert!(in_left > in_right + 2.0, "yaw {yaw}: {in_left} vs {in_right}");
+        }
+    }
+}
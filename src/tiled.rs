@@ -0,0 +1,316 @@
+use raylib::prelude::*;
+use crate::framebuffer::Framebuffer;
+#[cfg(feature = "parallel")]
+use crate::framebuffer::blend_over;
+use crate::triangle::{prepare_clipped, rasterize_region, FragmentTarget, PreparedTriangle, RasterOptions};
+use crate::shader::PlanetShader;
+
+/// Tamaño de tile por defecto (en píxeles).
+pub const DEFAULT_TILE_SIZE: i32 = 32;
+
+/// Rasterizador por tiles: los triángulos se acumulan con `submit` y se reparten en tiles
+/// de pantalla; `flush` los rasteriza tile por tile para que las escrituras al color/z-buffer
+/// queden locales. Dentro de cada tile se respeta el orden de envío, así que el resultado es
+/// idéntico al de llamar `draw_filled_view_triangle` directamente.
+///
+/// Con la feature `parallel`, `flush` reparte las filas de tiles entre hilos con rayon:
+/// cada hilo es dueño de su franja del z-buffer y de un color buffer propio, que al final
+/// se copian al framebuffer. Los fragmentos translúcidos se mezclan contra una copia de la
+/// imagen tomada antes de rasterizar, igual que `Framebuffer::blend_pixel`.
+pub struct TiledRasterizer<'a> {
+    tile_size: i32,
+    tiles_x: i32,
+    tiles_y: i32,
+    triangles: Vec<PreparedTriangle<'a>>, // cada uno con el shader con que se envió
+    bins: Vec<Vec<usize>>, // por tile: índices en `triangles`, en orden de envío
+}
+
+impl<'a> TiledRasterizer<'a> {
+    pub fn new(width: u32, height: u32, tile_size: i32) -> Self {
+        let tile_size = tile_size.max(1);
+        let tiles_x = (width as i32 + tile_size - 1) / tile_size;
+        let tiles_y = (height as i32 + tile_size - 1) / tile_size;
+        Self {
+            tile_size,
+            tiles_x,
+            tiles_y,
+            triangles: Vec::new(),
+            bins: vec![Vec::new(); (tiles_x * tiles_y) as usize],
+        }
+    }
+
+    /// Encola un triángulo (mismos parámetros que `draw_filled_view_triangle`).
+    #[allow(clippy::too_many_arguments)]
+    pub fn submit(
+        &mut self,
+        framebuffer: &Framebuffer,
+        v0: Vector3,
+        v1: Vector3,
+        v2: Vector3,
+        shader: &'a dyn PlanetShader,
+        time: f32,
+        options: &RasterOptions,
+    ) {
+        for tri in prepare_clipped(framebuffer, [v0, v1, v2], None, shader, time, options) {
+            self.push(tri);
+        }
+    }
+
+    /// Encola un triángulo con normales por vértice (mismos parámetros que `draw_smooth_triangle`).
+    #[allow(clippy::too_many_arguments)]
+    pub fn submit_smooth(
+        &mut self,
+        framebuffer: &Framebuffer,
+        v: [Vector3; 3],
+        n: [Vector3; 3],
+        shader: &'a dyn PlanetShader,
+        time: f32,
+        options: &RasterOptions,
+    ) {
+        let normals = options.smooth.then_some(n);
+        for tri in prepare_clipped(framebuffer, v, normals, shader, time, options) {
+            self.push(tri);
+        }
+    }
+
+    /// Reparte un triángulo ya preparado en los tiles que toca.
+    fn push(&mut self, tri: PreparedTriangle<'a>) {
+        if tri.min_x > tri.max_x || tri.min_y > tri.max_y {
+            return;
+        }
+
+        let index = self.triangles.len();
+        let tx0 = (tri.min_x / self.tile_size).clamp(0, self.tiles_x - 1);
+        let tx1 = (tri.max_x / self.tile_size).clamp(0, self.tiles_x - 1);
+        let ty0 = (tri.min_y / self.tile_size).clamp(0, self.tiles_y - 1);
+        let ty1 = (tri.max_y / self.tile_size).clamp(0, self.tiles_y - 1);
+        for ty in ty0..=ty1 {
+            for tx in tx0..=tx1 {
+                self.bins[(ty * self.tiles_x + tx) as usize].push(index);
+            }
+        }
+        self.triangles.push(tri);
+    }
+
+    /// Rasteriza todo lo encolado y vacía las colas.
+    /// Con la feature `parallel` las filas de tiles se rasterizan en paralelo (mismo resultado).
+    pub fn flush(&mut self, framebuffer: &mut Framebuffer) {
+        #[cfg(feature = "parallel")]
+        {
+            self.flush_parallel(framebuffer);
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            self.flush_serial(framebuffer);
+        }
+    }
+
+    /// Rasteriza tile por tile en el hilo actual.
+    pub fn flush_serial(&mut self, framebuffer: &mut Framebuffer) {
+        let (width, height) = (framebuffer.width as i32, framebuffer.height as i32);
+        for (ty, row) in self.bins.chunks_mut(self.tiles_x as usize).enumerate() {
+            rasterize_row(framebuffer, &self.triangles, row, ty as i32, self.tile_size, width, height);
+        }
+        self.triangles.clear();
+    }
+
+    /// Una fila de tiles por tarea de rayon, cada una sobre su `Band`.
+    #[cfg(feature = "parallel")]
+    pub fn flush_parallel(&mut self, framebuffer: &mut Framebuffer) {
+        use rayon::prelude::*;
+
+        let (width, height) = (framebuffer.width as i32, framebuffer.height as i32);
+        let band_len = (self.tile_size * width) as usize;
+        let (triangles, tile_size) = (&self.triangles, self.tile_size);
+        let background = framebuffer.pixels();
+        let bands: Vec<Band> = framebuffer
+            .z_buffer
+            .par_chunks_mut(band_len.max(1))
+            .zip(self.bins.par_chunks_mut(self.tiles_x as usize))
+            .enumerate()
+            .map(|(ty, (z, row))| {
+                let y0 = ty as i32 * tile_size;
+                let start = (y0 * width) as usize;
+                let background = &background[start..start + z.len()];
+                let mut band = Band { width, y0, colors: vec![None; z.len()], background, z };
+                rasterize_row(&mut band, triangles, row, ty as i32, tile_size, width, height);
+                band
+            })
+            .collect();
+
+        // Las escrituras al `Image` no se pueden repartir: se copian acá, en serie
+        for band in &bands {
+            for (i, color) in band.colors.iter().enumerate() {
+                if let Some(color) = *color {
+                    let (x, y) = (i as i32 % width, band.y0 + i as i32 / width);
+                    framebuffer.color_buffer.draw_pixel(x, y, color);
+                }
+            }
+        }
+        self.triangles.clear();
+    }
+}
+
+/// Rasteriza (y vacía) los bins de la fila de tiles `ty`, en orden de tile y de envío.
+fn rasterize_row<T: FragmentTarget + ?Sized>(
+    target: &mut T,
+    triangles: &[PreparedTriangle],
+    row: &mut [Vec<usize>],
+    ty: i32,
+    tile_size: i32,
+    width: i32,
+    height: i32,
+) {
+    let tile_min_y = ty * tile_size;
+    let tile_max_y = (tile_min_y + tile_size - 1).min(height - 1);
+    for (tx, bin) in row.iter_mut().enumerate() {
+        let tile_min_x = tx as i32 * tile_size;
+        let tile_max_x = (tile_min_x + tile_size - 1).min(width - 1);
+        for &i in bin.iter() {
+            let tri = &triangles[i];
+            rasterize_region(
+                target,
+                tri,
+                tri.min_x.max(tile_min_x),
+                tri.max_x.min(tile_max_x),
+                tri.min_y.max(tile_min_y),
+                tri.max_y.min(tile_max_y),
+            );
+        }
+        bin.clear();
+    }
+}
+
+/// Franja de filas `[y0, y0 + filas)` de la pantalla: su parte del z-buffer, los colores
+/// escritos (`None` = sin tocar) y los que había antes (contra los que se mezcla).
+#[cfg(feature = "parallel")]
+struct Band<'a> {
+    width: i32,
+    y0: i32,
+    z: &'a mut [f32],
+    colors: Vec<Option<Color>>,
+    background: &'a [Color],
+}
+
+#[cfg(feature = "parallel")]
+impl FragmentTarget for Band<'_> {
+    fn depth(&self, x: i32, y: i32) -> f32 {
+        self.z[((y - self.y0) * self.width + x) as usize]
+    }
+
+    /// Como en `Framebuffer`: los translúcidos se mezclan y no escriben profundidad.
+    fn write(&mut self, x: i32, y: i32, depth: f32, color: Color) {
+        let idx = ((y - self.y0) * self.width + x) as usize;
+        let color = if color.a == 255 {
+            self.z[idx] = depth;
+            color
+        } else {
+            blend_over(color, self.colors[idx].unwrap_or(self.background[idx]))
+        };
+        self.colors[idx] = Some(color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometria::transform_model;
+    use crate::obj_loader::ObjModel;
+    use crate::shader::lock_uniforms;
+    use crate::triangle::{draw_filled_view_triangle, DebugView, ShaderType};
+
+    /// Triángulos de la esfera del repo, centrada en `z` y con radio `scale`.
+    fn sphere(z: f32, scale: f32) -> Vec<[Vector3; 3]> {
+        let model = ObjModel::load_with("sphere-1.obj", true).expect("sphere-1.obj");
+        let v = transform_model(&model, Vector3::new(0.0, 0.0, z), 0.4, 0.2, scale);
+        model
+            .faces
+            .iter()
+            .flat_map(|f| (1..f.len() - 1).map(|i| [v[f[0]], v[f[i]], v[f[i + 1]]]).collect::<Vec<_>>())
+            .collect()
+    }
+
+    /// Dibuja `tris` en `fb` directamente o por tiles.
+    fn draw(fb: &mut Framebuffer, tris: &[[Vector3; 3]], shader: &dyn PlanetShader, options: &RasterOptions, tiled: bool) {
+        if !tiled {
+            for t in tris {
+                draw_filled_view_triangle(fb, t[0], t[1], t[2], shader, 0.5, options);
+            }
+            return;
+        }
+        let mut raster = TiledRasterizer::new(fb.width, fb.height, 16);
+        for t in tris {
+            raster.submit(fb, t[0], t[1], t[2], shader, 0.5, options);
+        }
+        raster.flush(fb);
+    }
+
+    #[test]
+    fn tiled_matches_direct_on_sphere() {
+        let _uniforms = lock_uniforms();
+        let normals = RasterOptions { debug_view: Some(DebugView::Normals), ..RasterOptions::default() };
+        let (inner, outer) = (sphere(1.0, 0.8), sphere(0.0, 1.2));
+
+        let render = |tiled: bool| {
+            let mut fb = Framebuffer::new(100, 75, Color::DARKGRAY);
+            draw(&mut fb, &inner, &ShaderType::Rocky, &normals, tiled);
+            // Translúcido encima del opaco: se mezcla y no escribe profundidad
+            draw(&mut fb, &outer, &ShaderType::Crystal, &RasterOptions::default(), tiled);
+            (fb.pixels(), fb.z_buffer.clone())
+        };
+        let (direct, tiled) = (render(false), render(true));
+        assert!(direct.0.iter().any(|&c| c != Color::DARKGRAY));
+        assert_eq!(direct.0, tiled.0);
+        assert_eq!(direct.1, tiled.1);
+    }
+
+    /// Medición, no prueba: `cargo test --release --features parallel -- --ignored --nocapture`.
+    /// Gigante gaseoso con anillos a 800x600, `flush_serial` contra `flush_parallel`.
+    #[cfg(feature = "parallel")]
+    #[test]
+    #[ignore]
+    fn serial_vs_parallel_timing_on_gas_giant() {
+        let _uniforms = lock_uniforms();
+        let planet = sphere(0.0, 1.0);
+        let rings_model = crate::geometria::generate_rings(1.35, 2.1, 128, 0.03);
+        let v = transform_model(&rings_model, Vector3::new(0.0, 0.0, 0.0), 0.4, 0.45, 1.0);
+        let rings: Vec<[Vector3; 3]> = rings_model
+            .faces
+            .iter()
+            .flat_map(|f| (1..f.len() - 1).map(|i| [v[f[0]], v[f[i]], v[f[i + 1]]]).collect::<Vec<_>>())
+            .collect();
+        let options = RasterOptions::default();
+        const FRAMES: u32 = 10;
+
+        let time = |parallel: bool| {
+            let mut image = Vec::new();
+            let start = std::time::Instant::now();
+            for _ in 0..FRAMES {
+                let mut fb = Framebuffer::new(800, 600, Color::BLACK);
+                let mut raster = TiledRasterizer::new(fb.width, fb.height, DEFAULT_TILE_SIZE);
+                for (tris, shader) in [(&planet, &ShaderType::Gas), (&rings, &ShaderType::Ice)] {
+                    for t in tris {
+                        raster.submit(&fb, t[0], t[1], t[2], shader, 0.5, &options);
+                    }
+                }
+                if parallel {
+                    raster.flush_parallel(&mut fb);
+                } else {
+                    raster.flush_serial(&mut fb);
+                }
+                image = fb.pixels();
+            }
+            (start.elapsed() / FRAMES, image)
+        };
+        let (serial, serial_image) = time(false);
+        let (parallel, parallel_image) = time(true);
+        println!(
+            "gigante gaseoso 800x600, {} triángulos: serie {:?}/frame, paralelo {:?}/frame ({:.2}x)",
+            planet.len() + rings.len(),
+            serial,
+            parallel,
+            serial.as_secs_f64() / parallel.as_secs_f64()
+        );
+        assert_eq!(serial_image, parallel_image);
+    }
+}
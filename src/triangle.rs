@@ -1,42 +1,489 @@
+//! Rasterizador de triángulos.
+//!
+//! Convenciones (todo el pipeline depende de ellas):
+//! - En espacio de vista el ojo está en z = -3 mirando hacia +Z: un vértice con z mayor está
+//!   más lejos y se proyecta más pequeño. Cada vértice pasa a clip space con una matriz
+//!   (`Camera::view_projection` desde el mundo, `RasterOptions::projection` desde la vista),
+//!   se recorta ahí (`clip_triangle`) y se divide entre `w` = `z + 3` (`clip_to_screen`).
+//! - En pantalla X crece a la derecha e Y hacia abajo (Y del mundo hacia arriba).
+//! - Cara frontal = normal `edge1 x edge2` apuntando hacia el ojo: `dot(normal, v0 - ojo) < 0`.
+//!   En pantalla (Y hacia abajo) eso es un triángulo en sentido horario, o sea antihorario
+//!   visto desde +Z. Se compara contra la dirección real ojo -> triángulo (no sólo contra
+//!   z < 0), así el culling sigue siendo correcto en los bordes de la vista y con la cámara
+//!   movida. Ver `is_front_facing`.
 use raylib::prelude::*;
+use std::sync::Arc;
+use crate::camera::{perspective, to_clip, Camera, BASE_EYE, DEFAULT_FAR, DEFAULT_NEAR};
 use crate::framebuffer::Framebuffer;
-use crate::shader::{roca, gas, marciano, arcoiris, panqueques};
+use crate::line::line_depth;
+use crate::shader::{
+    apply_globe_grid, apply_planet_shine, ArcoirisShader, GasShader, MarcianoShader, PanquequesShader,
+    PlanetShader, RocaShader, ToonShader, Uniforms, DEFAULT_LIGHT_DIR,
+};
 
-#[derive(Copy, Clone)]
+/// Los shaders de la demo, como valor `Copy`. Implementa `PlanetShader` (delegando en
+/// `shader()`), así que sirve donde se pide un shader: `&ShaderType::Rocky` o, para un
+/// `Body`, `ShaderType::Rocky` directo. Cualquier otro `PlanetShader` se usa igual, sin
+/// pasar por este enum.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ShaderType {
     Rocky,
     Gas,
     Crystal,
     Lava,
     Ice,
+    Toon,
+}
+
+impl ShaderType {
+    /// La implementación de este shader.
+    pub fn shader(self) -> &'static dyn PlanetShader {
+        match self {
+            ShaderType::Rocky => &RocaShader,
+            ShaderType::Gas => &GasShader,
+            ShaderType::Crystal => &MarcianoShader,
+            ShaderType::Lava => &ArcoirisShader,
+            ShaderType::Ice => &PanquequesShader,
+            ShaderType::Toon => &ToonShader,
+        }
+    }
+}
+
+impl PlanetShader for ShaderType {
+    fn shade(&self, pos: &Vector3, normal: &Vector3, time: f32) -> Color {
+        self.shader().shade(pos, normal, time)
+    }
+    fn shade_with(&self, pos: &Vector3, normal: &Vector3, time: f32, uniforms: &Uniforms) -> Color {
+        self.shader().shade_with(pos, normal, time, uniforms)
+    }
+    fn opacity(&self) -> f32 {
+        self.shader().opacity()
+    }
+    fn name(&self) -> &str {
+        self.shader().name()
+    }
+}
+
+impl From<ShaderType> for Arc<dyn PlanetShader> {
+    fn from(shader: ShaderType) -> Self {
+        Arc::new(shader)
+    }
+}
+
+/// Opacidad del shader de cristal (anillos y el planeta de cristal).
+pub const CRYSTAL_OPACITY: f32 = 0.55;
+
+/// Vistas de depuración que reemplazan al shader del planeta.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DebugView {
+    /// Normal de cara como color (x, y, z -> r, g, b).
+    Normals,
+    /// Gris según la distancia al ojo: blanco en `near`, negro en `far` (como mucho `DEBUG_FAR`).
+    Depth,
+    /// Gris mate con Lambert, para ver la forma sin texturas.
+    Clay,
+}
+
+/// Distancia al ojo que se muestra negra en `DebugView::Depth`.
+pub const DEBUG_FAR: f32 = 6.0;
+
+/// Opciones del rasterizador que no cambian por triángulo.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RasterOptions {
+    /// `false` = interpolación afín en pantalla (look "retro", sin pesos 1/z).
+    pub perspective_correct: bool,
+    /// Área mínima en pantalla (px²) para rasterizar un triángulo; 0 = sin descarte.
+    pub area_cull_threshold: f32,
+    /// Si un triángulo se descarta por área, sombrearlo una vez en su centroide y escribir
+    /// ese único píxel (evita que los cuerpos lejanos parpadeen o desaparezcan).
+    pub splat_culled: bool,
+    /// `Some` = color de depuración en lugar del shader.
+    pub debug_view: Option<DebugView>,
+    /// Sombreado suave: si el triángulo trae normales por vértice (`draw_smooth_triangle`)
+    /// se interpolan por píxel; si no, o con `false`, se usa la normal de la cara.
+    pub smooth: bool,
+    /// Recorte por distancia al ojo (ver `Camera::near`/`far`); junto con los bordes de la
+    /// pantalla forman el frustum contra el que se recorta cada triángulo (`clip_triangle`).
+    pub near: f32,
+    pub far: f32,
+    /// Distancia focal de la proyección (`Camera::focal`; 1 = la proyección original).
+    pub focal: f32,
+    /// `Some(centro)` = el cuerpo es una esfera: la normal de sombreado es la analítica
+    /// (`sphere_normal`) en vez de la de la cara o la interpolada. Sirve para validar la
+    /// iluminación sin que se note el teselado de la malla.
+    pub sphere_center: Option<Vector3>,
+    /// Amplitud (en niveles de 0..255) del ruido que se suma a cada fragmento antes de
+    /// escribirlo; rompe el banding de los degradados dentro de una cara. 0 = sin ruido.
+    pub dither_strength: f32,
+    /// Anillos, planetshine y rejilla de esta escena; `None` = los fijados con
+    /// `set_ring_shadow`/`set_planet_shine`/`set_globe_grid` (ver `Uniforms`).
+    pub uniforms: Option<Uniforms>,
+}
+
+impl Default for RasterOptions {
+    fn default() -> Self {
+        Self {
+            perspective_correct: true,
+            area_cull_threshold: 0.0,
+            splat_culled: false,
+            debug_view: None,
+            smooth: false,
+            near: DEFAULT_NEAR,
+            far: DEFAULT_FAR,
+            focal: 1.0,
+            sphere_center: None,
+            dither_strength: 0.0,
+            uniforms: None,
+        }
+    }
 }
 
-/// Dibuja un triángulo relleno con shading perspectiva-correcto (mejor aproximación)
+impl RasterOptions {
+    /// Matriz de espacio de vista a clip space con `near`/`far`/`focal` (ver `perspective`).
+    pub fn projection(&self) -> Matrix {
+        perspective(self.near, self.far, self.focal)
+    }
+}
+
+/// Vértice listo para recortar: su posición en clip space (con la que se recorta y se
+/// proyecta) y lo que se interpola junto con ella, la posición en espacio de vista (para
+/// sombrear) y la normal.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClipVertex {
+    pub clip: Vector4,
+    pub view: Vector3,
+    pub normal: Vector3,
+}
+
+impl ClipVertex {
+    /// Un vértice en espacio de vista llevado a clip space con `projection`.
+    pub fn new(view: Vector3, normal: Vector3, projection: &Matrix) -> Self {
+        Self { clip: to_clip(view, projection), view, normal }
+    }
+
+    /// El punto a `t` (0..1) de camino hacia `other`. La proyección es afín, así que es el
+    /// mismo punto en clip space y en vista.
+    fn lerp(self, other: Self, t: f32) -> Self {
+        let (a, b) = (self.clip, other.clip);
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        Self {
+            clip: Vector4::new(mix(a.x, b.x), mix(a.y, b.y), mix(a.z, b.z), mix(a.w, b.w)),
+            view: self.view + (other.view - self.view) * t,
+            normal: self.normal + (other.normal - self.normal) * t,
+        }
+    }
+}
+
+/// Triángulo ya proyectado y con culling resuelto: todo lo que el loop de píxeles necesita.
+/// Permite rasterizar el mismo triángulo por regiones (p. ej. por tiles) con resultado idéntico.
+#[derive(Copy, Clone)]
+pub(crate) struct PreparedTriangle<'a> {
+    v0: Vector3,
+    v1: Vector3,
+    v2: Vector3,
+    p0: Vector2,
+    p1: Vector2,
+    p2: Vector2,
+    iz0: f32,
+    iz1: f32,
+    iz2: f32,
+    denom: f32,
+    normal: Vector3,
+    vertex_normals: Option<[Vector3; 3]>, // sólo con `RasterOptions::smooth`
+    pub(crate) min_x: i32,
+    pub(crate) max_x: i32,
+    pub(crate) min_y: i32,
+    pub(crate) max_y: i32,
+    shader: &'a dyn PlanetShader,
+    time: f32,
+    perspective_correct: bool,
+    debug_view: Option<DebugView>,
+    near: f32,
+    far: f32,
+    sphere_center: Option<Vector3>,
+    dither_strength: f32,
+    uniforms: Uniforms, // ya resueltos (los globales se leen una vez por triángulo)
+    tiny: bool, // más chico que `area_cull_threshold`: sólo se escribe el píxel del centroide
+}
+
+/// Dibuja un triángulo relleno (vértices en coordenadas de mundo) visto desde `camera`: lo
+/// lleva a clip space con `Camera::view_projection`, donde se recorta y se divide entre `w`,
+/// y lo sombrea en espacio de vista (`Camera::to_view`). El `near`/`far`/`fov` de la cámara
+/// reemplazan a los de `options`. Con `Camera::default()` es la proyección original
+/// `1/(z+3)`. Ver `draw_filled_view_triangle`.
+#[allow(clippy::too_many_arguments)]
 pub fn draw_filled_triangle(
     framebuffer: &mut Framebuffer,
     v0: Vector3,
     v1: Vector3,
     v2: Vector3,
-    shader_type: ShaderType,
+    camera: &Camera,
+    shader: &dyn PlanetShader,
+    time: f32,
+    options: &RasterOptions,
+) {
+    let options = RasterOptions { near: camera.near, far: camera.far, focal: camera.focal(), ..*options };
+    let view_projection = camera.view_projection();
+    let corners = [v0, v1, v2].map(|v| ClipVertex {
+        clip: to_clip(v, &view_projection),
+        view: if camera.is_identity() { v } else { camera.to_view(v) },
+        normal: Vector3::zero(),
+    });
+    for tri in prepare_clip_space(framebuffer, corners, false, shader, time, &options) {
+        rasterize_region(framebuffer, &tri, tri.min_x, tri.max_x, tri.min_y, tri.max_y);
+    }
+}
+
+/// Dibuja un triángulo relleno ya en espacio de vista (ojo en `BASE_EYE`), con el recorte y
+/// la proyección de `options` (`RasterOptions::projection`), y shading perspectiva-correcto
+/// (mejor aproximación).
+/// Con `options.perspective_correct = false` la posición se interpola de forma afín en pantalla
+/// (sin pesos 1/z), imitando el "warping" de los renderers estilo PlayStation. `shader` puede
+/// ser cualquier `PlanetShader` (uno de `ShaderType` o uno propio).
+pub fn draw_filled_view_triangle(
+    framebuffer: &mut Framebuffer,
+    v0: Vector3,
+    v1: Vector3,
+    v2: Vector3,
+    shader: &dyn PlanetShader,
+    time: f32,
+    options: &RasterOptions,
+) {
+    for tri in prepare_clipped(framebuffer, [v0, v1, v2], None, shader, time, options) {
+        rasterize_region(framebuffer, &tri, tri.min_x, tri.max_x, tri.min_y, tri.max_y);
+    }
+}
+
+/// Como `draw_filled_view_triangle` pero con una normal por vértice (`n0..n2`, en el mismo espacio
+/// que los vértices). Con `options.smooth` las normales se interpolan por píxel (sombreado
+/// suave); sin él se dibuja igual que `draw_filled_view_triangle`.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_smooth_triangle(
+    framebuffer: &mut Framebuffer,
+    v0: Vector3,
+    v1: Vector3,
+    v2: Vector3,
+    n0: Vector3,
+    n1: Vector3,
+    n2: Vector3,
+    shader: &dyn PlanetShader,
+    time: f32,
+    options: &RasterOptions,
+) {
+    let normals = options.smooth.then_some([n0, n1, n2]);
+    for tri in prepare_clipped(framebuffer, [v0, v1, v2], normals, shader, time, options) {
+        rasterize_region(framebuffer, &tri, tri.min_x, tri.max_x, tri.min_y, tri.max_y);
+    }
+}
+
+/// Profundidad que se le resta a las aristas en `draw_wire_on_shaded_triangle`.
+pub const EDGE_DEPTH_BIAS: f32 = 0.01;
+
+/// Relleno + aristas: rasteriza el triángulo y luego dibuja sus tres aristas con
+/// `edge_color` y un bias de profundidad negativo (sin z-fighting, pero ocluidas por caras más cercanas).
+#[allow(clippy::too_many_arguments)]
+pub fn draw_wire_on_shaded_triangle(
+    framebuffer: &mut Framebuffer,
+    v0: Vector3,
+    v1: Vector3,
+    v2: Vector3,
+    shader: &dyn PlanetShader,
     time: f32,
+    options: &RasterOptions,
+    edge_color: Color,
+) {
+    let pieces = prepare_clipped(framebuffer, [v0, v1, v2], None, shader, time, options);
+    for tri in &pieces {
+        rasterize_region(framebuffer, tri, tri.min_x, tri.max_x, tri.min_y, tri.max_y);
+    }
+    if pieces.is_empty() || pieces.iter().all(|t| t.tiny) {
+        return;
+    }
+
+    framebuffer.set_current_color(edge_color);
+    draw_clipped_edges(framebuffer, [v0, v1, v2], options, EDGE_DEPTH_BIAS);
+}
+
+/// Sólo aristas (modo alambre), con profundidad. Mismo culling, recorte y proyección que
+/// `draw_filled_view_triangle`, así las aristas caen justo sobre el relleno del mismo triángulo.
+pub fn draw_wire_triangle(
+    framebuffer: &mut Framebuffer,
+    v0: Vector3,
+    v1: Vector3,
+    v2: Vector3,
+    options: &RasterOptions,
+    color: Color,
 ) {
+    if !is_front_facing(v0, v1, v2) {
+        return;
+    }
+    framebuffer.set_current_color(color);
+    draw_clipped_edges(framebuffer, [v0, v1, v2], options, 0.0);
+}
+
+/// Las aristas del triángulo original (no las que agrega el recorte), recortadas contra el
+/// mismo frustum que el relleno y proyectadas con `screen_position`.
+fn draw_clipped_edges(framebuffer: &mut Framebuffer, v: [Vector3; 3], options: &RasterOptions, bias: f32) {
+    let projection = options.projection();
+    let [v0, v1, v2] = v.map(|p| ClipVertex::new(p, Vector3::zero(), &projection));
+    for (a, b) in [(v0, v1), (v1, v2), (v2, v0)] {
+        if let Some((a, b)) = clip_segment(a, b) {
+            let pa = screen_position(framebuffer, a);
+            let pb = screen_position(framebuffer, b);
+            line_depth(framebuffer, pa, pb, a.view.z, b.view.z, bias);
+        }
+    }
+}
+
+/// Proyección de un vértice a pantalla que usan tanto el relleno como las aristas: un único
+/// lugar, así no pueden separarse ni por un subpíxel.
+fn screen_position(framebuffer: &Framebuffer, v: ClipVertex) -> Vector2 {
+    clip_to_screen(v.clip, framebuffer.width as f32, framebuffer.height as f32)
+}
+
+/// Magnitud mínima de `edge1 x edge2` (2x el área) para considerar un triángulo no degenerado.
+const DEGENERATE_EPSILON: f32 = 1e-10;
+
+/// Descarta caras traseras, lleva los vértices (en espacio de vista) a clip space con
+/// `RasterOptions::projection` y sigue como `prepare_clip_space`. `normals` (las de cada
+/// vértice) se recortan junto con la posición y quedan como `vertex_normals`.
+pub(crate) fn prepare_clipped<'a>(
+    framebuffer: &Framebuffer,
+    v: [Vector3; 3],
+    normals: Option<[Vector3; 3]>,
+    shader: &'a dyn PlanetShader,
+    time: f32,
+    options: &RasterOptions,
+) -> Vec<PreparedTriangle<'a>> {
+    let projection = options.projection();
+    let n = normals.unwrap_or([Vector3::new(0.0, 0.0, 0.0); 3]);
+    let corners = [0, 1, 2].map(|i| ClipVertex::new(v[i], n[i], &projection));
+    prepare_clip_space(framebuffer, corners, normals.is_some(), shader, time, options)
+}
+
+/// Descarta caras traseras, recorta en clip space (`clip_triangle`) y prepara cada triángulo
+/// del abanico resultante. Con `smooth` las normales recortadas quedan como `vertex_normals`.
+fn prepare_clip_space<'a>(
+    framebuffer: &Framebuffer,
+    v: [ClipVertex; 3],
+    smooth: bool,
+    shader: &'a dyn PlanetShader,
+    time: f32,
+    options: &RasterOptions,
+) -> Vec<PreparedTriangle<'a>> {
+    // El culling va antes del recorte: los pedazos son coplanares, no cambiaría nada
+    if !is_front_facing(v[0].view, v[1].view, v[2].view) {
+        return Vec::new();
+    }
+    let polygon = clip_triangle(v);
+
+    let mut out = Vec::with_capacity(polygon.len().saturating_sub(2));
+    for i in 1..polygon.len().saturating_sub(1) {
+        let (a, b, c) = (polygon[0], polygon[i], polygon[i + 1]);
+        if let Some(mut tri) = prepare_triangle(framebuffer, a, b, c, shader, time, options) {
+            if smooth {
+                tri.vertex_normals = Some([a.normal, b.normal, c.normal]);
+            }
+            out.push(tri);
+        }
+    }
+    out
+}
+
+/// Distancia con signo (>= 0 = adentro) de un punto en clip space a cada plano del frustum:
+/// izquierda, derecha, abajo, arriba (|x|, |y| <= w, los bordes de la pantalla), `near` y
+/// `far` (|z| <= w).
+fn frustum_distances(c: Vector4) -> [f32; 6] {
+    [c.w + c.x, c.w - c.x, c.w + c.y, c.w - c.y, c.w + c.z, c.w - c.z]
+}
+
+/// Recorta un triángulo contra los seis planos del frustum en clip space
+/// (Sutherland–Hodgman), antes de dividir entre `w`. La posición en vista y la normal de cada
+/// vértice se interpolan igual que la de clip space. Devuelve el polígono convexo
+/// resultante, en el mismo orden de giro (vacío si queda afuera; si estaba todo adentro, los
+/// mismos tres vértices).
+pub fn clip_triangle(v: [ClipVertex; 3]) -> Vec<ClipVertex> {
+    let mut polygon = v.to_vec();
+    if v.iter().all(|p| frustum_distances(p.clip).iter().all(|&d| d >= 0.0)) {
+        return polygon;
+    }
+
+    for plane in 0..6 {
+        let input = std::mem::take(&mut polygon);
+        for (i, &cur) in input.iter().enumerate() {
+            let prev = input[(i + input.len() - 1) % input.len()];
+            let d_cur = frustum_distances(cur.clip)[plane];
+            let d_prev = frustum_distances(prev.clip)[plane];
+            if (d_cur >= 0.0) != (d_prev >= 0.0) {
+                // La arista cruza el plano: se agrega el punto de corte
+                let t = d_prev / (d_prev - d_cur);
+                polygon.push(prev.lerp(cur, t));
+            }
+            if d_cur >= 0.0 {
+                polygon.push(cur);
+            }
+        }
+        if polygon.len() < 3 {
+            return Vec::new();
+        }
+    }
+    polygon
+}
+
+/// Recorta un segmento contra el frustum (mismos planos que `clip_triangle`). `None` si
+/// queda entero afuera.
+pub fn clip_segment(a: ClipVertex, b: ClipVertex) -> Option<(ClipVertex, ClipVertex)> {
+    let (da, db) = (frustum_distances(a.clip), frustum_distances(b.clip));
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for plane in 0..6 {
+        let (pa, pb) = (da[plane], db[plane]);
+        if pa < 0.0 && pb < 0.0 {
+            return None;
+        }
+        if pa < 0.0 {
+            t0 = t0.max(pa / (pa - pb));
+        } else if pb < 0.0 {
+            t1 = t1.min(pa / (pa - pb));
+        }
+    }
+    (t0 <= t1).then(|| (a.lerp(b, t0), a.lerp(b, t1)))
+}
+
+/// Proyecta, hace backface culling y calcula el bounding box. `None` si el triángulo no se ve.
+/// No recorta: los vértices tienen que estar dentro del frustum (ver `prepare_clip_space`).
+pub(crate) fn prepare_triangle<'a>(
+    framebuffer: &Framebuffer,
+    c0: ClipVertex,
+    c1: ClipVertex,
+    c2: ClipVertex,
+    shader: &'a dyn PlanetShader,
+    time: f32,
+    options: &RasterOptions,
+) -> Option<PreparedTriangle<'a>> {
     let width = framebuffer.width as f32;
     let height = framebuffer.height as f32;
-    let scale = 1.0;
-
-    // Proyección simple perspectiva (igual que antes)
-    let p0 = project(&v0, width, height, scale);
-    let p1 = project(&v1, width, height, scale);
-    let p2 = project(&v2, width, height, scale);
+    let (v0, v1, v2) = (c0.view, c1.view, c2.view);
 
     // Normales por cara (si quieres normales por-vertex, hay que calcular otros datos)
     let edge1 = Vector3::new(v1.x - v0.x, v1.y - v0.y, v1.z - v0.z);
     let edge2 = Vector3::new(v2.x - v0.x, v2.y - v0.y, v2.z - v0.z);
-    let normal = edge1.cross(edge2).normalized();
+    let cross = edge1.cross(edge2);
 
-    // Backface culling: usar componente Z de la normal en cámara simple
-    if normal.z >= 0.0 {
-        return;
+    // Triángulos degenerados (índices repetidos en la cara, aristas casi paralelas):
+    // se descartan antes de proyectar, si no `normalized` y `denom` dan NaN/inf
+    if cross.length() < DEGENERATE_EPSILON {
+        return None;
+    }
+    let normal = cross.normalized();
+
+    // División entre w (la misma que usan las aristas)
+    let p0 = screen_position(framebuffer, c0);
+    let p1 = screen_position(framebuffer, c1);
+    let p2 = screen_position(framebuffer, c2);
+
+    // Backface culling contra la dirección ojo -> triángulo
+    if !faces_camera(normal, v0) {
+        return None;
     }
 
     // Bounding box en pantalla
@@ -45,13 +492,93 @@ pub fn draw_filled_triangle(
     let min_y = p0.y.min(p1.y).min(p2.y).max(0.0) as i32;
     let max_y = p0.y.max(p1.y).max(p2.y).min(height - 1.0) as i32;
 
-    let denom = ((p1.y - p2.y) * (p0.x - p2.x) + (p2.x - p1.x) * (p0.y - p2.y));
-    if denom.abs() < 1e-6 { return; }
+    let denom = (p1.y - p2.y) * (p0.x - p2.x) + (p2.x - p1.x) * (p0.y - p2.y);
+    if denom.abs() < 1e-6 { return None; }
+
+    // Descarte por área en pantalla (denom = 2x área con signo)
+    let tiny = denom.abs() * 0.5 < options.area_cull_threshold;
+    if tiny && !options.splat_culled {
+        return None;
+    }
+
+    // Para interpolación perspectiva-correcta, pesos 1/w con w = distancia al ojo en Z (lo que
+    // divide `clip_to_screen`); el recorte contra near garantiza w > 0
+    let iz0 = 1.0 / c0.clip.w;
+    let iz1 = 1.0 / c1.clip.w;
+    let iz2 = 1.0 / c2.clip.w;
+
+    Some(PreparedTriangle {
+        v0, v1, v2,
+        p0, p1, p2,
+        iz0, iz1, iz2,
+        denom,
+        normal,
+        vertex_normals: None,
+        min_x, max_x, min_y, max_y,
+        shader,
+        time,
+        perspective_correct: options.perspective_correct,
+        debug_view: options.debug_view,
+        near: options.near,
+        far: options.far,
+        sphere_center: options.sphere_center,
+        dither_strength: options.dither_strength,
+        uniforms: options.uniforms.unwrap_or_else(Uniforms::current),
+        tiny,
+    })
+}
+
+/// Dónde escribe `rasterize_region`: el framebuffer completo o una franja suya (ver
+/// `tiled`). Las coordenadas son siempre las de la pantalla completa.
+pub(crate) trait FragmentTarget {
+    /// Profundidad guardada en `(x, y)` (dentro de la pantalla).
+    fn depth(&self, x: i32, y: i32) -> f32;
+    /// Escribe profundidad y color en `(x, y)` (ya pasó la prueba de profundidad).
+    fn write(&mut self, x: i32, y: i32, depth: f32, color: Color);
+}
+
+impl FragmentTarget for Framebuffer {
+    fn depth(&self, x: i32, y: i32) -> f32 {
+        self.z_buffer[(y as u32 * self.width + x as u32) as usize]
+    }
 
-    // Para interpolación perspectiva-correcta, usamos 1/z weights
-    let iz0 = 1.0 / (v0.z + 1e-6);
-    let iz1 = 1.0 / (v1.z + 1e-6);
-    let iz2 = 1.0 / (v2.z + 1e-6);
+    /// Los fragmentos translúcidos (alfa < 255) se mezclan y no escriben profundidad: lo
+    /// que se dibuje después detrás de ellos todavía pasa la prueba.
+    fn write(&mut self, x: i32, y: i32, depth: f32, color: Color) {
+        if color.a == 255 {
+            self.z_buffer[(y as u32 * self.width + x as u32) as usize] = depth;
+        }
+        self.blend_pixel(x, y, color);
+    }
+}
+
+/// Rasteriza la parte del triángulo que cae dentro de `[min_x, max_x] x [min_y, max_y]`
+/// (que ya tiene que estar dentro de la pantalla y de `target`).
+pub(crate) fn rasterize_region<T: FragmentTarget + ?Sized>(
+    target: &mut T,
+    tri: &PreparedTriangle,
+    min_x: i32,
+    max_x: i32,
+    min_y: i32,
+    max_y: i32,
+) {
+    let PreparedTriangle { v0, v1, v2, p0, p1, p2, iz0, iz1, iz2, denom, normal, shader, time, perspective_correct, debug_view, near, far, .. } = *tri;
+
+    if tri.tiny {
+        // Un solo fragmento en el centroide, con su profundidad
+        let c = (p0 + p1 + p2) / 3.0;
+        let (x, y) = (c.x as i32, c.y as i32);
+        if x < min_x || x > max_x || y < min_y || y > max_y || x < 0 || y < 0 {
+            return;
+        }
+        let pos = (v0 + v1 + v2) / 3.0;
+        if pos.z < target.depth(x, y) && pos.z - BASE_EYE.z <= far {
+            let normal = tri.sphere_center.map_or(normal, |c| sphere_normal(pos, c));
+            let color = shade(shader, debug_view, (near, far), &pos, &normal, time, &tri.uniforms);
+            target.write(x, y, pos.z, dither_fragment(color, x, y, tri.dither_strength));
+        }
+        return;
+    }
 
     for y in min_y..=max_y {
         for x in min_x..=max_x {
@@ -66,37 +593,532 @@ pub fn draw_filled_triangle(
             if w0 >= EPSILON && w1 >= EPSILON && w2 >= EPSILON {
                 // Depth interpolación (perspectiva-correcta)
                 let iz = w0 * iz0 + w1 * iz1 + w2 * iz2;
-                let depth = 1.0 / iz;
-
-                let idx = (y as u32 * framebuffer.width + x as u32) as usize;
-                if depth < framebuffer.z_buffer[idx] {
-                    framebuffer.z_buffer[idx] = depth;
-
-                    // Interpolar posición 3D perspectiva-correcta
-                    let px_x = (w0 * v0.x * iz0 + w1 * v1.x * iz1 + w2 * v2.x * iz2) / iz;
-                    let px_y = (w0 * v0.y * iz0 + w1 * v1.y * iz1 + w2 * v2.y * iz2) / iz;
-                    let px_z = (w0 * v0.z * iz0 + w1 * v1.z * iz1 + w2 * v2.z * iz2) / iz;
-                    let pos = Vector3::new(px_x, px_y, px_z);
-
-                    // Aplicar shader según tipo (usamos normal de cara)
-                    let color = match shader_type {
-                        ShaderType::Rocky => roca(&pos, &normal, time),
-                        ShaderType::Gas => gas(&pos, &normal, time),
-                        ShaderType::Crystal => marciano(&pos, &normal, time),
-                        ShaderType::Lava => arcoiris(&pos, &normal, time),
-                        ShaderType::Ice => panqueques(&pos, &normal, time),
+                let depth = 1.0 / iz + BASE_EYE.z;
+
+                if depth < target.depth(x, y) && depth - BASE_EYE.z <= far {
+                    // Pesos de interpolación de atributos: perspectiva-correctos (w_i/z_i
+                    // renormalizados) o afines en pantalla ("retro")
+                    let weights = if perspective_correct {
+                        [w0 * iz0 / iz, w1 * iz1 / iz, w2 * iz2 / iz]
+                    } else {
+                        [w0, w1, w2]
+                    };
+                    let pos = interpolate(weights, v0, v1, v2);
+
+                    // Normal analítica de esfera, la de cara, o interpolada (mismos pesos que la
+                    // posición) si es suave
+                    let normal = match (tri.sphere_center, tri.vertex_normals) {
+                        (Some(c), _) => sphere_normal(pos, c),
+                        (None, Some([n0, n1, n2])) => {
+                            // Mismos pesos que la posición; interpolar normales unitarias las
+                            // acorta, así que se renormaliza
+                            let n = interpolate(weights, n0, n1, n2);
+                            if n.length() > 1e-12 { n.normalized() } else { normal }
+                        }
+                        (None, None) => normal,
                     };
-                    framebuffer.set_pixel_with_color(x, y, color);
+
+                    // Aplicar shader según tipo
+                    let color = shade(shader, debug_view, (near, far), &pos, &normal, time, &tri.uniforms);
+                    target.write(x, y, depth, dither_fragment(color, x, y, tri.dither_strength));
                 }
             }
         }
     }
 }
 
-fn project(v: &Vector3, width: f32, height: f32, scale: f32) -> Vector2 {
-    // Proyección simple: fov dependiente de z para dar sensación de profundidad.
-    let fov = 1.0 / (v.z + 3.0);
-    let x = width / 2.0 + v.x * scale * fov * width / 2.0;
-    let y = height / 2.0 - v.y * scale * fov * height / 2.0;
-    Vector2::new(x, y)
+/// Suma a `color` ruido de ±`strength`/2 niveles según el píxel (interleaved gradient noise:
+/// ruido de alta frecuencia, casi azul, sin tabla ni estado). Con `strength` = 0 no hace nada.
+fn dither_fragment(color: Color, x: i32, y: i32, strength: f32) -> Color {
+    if strength <= 0.0 {
+        return color;
+    }
+    let noise = (52.982_918 * (0.067_110_56 * x as f32 + 0.005_837_15 * y as f32).fract()).fract();
+    let offset = (noise - 0.5) * strength;
+    let add = |v: u8| (v as f32 + offset).round().clamp(0.0, 255.0) as u8;
+    Color::new(add(color.r), add(color.g), add(color.b), color.a)
+}
+
+/// Combinación de un atributo por vértice con los pesos de `rasterize_region`. Posición y
+/// normal pasan por aquí para que usen siempre la misma interpolación.
+fn interpolate([w0, w1, w2]: [f32; 3], a: Vector3, b: Vector3, c: Vector3) -> Vector3 {
+    a * w0 + b * w1 + c * w2
+}
+
+/// Normal exacta de una esfera centrada en `center` en el punto `pos` de su superficie
+/// (para una esfera unitaria en el origen es la posición misma).
+pub fn sphere_normal(pos: Vector3, center: Vector3) -> Vector3 {
+    let d = pos - center;
+    if d.length() > 1e-12 { d.normalized() } else { Vector3::new(0.0, 0.0, -1.0) }
+}
+
+/// Evalúa el shader del tipo indicado en un punto (o la vista de depuración, si hay).
+fn shade(
+    shader: &dyn PlanetShader,
+    debug_view: Option<DebugView>,
+    depth_range: (f32, f32),
+    pos: &Vector3,
+    normal: &Vector3,
+    time: f32,
+    uniforms: &Uniforms,
+) -> Color {
+    if let Some(view) = debug_view {
+        return debug_color(view, depth_range, pos, normal, uniforms);
+    }
+    let color = shader.shade_with(pos, normal, time, uniforms);
+    let color = apply_planet_shine(color, pos, normal, uniforms.planet_shine.as_ref());
+    let color = apply_globe_grid(color, pos, uniforms.globe_grid.as_ref());
+    Color { a: (shader.opacity().clamp(0.0, 1.0) * 255.0).round() as u8, ..color }
+}
+
+/// Color promedio de un shader sobre el hemisferio de una esfera unitaria que mira a
+/// `light_dir` (el "albedo" aproximado del planeta visto de día), con `samples` puntos
+/// repartidos en espiral de Fibonacci.
+pub fn average_lit_color(shader: &dyn PlanetShader, light_dir: Vector3, time: f32, samples: u32) -> Color {
+    let light_dir = light_dir.normalized();
+    let golden = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+    let (mut sum, mut count) = (Vector3::new(0.0, 0.0, 0.0), 0);
+    for i in 0..samples {
+        let y = 1.0 - 2.0 * (i as f32 + 0.5) / samples as f32;
+        let r = (1.0 - y * y).max(0.0).sqrt();
+        let theta = golden * i as f32;
+        let p = Vector3::new(r * theta.cos(), y, r * theta.sin());
+        if p.dot(light_dir) <= 0.0 {
+            continue;
+        }
+        let c = shader.shade(&p, &p, time);
+        sum += Vector3::new(c.r as f32, c.g as f32, c.b as f32);
+        count += 1;
+    }
+    if count == 0 {
+        return Color::new(0, 0, 0, 255);
+    }
+    let avg = sum / count as f32;
+    Color::new(avg.x as u8, avg.y as u8, avg.z as u8, 255)
+}
+
+fn debug_color(view: DebugView, (near, far): (f32, f32), pos: &Vector3, normal: &Vector3, uniforms: &Uniforms) -> Color {
+    let to_u8 = |v: f32| (v.clamp(0.0, 1.0) * 255.0) as u8;
+    match view {
+        DebugView::Normals => Color::new(
+            to_u8(normal.x * 0.5 + 0.5),
+            to_u8(normal.y * 0.5 + 0.5),
+            to_u8(normal.z * 0.5 + 0.5),
+            255,
+        ),
+        DebugView::Depth => {
+            // De `near` (blanco) a `far` o `DEBUG_FAR`, lo que esté más cerca (negro)
+            let far = far.min(DEBUG_FAR).max(near + 1e-3);
+            let g = to_u8(1.0 - (pos.z - BASE_EYE.z - near) / (far - near));
+            Color::new(g, g, g, 255)
+        }
+        DebugView::Clay => {
+            // Misma luz principal que los shaders
+            let light_dir = uniforms.key_light_dir(DEFAULT_LIGHT_DIR);
+            let g = to_u8(0.15 + 0.75 * normal.dot(light_dir).max(0.0));
+            Color::new(g, g, (g as f32 * 0.95) as u8, 255)
+        }
+    }
+}
+
+/// `true` si el triángulo da la cara a la cámara (ver convenciones del módulo).
+pub fn is_front_facing(v0: Vector3, v1: Vector3, v2: Vector3) -> bool {
+    faces_camera((v1 - v0).cross(v2 - v0), v0)
+}
+
+/// `normal` es la del plano del triángulo y `point` cualquier vértice suyo (en espacio de vista).
+fn faces_camera(normal: Vector3, point: Vector3) -> bool {
+    normal.dot(point - BASE_EYE) < 0.0
+}
+
+/// Proyecta un punto (en espacio de vista) a coordenadas de pantalla igual que el
+/// rasterizador con la cámara por defecto.
+pub fn project_point(v: Vector3, width: u32, height: u32) -> Vector2 {
+    project_point_focal(v, width, height, 1.0)
+}
+
+/// Como `project_point` con distancia focal `focal` (ver `Camera::focal`).
+pub fn project_point_focal(v: Vector3, width: u32, height: u32, focal: f32) -> Vector2 {
+    let projection = perspective(DEFAULT_NEAR, DEFAULT_FAR, focal);
+    clip_to_screen(to_clip(v, &projection), width as f32, height as f32)
+}
+
+/// Posición en pantalla de un punto en clip space: la división entre `w` da las NDC
+/// ([-1, 1] de borde a borde) y de ahí a píxeles, con Y hacia abajo.
+pub fn clip_to_screen(c: Vector4, width: f32, height: f32) -> Vector2 {
+    let (x, y) = (c.x / c.w, c.y / c.w);
+    Vector2::new(width / 2.0 + x * width / 2.0, height / 2.0 - y * height / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shader::lock_uniforms;
+
+    /// Triángulo frontal muy inclinado: el vértice de arriba está 2 unidades más lejos.
+    fn steep_triangle() -> [Vector3; 3] {
+        [Vector3::new(-1.0, -1.0, 0.0), Vector3::new(0.0, 1.0, 2.0), Vector3::new(1.0, -1.0, 0.0)]
+    }
+
+    /// Colores y profundidades tras dibujar `v` en un framebuffer de 64x64.
+    fn render(v: [Vector3; 3], options: &RasterOptions) -> (Vec<Color>, Vec<f32>) {
+        let mut fb = Framebuffer::new(64, 64, Color::BLACK);
+        draw_filled_view_triangle(&mut fb, v[0], v[1], v[2], &ShaderType::Rocky, 0.0, options);
+        (fb.pixels(), fb.z_buffer.clone())
+    }
+
+    #[test]
+    fn affine_differs_from_perspective_on_steep_triangle() {
+        let perspective = RasterOptions { debug_view: Some(DebugView::Depth), ..RasterOptions::default() };
+        let affine = RasterOptions { perspective_correct: false, ..perspective };
+        let (color_p, z_p) = render(steep_triangle(), &perspective);
+        let (color_a, z_a) = render(steep_triangle(), &affine);
+
+        // La profundidad es la misma (siempre 1/z); lo que cambia es la posición interpolada
+        assert_eq!(z_p, z_a);
+        assert!(z_p.iter().any(|z| z.is_finite()));
+        assert!(color_p.iter().zip(&color_a).any(|(p, a)| p != a));
+    }
+
+    #[test]
+    fn dither_strength_adds_levels_to_a_smooth_gradient() {
+        // La vista de profundidad de un triángulo inclinado es un degradado gris en bandas
+        let distinct = |dither_strength: f32| {
+            let options = RasterOptions { debug_view: Some(DebugView::Depth), dither_strength, ..RasterOptions::default() };
+            let (color, _) = render(steep_triangle(), &options);
+            let mut lit: Vec<(u8, u8, u8)> = color.iter().filter(|&&c| c != Color::BLACK).map(|c| (c.r, c.g, c.b)).collect();
+            lit.sort_unstable();
+            lit.dedup();
+            lit.len()
+        };
+        let (plain, dithered) = (distinct(0.0), distinct(4.0));
+        assert!(plain > 1);
+        assert!(dithered > plain, "{plain} -> {dithered} colores");
+    }
+
+    #[test]
+    fn smooth_normals_use_the_perspective_weights() {
+        // Normales de vértice = ejes: la normal interpolada (normalizada) da la proporción de
+        // los pesos de cada vértice, y con ellos la z que se interpoló en ese píxel
+        let v = [Vector3::new(-1.0, -1.0, 0.2), Vector3::new(0.0, 1.0, 3.0), Vector3::new(1.0, -1.0, 0.2)];
+        let n = [Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0)];
+        let z_from_normals = |perspective_correct: bool| {
+            let options = RasterOptions { debug_view: Some(DebugView::Normals), smooth: true, perspective_correct, ..RasterOptions::default() };
+            let mut fb = Framebuffer::new(64, 64, Color::BLACK);
+            draw_smooth_triangle(&mut fb, v[0], v[1], v[2], n[0], n[1], n[2], &ShaderType::Rocky, 0.0, &options);
+            let weight = |c: u8| (c as f32 / 255.0 * 2.0 - 1.0).max(0.0);
+            let z: Vec<Option<f32>> = fb
+                .pixels()
+                .iter()
+                .map(|&c| {
+                    let w = [weight(c.r), weight(c.g), weight(c.b)];
+                    let sum = w[0] + w[1] + w[2];
+                    (c != Color::BLACK).then(|| (w[0] * v[0].z + w[1] * v[1].z + w[2] * v[2].z) / sum)
+                })
+                .collect();
+            (z, fb.z_buffer.clone())
+        };
+
+        // La profundidad no depende del modo; la z de la normal perspectiva coincide con ella
+        let (perspective, depth) = z_from_normals(true);
+        let (affine, _) = z_from_normals(false);
+        let error = |zs: &[Option<f32>]| {
+            zs.iter().zip(&depth).filter_map(|(z, d)| Some((z.as_ref()? - d).abs())).fold(0.0f32, f32::max)
+        };
+        assert!(perspective.iter().filter(|z| z.is_some()).count() > 100);
+        assert!(error(&perspective) < 0.1, "perspectiva: {}", error(&perspective));
+        assert!(error(&affine) > 0.3, "afín: {}", error(&affine));
+    }
+
+    #[test]
+    fn wire_on_shaded_edges_are_occluded_by_nearer_geometry() {
+        let options = RasterOptions { debug_view: Some(DebugView::Normals), ..RasterOptions::default() };
+        let far = [Vector3::new(-2.0, -2.0, 2.0), Vector3::new(0.0, 2.0, 2.0), Vector3::new(2.0, -2.0, 2.0)];
+        let near = [Vector3::new(-1.5, -2.0, 0.0), Vector3::new(0.0, 1.0, 0.0), Vector3::new(1.5, -2.0, 0.0)];
+        let draw = |occluder: bool| {
+            let mut fb = Framebuffer::new(64, 64, Color::BLACK);
+            if occluder {
+                draw_filled_view_triangle(&mut fb, near[0], near[1], near[2], &ShaderType::Rocky, 0.0, &options);
+            }
+            draw_wire_on_shaded_triangle(&mut fb, far[0], far[1], far[2], &ShaderType::Rocky, 0.0, &options, Color::RED);
+            fb.pixels()
+        };
+        let covered = render(near, &options).0;
+        let (alone, occluded) = (draw(false), draw(true));
+
+        let is_edge = |c: &Color| *c == Color::RED;
+        let hidden: Vec<usize> = (0..alone.len()).filter(|&i| is_edge(&alone[i]) && covered[i] != Color::BLACK).collect();
+        let visible: Vec<usize> = (0..alone.len()).filter(|&i| is_edge(&alone[i]) && covered[i] == Color::BLACK).collect();
+        assert!(!hidden.is_empty() && !visible.is_empty());
+        assert!(hidden.iter().all(|&i| !is_edge(&occluded[i])));
+        assert!(visible.iter().all(|&i| is_edge(&occluded[i])));
+    }
+
+    #[test]
+    fn wire_depth_matches_the_fill_on_an_edge_straddling_z0() {
+        let _uniforms = lock_uniforms();
+        // Inclinado: las aristas que llegan al vértice de arriba cruzan z = 0
+        let v = [Vector3::new(-1.0, -1.0, -1.0), Vector3::new(0.0, 1.0, 1.0), Vector3::new(1.0, -1.0, -1.0)];
+        let (_, filled) = render(v, &RasterOptions::default());
+        let mut wire = Framebuffer::new(64, 64, Color::BLACK);
+        draw_wire_triangle(&mut wire, v[0], v[1], v[2], &RasterOptions::default(), Color::RED);
+
+        let mut compared = 0;
+        for (&edge, &fill) in wire.z_buffer.iter().zip(&filled) {
+            if edge.is_infinite() {
+                continue;
+            }
+            assert!((-1.0 - 1e-4..=1.0 + 1e-4).contains(&edge), "profundidad de arista {edge}");
+            if fill.is_finite() {
+                assert!((edge - fill).abs() < 0.1, "arista {edge} != relleno {fill}");
+                compared += 1;
+            }
+        }
+        assert!(compared > 20, "{compared}");
+    }
+
+    #[test]
+    fn duplicated_index_face_skips_degenerate_triangle() {
+        let _uniforms = lock_uniforms();
+        let vertices = [Vector3::new(-1.0, -1.0, 0.0), Vector3::new(0.0, 1.0, 0.0), Vector3::new(1.0, -1.0, 0.0)];
+        let face = [0, 1, 1, 2]; // el índice 1 repetido
+        let mut fb = Framebuffer::new(64, 64, Color::BLACK);
+        let options = RasterOptions::default();
+
+        let fan: Vec<[Vector3; 3]> = (1..face.len() - 1).map(|i| [vertices[face[0]], vertices[face[i]], vertices[face[i + 1]]]).collect();
+        assert!(prepare_clipped(&fb, fan[0], None, &ShaderType::Rocky, 0.0, &options).is_empty());
+        for [a, b, c] in fan {
+            draw_filled_view_triangle(&mut fb, a, b, c, &ShaderType::Rocky, 0.0, &options);
+        }
+        assert!(fb.z_buffer.iter().all(|z| !z.is_nan()));
+        assert!(fb.z_buffer.iter().any(|z| z.is_finite()));
+    }
+
+    /// Antihorario visto desde +Z (detrás de la escena), o sea horario en pantalla: la cara
+    /// frontal de la convención del módulo.
+    fn front_triangle(z: f32) -> [Vector3; 3] {
+        [Vector3::new(-1.0, -1.0, z), Vector3::new(0.0, 1.0, z), Vector3::new(1.0, -1.0, z)]
+    }
+
+    #[test]
+    fn front_facing_winding_renders_and_reverse_is_culled() {
+        let [a, b, c] = front_triangle(0.0);
+        assert!(is_front_facing(a, b, c));
+        assert!(!is_front_facing(a, c, b));
+
+        let options = RasterOptions { debug_view: Some(DebugView::Normals), ..RasterOptions::default() };
+        let (front, _) = render([a, b, c], &options);
+        let (back, _) = render([a, c, b], &options);
+        assert!(front.iter().any(|&px| px != Color::BLACK));
+        assert!(back.iter().all(|&px| px == Color::BLACK));
+    }
+
+    /// `clip_triangle` de tres vértices en espacio de vista, proyectados con `near`/`far` y
+    /// focal 1. Devuelve las posiciones en vista del polígono recortado.
+    fn clip_view(v: [Vector3; 3], near: f32, far: f32) -> Vec<Vector3> {
+        let projection = perspective(near, far, 1.0);
+        let corners = v.map(|p| ClipVertex::new(p, Vector3::new(0.0, 0.0, -1.0), &projection));
+        clip_triangle(corners).iter().map(|c| c.view).collect()
+    }
+
+    #[test]
+    fn vertex_beyond_far_is_clipped_and_in_range_ones_are_kept() {
+        let (near, far) = (0.1, 5.0);
+        let inside = [Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.3, 0.0, 0.5), Vector3::new(0.0, 0.3, 0.5)];
+        assert_eq!(clip_view(inside, near, far), inside);
+
+        // El tercer vértice queda a 13 del ojo: se recorta en el plano far
+        let beyond = Vector3::new(0.0, 0.3, 10.0);
+        let clipped = clip_view([inside[0], inside[1], beyond], near, far);
+        assert!(!clipped.is_empty());
+        assert!(clipped.iter().all(|p| p.z - BASE_EYE.z <= far + 1e-4), "{clipped:?}");
+        assert!(clipped.iter().all(|p| *p != beyond));
+        assert!(clipped.contains(&inside[0]) && clipped.contains(&inside[1]));
+        assert!(clipped.iter().any(|p| (p.z - BASE_EYE.z - far).abs() < 1e-4));
+    }
+
+    #[test]
+    fn analytic_normal_is_the_position_on_a_unit_sphere() {
+        let sphere = crate::geometria::generate_icosphere(1.0, 2);
+        let center = Vector3::new(0.5, -1.0, 4.0);
+        for &p in &sphere.vertices {
+            assert!((p.length() - 1.0).abs() < 1e-5);
+            assert!((sphere_normal(p, Vector3::zero()) - p).length() < 1e-5);
+            assert!((sphere_normal(center + p, center) - p).length() < 1e-5);
+        }
+        // Puntos fuera de la superficie dan igual la dirección radial, unitaria
+        let n = sphere_normal(Vector3::new(0.0, 3.0, 4.0), Vector3::zero());
+        assert!((n - Vector3::new(0.0, 0.6, 0.8)).length() < 1e-6);
+    }
+
+    #[test]
+    fn triangle_straddling_z0_gets_the_depth_of_its_plane() {
+        let _uniforms = lock_uniforms();
+        let v = [Vector3::new(-2.0, -2.0, -1.0), Vector3::new(0.0, 2.0, 1.0), Vector3::new(2.0, -2.0, -1.0)];
+        let (_, depth) = render(v, &RasterOptions::default());
+        let normal = (v[1] - v[0]).cross(v[2] - v[0]);
+        let mut covered = 0;
+        for (i, &z) in depth.iter().enumerate() {
+            if z.is_infinite() {
+                continue;
+            }
+            // Rayo del ojo por el centro del píxel (inversa de `project_point`)
+            let (px, py) = ((i % 64) as f32 + 0.5, (i / 64) as f32 + 0.5);
+            let dir = Vector3::new((px - 32.0) / 32.0, (32.0 - py) / 32.0, 1.0);
+            let t = (v[0] - BASE_EYE).dot(normal) / dir.dot(normal);
+            let expected = BASE_EYE.z + t;
+            assert!((z - expected).abs() < 1e-3, "píxel {i}: {z} != {expected}");
+            covered += 1;
+        }
+        assert!(covered > 200, "{covered}");
+    }
+
+    #[test]
+    fn triangle_past_two_planes_is_clipped_inside_the_frustum() {
+        let (near, far) = (0.1, 6.0);
+        // Se sale por la izquierda (x < -d) y por detrás del plano far
+        let v = [Vector3::new(-8.0, 0.0, 1.0), Vector3::new(0.0, 0.5, 9.0), Vector3::new(0.5, -0.5, 1.0)];
+        let projection = perspective(near, far, 1.0);
+        let clipped = clip_triangle(v.map(|p| ClipVertex::new(p, Vector3::zero(), &projection)));
+        assert!(clipped.len() >= 4, "{clipped:?}");
+        for c in &clipped {
+            assert!(frustum_distances(c.clip).iter().all(|&d| d >= -1e-4), "{c:?}");
+            // La posición en vista sigue a la de clip space
+            assert!((to_clip(c.view, &projection).w - c.clip.w).abs() < 1e-4, "{c:?}");
+        }
+        // El vértice que ya estaba adentro se conserva
+        assert!(clipped.iter().any(|c| c.view == v[2]));
+    }
+
+    #[test]
+    fn vertex_behind_the_camera_is_clipped_without_nan() {
+        let near = RasterOptions::default().near;
+        // El vértice de arriba queda 2 unidades detrás del ojo (z = -5)
+        let v = [Vector3::new(-1.0, -1.0, 0.0), Vector3::new(0.0, 1.0, -5.0), Vector3::new(1.0, -1.0, 0.0)];
+        let clipped = clip_view(v, near, DEFAULT_FAR);
+        assert!(clipped.len() >= 3);
+        for p in &clipped {
+            assert!(p.x.is_finite() && p.y.is_finite() && p.z.is_finite());
+            assert!(p.z - BASE_EYE.z >= near - 1e-5, "{p:?}");
+        }
+
+        let (colors, depth) = render(v, &RasterOptions { debug_view: Some(DebugView::Normals), ..RasterOptions::default() });
+        assert!(colors.iter().any(|&c| c != Color::BLACK));
+        assert!(depth.iter().all(|z| !z.is_nan()));
+        assert!(depth.iter().filter(|z| z.is_finite()).all(|&z| z - BASE_EYE.z >= near - 1e-4));
+    }
+
+    #[test]
+    fn wire_endpoints_land_on_the_filled_vertex_pixels() {
+        // Con z = 1 (distancia 4) cada unidad son 8 px: vértices justo en centros de píxel
+        let at = |px: f32, py: f32| Vector3::new((px - 32.0) / 8.0, (32.0 - py) / 8.0, 1.0);
+        let v = [at(16.5, 48.5), at(32.5, 12.5), at(48.5, 48.5)];
+        let (filled, _) = render(v, &RasterOptions { debug_view: Some(DebugView::Normals), ..RasterOptions::default() });
+        let mut wire = Framebuffer::new(64, 64, Color::BLACK);
+        draw_wire_triangle(&mut wire, v[0], v[1], v[2], &RasterOptions::default(), Color::RED);
+        let wire = wire.pixels();
+
+        for (px, py) in [(16, 48), (32, 12), (48, 48)] {
+            let i = py * 64 + px;
+            assert_ne!(filled[i], Color::BLACK, "relleno en ({px}, {py})");
+            assert_eq!(wire[i], Color::RED, "arista en ({px}, {py})");
+        }
+        // Ninguna arista se separa del relleno más de un píxel
+        let near_fill = |x: i32, y: i32| {
+            (-1..=1).any(|dy| (-1..=1).any(|dx| {
+                let (x, y) = (x + dx, y + dy);
+                (0..64).contains(&x) && (0..64).contains(&y) && filled[(y * 64 + x) as usize] != Color::BLACK
+            }))
+        };
+        for (i, _) in wire.iter().enumerate().filter(|(_, c)| **c == Color::RED) {
+            assert!(near_fill(i as i32 % 64, i as i32 / 64), "arista suelta en {i}");
+        }
+    }
+
+    #[test]
+    fn culling_follows_the_camera_around_the_model() {
+        use crate::camera::{Camera, OrbitCamera};
+        // Mira hacia -Z: de frente para la cámara fija
+        let world = front_triangle(0.0);
+        let mut camera = Camera::default();
+        for step in 0..16 {
+            let orbit = OrbitCamera { azimuth: step as f32 * std::f32::consts::TAU / 16.0 + 0.1, ..OrbitCamera::default() };
+            orbit.apply(&mut camera);
+            let [a, b, c] = world.map(|v| camera.to_view(v));
+            assert_eq!(is_front_facing(a, b, c), camera.eye().z < 0.0, "azimut {}", orbit.azimuth);
+        }
+
+        // Al borde de la vista: normal con z = 0 (hacia +X) pero el ojo la ve de frente
+        let (a, b, c) = (Vector3::new(-2.0, -1.0, 0.0), Vector3::new(-2.0, 1.0, 0.0), Vector3::new(-2.0, 0.0, 1.0));
+        assert_eq!((b - a).cross(c - a).z, 0.0);
+        assert!(is_front_facing(a, b, c));
+        assert!(!is_front_facing(a, c, b));
+    }
+
+    #[test]
+    fn filled_triangle_is_drawn_through_the_camera() {
+        let options = RasterOptions { debug_view: Some(DebugView::Normals), ..RasterOptions::default() };
+        let [a, b, c] = front_triangle(0.0);
+        let draw = |camera: &Camera| {
+            let mut fb = Framebuffer::new(64, 64, Color::BLACK);
+            draw_filled_triangle(&mut fb, a, b, c, camera, &ShaderType::Rocky, 0.0, &options);
+            fb.pixels()
+        };
+        let covered = |pixels: &[Color]| pixels.iter().filter(|&&c| c != Color::BLACK).count();
+
+        // La cámara por defecto es la proyección original
+        let fixed = draw(&Camera::default());
+        assert_eq!(fixed, render([a, b, c], &options).0);
+        // Menos fov: el mismo triángulo se ve más grande
+        assert!(covered(&draw(&Camera { fov: 0.8, ..Camera::default() })) > 2 * covered(&fixed));
+        // El `far` de la cámara reemplaza al de `options`: a distancia 3 ya no se dibuja
+        assert_eq!(covered(&draw(&Camera { far: 2.0, ..Camera::default() })), 0);
+        // Desde un costado, mirando al origen: el centro de la pantalla sigue cubierto
+        let side = Camera { position: Vector3::new(1.5, 0.5, -2.0), ..Camera::default() };
+        let seen = draw(&side);
+        assert_ne!(seen[32 * 64 + 32], Color::BLACK);
+        assert_ne!(seen, fixed);
+    }
+
+    #[test]
+    fn origin_projects_to_screen_center_and_farther_is_smaller() {
+        assert_eq!(project_point(Vector3::zero(), 64, 48), Vector2::new(32.0, 24.0));
+
+        let near = project_point(Vector3::new(1.0, 1.0, 0.0), 64, 48);
+        let far = project_point(Vector3::new(1.0, 1.0, 3.0), 64, 48);
+        assert!(far.x - 32.0 < near.x - 32.0);
+        assert!(24.0 - far.y < 24.0 - near.y);
+        // +X a la derecha, +Y hacia arriba en pantalla
+        assert!(near.x > 32.0 && near.y < 24.0);
+    }
+
+    #[test]
+    fn nearer_fragment_wins_depth_test() {
+        let options = RasterOptions { debug_view: Some(DebugView::Depth), ..RasterOptions::default() };
+        let (near, far) = (front_triangle(0.0), front_triangle(1.0));
+        let center = (32 * 64 + 32) as usize;
+
+        let (only_near, _) = render(near, &options);
+        for order in [[far, near], [near, far]] {
+            let mut fb = Framebuffer::new(64, 64, Color::BLACK);
+            for [a, b, c] in order {
+                draw_filled_view_triangle(&mut fb, a, b, c, &ShaderType::Rocky, 0.0, &options);
+            }
+            assert_eq!(fb.pixels()[center], only_near[center]);
+            assert!(fb.z_buffer[center].abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn sub_threshold_triangles_are_rejected() {
+        let options = RasterOptions { area_cull_threshold: 20.0, debug_view: Some(DebugView::Normals), ..RasterOptions::default() };
+        let drawn = |v: [Vector3; 3], options: &RasterOptions| render(v, options).0.iter().filter(|&&c| c != Color::BLACK).count();
+        let small = front_triangle(0.0).map(|v| v * 0.1);
+
+        assert_eq!(drawn(small, &options), 0);
+        assert!(drawn(small, &RasterOptions { area_cull_threshold: 0.0, ..options }) > 0);
+        assert!(drawn(front_triangle(0.0), &options) > 20);
+        // Con `splat_culled` el descartado deja un solo píxel
+        assert_eq!(drawn(small, &RasterOptions { splat_culled: true, ..options }), 1);
+    }
 }